@@ -0,0 +1,7 @@
+-- Deleting a webhook should also remove its delivery history
+ALTER TABLE webhook_deliveries
+    DROP CONSTRAINT IF EXISTS webhook_deliveries_webhook_id_fkey;
+
+ALTER TABLE webhook_deliveries
+    ADD CONSTRAINT webhook_deliveries_webhook_id_fkey
+    FOREIGN KEY (webhook_id) REFERENCES webhooks(id) ON DELETE CASCADE;
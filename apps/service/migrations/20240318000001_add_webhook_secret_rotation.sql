@@ -0,0 +1,3 @@
+-- Keep the previous webhook secret around while receivers switch to a rotated one
+ALTER TABLE webhooks ADD COLUMN IF NOT EXISTS previous_secret VARCHAR(255);
+ALTER TABLE webhooks ADD COLUMN IF NOT EXISTS previous_secret_expires_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,7 @@
+-- Crawl state of paused jobs so they can be resumed on any worker
+CREATE TABLE IF NOT EXISTS job_checkpoints (
+    job_id UUID PRIMARY KEY REFERENCES jobs(id) ON DELETE CASCADE,
+    frontier JSONB NOT NULL,
+    pages_processed INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Track how often a job was claimed and when its worker last reported in, so
+-- jobs of crashed workers can be recovered
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS heartbeat_at TIMESTAMP WITH TIME ZONE;
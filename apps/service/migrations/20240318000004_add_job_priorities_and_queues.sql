@@ -0,0 +1,6 @@
+-- Jobs are routed to named queues and ordered by priority within a queue
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS priority VARCHAR(10) NOT NULL DEFAULT 'normal';
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS queue_name VARCHAR(100) NOT NULL DEFAULT 'scraper_jobs';
+ALTER TABLE scraper_configs ADD COLUMN IF NOT EXISTS queue_name VARCHAR(100);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_queue_name ON jobs(queue_name);
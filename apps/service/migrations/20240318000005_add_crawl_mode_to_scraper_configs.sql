@@ -0,0 +1,2 @@
+-- Configs can seed their crawls from sitemaps instead of, or as well as, following links
+ALTER TABLE scraper_configs ADD COLUMN IF NOT EXISTS crawl_mode VARCHAR(20) NOT NULL DEFAULT 'links';
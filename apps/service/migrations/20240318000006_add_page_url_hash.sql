@@ -0,0 +1,8 @@
+-- Crawls of the same URL are linked by the SHA-256 of its normalized form, which is used in version URLs
+ALTER TABLE pages ADD COLUMN IF NOT EXISTS url_hash VARCHAR(64);
+
+UPDATE pages SET url_hash = encode(sha256(convert_to(normalized_url, 'UTF8')), 'hex') WHERE url_hash IS NULL;
+
+ALTER TABLE pages ALTER COLUMN url_hash SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_pages_url_hash_crawled_at ON pages(url_hash, crawled_at);
@@ -0,0 +1,6 @@
+-- Pages can be documents such as PDFs, which are stored as downloaded next to their Markdown rendition
+ALTER TABLE pages ADD COLUMN IF NOT EXISTS content_type VARCHAR(255);
+ALTER TABLE pages ADD COLUMN IF NOT EXISTS document_storage_path VARCHAR(1024);
+
+-- Only HTML was crawled so far
+UPDATE pages SET content_type = 'text/html' WHERE content_type IS NULL AND error_message IS NULL;
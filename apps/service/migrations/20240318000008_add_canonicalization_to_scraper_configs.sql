@@ -0,0 +1,2 @@
+-- Rules for rewriting the URLs of a config's crawls, missing rules take their defaults
+ALTER TABLE scraper_configs ADD COLUMN IF NOT EXISTS canonicalization JSONB NOT NULL DEFAULT '{}'::jsonb;
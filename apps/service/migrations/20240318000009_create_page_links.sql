@@ -0,0 +1,12 @@
+-- Links found on each page, with the anchor text they were found with
+CREATE TABLE IF NOT EXISTS page_links (
+    page_id UUID NOT NULL REFERENCES pages(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    anchor_text TEXT,
+    element VARCHAR(20) NOT NULL,
+    nofollow BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (page_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS idx_page_links_url ON page_links(url);
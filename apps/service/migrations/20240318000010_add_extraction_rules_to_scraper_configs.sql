@@ -0,0 +1,2 @@
+-- Rules extracting fields such as the court or decision date of a page into its metadata
+ALTER TABLE scraper_configs ADD COLUMN IF NOT EXISTS extraction_rules JSONB NOT NULL DEFAULT '[]'::jsonb;
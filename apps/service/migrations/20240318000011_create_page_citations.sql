@@ -0,0 +1,13 @@
+-- Statute, regulation and case citations found in the text of each page
+CREATE TABLE IF NOT EXISTS page_citations (
+    page_id UUID NOT NULL REFERENCES pages(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    kind VARCHAR(20) NOT NULL,
+    citation TEXT NOT NULL,
+    normalized TEXT NOT NULL,
+    occurrences INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (page_id, position)
+);
+
+-- text_pattern_ops also serves the prefix matches that find subsections of a cited section
+CREATE INDEX IF NOT EXISTS idx_page_citations_normalized ON page_citations(normalized text_pattern_ops);
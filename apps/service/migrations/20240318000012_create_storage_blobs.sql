@@ -0,0 +1,30 @@
+-- Stored objects with the number of page columns referring to them, so an object is only deleted once nothing uses it
+CREATE TABLE IF NOT EXISTS storage_blobs (
+    path TEXT PRIMARY KEY,
+    -- Hex SHA-256 of the content, NULL for objects stored under a job and URL before content addressing
+    sha256 CHAR(64),
+    size_bytes BIGINT,
+    ref_count INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_referenced_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_storage_blobs_unreferenced ON storage_blobs(last_referenced_at) WHERE ref_count = 0;
+CREATE INDEX IF NOT EXISTS idx_storage_blobs_legacy ON storage_blobs(created_at) WHERE sha256 IS NULL;
+
+-- Register the objects stored so far, with one reference per page column holding their path
+INSERT INTO storage_blobs (path, ref_count)
+SELECT path, COUNT(*)
+FROM (
+    SELECT html_storage_path AS path FROM pages WHERE html_storage_path IS NOT NULL
+    UNION ALL
+    SELECT markdown_storage_path FROM pages WHERE markdown_storage_path IS NOT NULL
+    UNION ALL
+    SELECT document_storage_path FROM pages WHERE document_storage_path IS NOT NULL
+) refs
+GROUP BY path
+ON CONFLICT (path) DO NOTHING;
+
+CREATE INDEX IF NOT EXISTS idx_pages_html_storage_path ON pages(html_storage_path);
+CREATE INDEX IF NOT EXISTS idx_pages_markdown_storage_path ON pages(markdown_storage_path);
+CREATE INDEX IF NOT EXISTS idx_pages_document_storage_path ON pages(document_storage_path);
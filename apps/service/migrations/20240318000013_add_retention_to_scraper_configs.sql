@@ -0,0 +1,2 @@
+-- Rules for how long a config's crawls are kept, see RetentionPolicy
+ALTER TABLE scraper_configs ADD COLUMN IF NOT EXISTS retention JSONB NOT NULL DEFAULT '{}'::jsonb;
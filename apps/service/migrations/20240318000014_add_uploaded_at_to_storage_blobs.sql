@@ -0,0 +1,5 @@
+-- When the object was confirmed written, so content is only reused once its upload has succeeded
+ALTER TABLE storage_blobs ADD COLUMN IF NOT EXISTS uploaded_at TIMESTAMPTZ;
+
+-- Objects registered so far were written before their pages referred to them
+UPDATE storage_blobs SET uploaded_at = created_at WHERE uploaded_at IS NULL;
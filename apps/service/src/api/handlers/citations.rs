@@ -0,0 +1,121 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::api::routes::AppState;
+use crate::application::scraper::citation;
+use crate::utils::error::AppError;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchCitationsQuery {
+    /// The authority to look for, e.g. `42 U.S.C. § 1983` or `[2019] UKSC 41`
+    q: String,
+    /// `statute`, `regulation` or `case`
+    kind: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+/// Pages citing an authority, most recently crawled first.
+///
+/// A query that reads as a citation matches its normalised form, so `42 USC 1983`
+/// finds `42 U.S.C. § 1983` as well as its subsections such as `42 U.S.C. § 1983(a)`.
+/// Any other query matches citations containing it.
+pub async fn search_citations(
+    State(state): State<AppState>,
+    Query(params): Query<SearchCitationsQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let q = params.q.trim();
+    if q.is_empty() {
+        return Err(AppError::InvalidInput("Query parameter q must not be empty".to_string()));
+    }
+
+    let limit = params.limit.unwrap_or(50).clamp(1, 1000);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let normalized = citation::normalize(q);
+
+    let mut query_builder = sqlx::QueryBuilder::new(
+        r#"
+        SELECT c.kind, c.citation, c.normalized, c.occurrences,
+            p.id AS page_id, p.job_id, p.url, p.title, p.crawled_at
+        FROM page_citations c
+        JOIN pages p ON p.id = c.page_id
+        WHERE "#,
+    );
+
+    match &normalized {
+        Some(normalized) => {
+            query_builder.push("(c.normalized = ");
+            query_builder.push_bind(normalized.clone());
+            query_builder.push(" OR c.normalized LIKE ");
+            query_builder.push_bind(format!("{}(%", escape_like(normalized)));
+            query_builder.push(")");
+        },
+        None => {
+            let pattern = format!("%{}%", escape_like(q));
+            query_builder.push("(c.normalized ILIKE ");
+            query_builder.push_bind(pattern.clone());
+            query_builder.push(" OR c.citation ILIKE ");
+            query_builder.push_bind(pattern);
+            query_builder.push(")");
+        },
+    }
+
+    if let Some(kind) = &params.kind {
+        query_builder.push(" AND c.kind = ");
+        query_builder.push_bind(kind.to_lowercase());
+    }
+
+    query_builder.push(" ORDER BY p.crawled_at DESC, c.page_id, c.position LIMIT ");
+    query_builder.push_bind(limit);
+    query_builder.push(" OFFSET ");
+    query_builder.push_bind(offset);
+
+    let rows = query_builder
+        .build()
+        .fetch_all(&state.db_pool)
+        .await
+        .map_err(AppError::from)?;
+
+    let citations: Vec<serde_json::Value> = rows.iter().map(|row| {
+        let page_id: Uuid = row.get("page_id");
+        let job_id: Uuid = row.get("job_id");
+        let crawled_at: DateTime<Utc> = row.get("crawled_at");
+
+        serde_json::json!({
+            "kind": row.get::<String, _>("kind"),
+            "citation": row.get::<String, _>("citation"),
+            "normalized": row.get::<String, _>("normalized"),
+            "occurrences": row.get::<i32, _>("occurrences"),
+            "page_id": page_id,
+            "job_id": job_id,
+            "url": row.get::<String, _>("url"),
+            "title": row.get::<Option<String>, _>("title"),
+            "crawled_at": crawled_at,
+            "_links": {
+                "page": { "href": format!("/api/pages/{}", page_id) },
+                "citations": { "href": format!("/api/pages/{}/citations", page_id) }
+            }
+        })
+    }).collect();
+
+    let response = serde_json::json!({
+        "query": q,
+        "normalized": normalized,
+        "citations": citations,
+        "_links": {
+            "self": { "href": "/api/citations" }
+        }
+    });
+
+    Ok(Json(response))
+}
+
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
@@ -3,14 +3,19 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::sync::Arc;
 use uuid::Uuid;
 use tracing::{info, error, debug, instrument};
 
+use crate::application::scraper::crawler::Crawler;
+use crate::application::scraper::extraction::{FieldExtractor, EXTRACTION_ERRORS_KEY};
 use crate::application::scraper::service::ScraperService;
-use crate::domain::scraper_config::ScraperConfig;
+use crate::application::storage::retention;
+use crate::domain::job::JobPriority;
+use crate::domain::scraper_config::{CanonicalizationRules, CrawlMode, ExtractionRule, RetentionPolicy, ScraperConfig};
 use crate::utils::error::AppError;
 use crate::api::routes::AppState;
 
@@ -29,6 +34,15 @@ pub struct CreateConfigRequest {
     max_concurrent_requests: Option<i32>,
     schedule: Option<String>,
     headers: Option<serde_json::Value>,
+    queue_name: Option<String>,
+    /// `links` (default), `sitemap` or `both`
+    crawl_mode: Option<CrawlMode>,
+    /// Rules for rewriting URLs, with defaults for any rule left out
+    canonicalization: Option<CanonicalizationRules>,
+    /// Rules extracting structured fields into page metadata
+    extraction_rules: Option<Vec<ExtractionRule>>,
+    /// How long crawls are kept, by default forever
+    retention: Option<RetentionPolicy>,
 }
 
 #[derive(Debug, Serialize)]
@@ -43,6 +57,49 @@ pub struct ListConfigsQuery {
     offset: Option<i64>,
 }
 
+/// Queue names become part of Redis keys, so only plain names are accepted
+fn validate_queue_name(queue_name: Option<&str>) -> Result<(), AppError> {
+    match queue_name {
+        Some(name) if name.is_empty()
+            || name.len() > 100
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+        {
+            Err(AppError::InvalidInput(format!(
+                "Invalid queue name '{}': use up to 100 letters, digits, '_' or '-'",
+                name
+            )))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Headers are checked before they are stored, so a job never fails on a header it can't send
+fn validate_headers(headers: Option<&serde_json::Value>) -> Result<(), AppError> {
+    headers.map_or(Ok(()), |headers| Crawler::parse_headers(headers).map(|_| ()))
+}
+
+/// Rules are compiled before they are stored, so a job never starts with a broken selector or regex
+fn validate_extraction_rules(rules: Option<&[ExtractionRule]>) -> Result<(), AppError> {
+    rules.map_or(Ok(()), |rules| FieldExtractor::new(rules).map(|_| ()))
+}
+
+/// A policy must keep at least one job or one day, so a typo can't delete a config's whole history
+fn validate_retention(retention: Option<&RetentionPolicy>) -> Result<(), AppError> {
+    let Some(retention) = retention else {
+        return Ok(());
+    };
+
+    if retention.keep_last_jobs.is_some_and(|jobs| jobs < 1) {
+        return Err(AppError::InvalidInput("retention.keep_last_jobs must be at least 1".to_string()));
+    }
+
+    if retention.keep_versions_days.is_some_and(|days| days < 1) {
+        return Err(AppError::InvalidInput("retention.keep_versions_days must be at least 1".to_string()));
+    }
+
+    Ok(())
+}
+
 #[instrument(skip(state, payload), fields(config_name = %payload.name, base_url = %payload.base_url))]
 pub async fn create_config(
     State(state): State<AppState>,
@@ -50,6 +107,11 @@ pub async fn create_config(
 ) -> Result<(StatusCode, Json<ConfigResponse>), AppError> {
     info!("Creating new scraper config: {}", payload.name);
     
+    validate_queue_name(payload.queue_name.as_deref())?;
+    validate_headers(payload.headers.as_ref())?;
+    validate_extraction_rules(payload.extraction_rules.as_deref())?;
+    validate_retention(payload.retention.as_ref())?;
+    
     // Create a new config from the request
     let mut config = ScraperConfig::new(
         payload.name,
@@ -68,6 +130,11 @@ pub async fn create_config(
     config.max_concurrent_requests = payload.max_concurrent_requests.unwrap_or(5);
     config.schedule = payload.schedule;
     config.headers = payload.headers.unwrap_or_else(|| serde_json::json!({}));
+    config.queue_name = payload.queue_name;
+    config.crawl_mode = payload.crawl_mode.unwrap_or_default();
+    config.canonicalization = sqlx::types::Json(payload.canonicalization.unwrap_or_default());
+    config.extraction_rules = sqlx::types::Json(payload.extraction_rules.unwrap_or_default());
+    config.retention = sqlx::types::Json(payload.retention.unwrap_or_default());
     config.active = true;
     
     debug!("Inserting config into database with id: {}", config.id);
@@ -79,9 +146,10 @@ pub async fn create_config(
             id, name, description, base_url, include_patterns, exclude_patterns,
             max_depth, max_pages_per_job, respect_robots_txt, user_agent,
             request_delay_ms, max_concurrent_requests, schedule, headers,
-            created_at, updated_at, active
+            created_at, updated_at, active, queue_name, crawl_mode, canonicalization,
+            extraction_rules, retention
         ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22
         )
         RETURNING id
         "#,
@@ -101,7 +169,12 @@ pub async fn create_config(
         config.headers,
         config.created_at,
         config.updated_at,
-        config.active
+        config.active,
+        config.queue_name,
+        config.crawl_mode.to_string(),
+        &config.canonicalization as _,
+        &config.extraction_rules as _,
+        &config.retention as _
     )
     .fetch_one(&state.db_pool)
     .await
@@ -117,7 +190,9 @@ pub async fn create_config(
     let links = serde_json::json!({
         "self": { "href": format!("/api/configs/{}", config_id) },
         "jobs": { "href": format!("/api/configs/{}/jobs", config_id) },
-        "start": { "href": format!("/api/configs/{}/start", config_id) }
+        "start": { "href": format!("/api/configs/{}/start", config_id) },
+        "dry_run": { "href": format!("/api/configs/{}/dry-run", config_id) },
+        "retention": { "href": format!("/api/configs/{}/retention", config_id) }
     });
     
     Ok((StatusCode::CREATED, Json(ConfigResponse {
@@ -144,7 +219,11 @@ pub async fn list_configs(
             max_depth, max_pages_per_job, respect_robots_txt, user_agent,
             request_delay_ms, max_concurrent_requests, schedule, 
             headers as "headers: serde_json::Value",
-            created_at, updated_at, active
+            created_at, updated_at, active, queue_name,
+            crawl_mode as "crawl_mode: CrawlMode",
+            canonicalization as "canonicalization: sqlx::types::Json<CanonicalizationRules>",
+            extraction_rules as "extraction_rules: sqlx::types::Json<Vec<ExtractionRule>>",
+            retention as "retention: sqlx::types::Json<RetentionPolicy>"
         FROM scraper_configs
         ORDER BY created_at DESC
         LIMIT $1 OFFSET $2
@@ -186,7 +265,11 @@ pub async fn get_config(
             max_depth, max_pages_per_job, respect_robots_txt, user_agent,
             request_delay_ms, max_concurrent_requests, schedule, 
             headers as "headers: serde_json::Value",
-            created_at, updated_at, active
+            created_at, updated_at, active, queue_name,
+            crawl_mode as "crawl_mode: CrawlMode",
+            canonicalization as "canonicalization: sqlx::types::Json<CanonicalizationRules>",
+            extraction_rules as "extraction_rules: sqlx::types::Json<Vec<ExtractionRule>>",
+            retention as "retention: sqlx::types::Json<RetentionPolicy>"
         FROM scraper_configs
         WHERE id = $1
         "#,
@@ -209,7 +292,9 @@ pub async fn get_config(
     let links = serde_json::json!({
         "self": { "href": format!("/api/configs/{}", config.id) },
         "jobs": { "href": format!("/api/configs/{}/jobs", config.id) },
-        "start": { "href": format!("/api/configs/{}/start", config.id) }
+        "start": { "href": format!("/api/configs/{}/start", config.id) },
+        "dry_run": { "href": format!("/api/configs/{}/dry-run", config.id) },
+        "retention": { "href": format!("/api/configs/{}/retention", config.id) }
     });
     
     Ok(Json(ConfigResponse {
@@ -226,6 +311,11 @@ pub async fn update_config(
 ) -> Result<Json<ConfigResponse>, AppError> {
     info!("Updating config: {}", id);
     
+    validate_queue_name(payload.queue_name.as_deref())?;
+    validate_headers(payload.headers.as_ref())?;
+    validate_extraction_rules(payload.extraction_rules.as_deref())?;
+    validate_retention(payload.retention.as_ref())?;
+    
     // Get the existing config
     let mut config = sqlx::query_as!(
         ScraperConfig,
@@ -235,7 +325,11 @@ pub async fn update_config(
             max_depth, max_pages_per_job, respect_robots_txt, user_agent,
             request_delay_ms, max_concurrent_requests, schedule, 
             headers as "headers: serde_json::Value",
-            created_at, updated_at, active
+            created_at, updated_at, active, queue_name,
+            crawl_mode as "crawl_mode: CrawlMode",
+            canonicalization as "canonicalization: sqlx::types::Json<CanonicalizationRules>",
+            extraction_rules as "extraction_rules: sqlx::types::Json<Vec<ExtractionRule>>",
+            retention as "retention: sqlx::types::Json<RetentionPolicy>"
         FROM scraper_configs
         WHERE id = $1
         "#,
@@ -268,6 +362,17 @@ pub async fn update_config(
     config.max_concurrent_requests = payload.max_concurrent_requests.unwrap_or(config.max_concurrent_requests);
     config.schedule = payload.schedule.or(config.schedule);
     config.headers = payload.headers.unwrap_or(config.headers);
+    config.queue_name = payload.queue_name.or(config.queue_name);
+    config.crawl_mode = payload.crawl_mode.unwrap_or(config.crawl_mode);
+    if let Some(canonicalization) = payload.canonicalization {
+        config.canonicalization = sqlx::types::Json(canonicalization);
+    }
+    if let Some(extraction_rules) = payload.extraction_rules {
+        config.extraction_rules = sqlx::types::Json(extraction_rules);
+    }
+    if let Some(retention) = payload.retention {
+        config.retention = sqlx::types::Json(retention);
+    }
     config.updated_at = chrono::Utc::now();
     config.active = true;
     
@@ -282,8 +387,10 @@ pub async fn update_config(
             include_patterns = $4, exclude_patterns = $5, max_depth = $6,
             max_pages_per_job = $7, respect_robots_txt = $8, user_agent = $9,
             request_delay_ms = $10, max_concurrent_requests = $11, schedule = $12,
-            headers = $13, updated_at = $14, active = $15
-        WHERE id = $16
+            headers = $13, updated_at = $14, active = $15, queue_name = $16,
+            crawl_mode = $17, canonicalization = $18, extraction_rules = $19,
+            retention = $20
+        WHERE id = $21
         "#,
         config.name,
         config.description,
@@ -300,6 +407,11 @@ pub async fn update_config(
         config.headers,
         config.updated_at,
         config.active,
+        config.queue_name,
+        config.crawl_mode.to_string(),
+        &config.canonicalization as _,
+        &config.extraction_rules as _,
+        &config.retention as _,
         config.id
     )
     .execute(&state.db_pool)
@@ -315,7 +427,9 @@ pub async fn update_config(
     let links = serde_json::json!({
         "self": { "href": format!("/api/configs/{}", config.id) },
         "jobs": { "href": format!("/api/configs/{}/jobs", config.id) },
-        "start": { "href": format!("/api/configs/{}/start", config.id) }
+        "start": { "href": format!("/api/configs/{}/start", config.id) },
+        "dry_run": { "href": format!("/api/configs/{}/dry-run", config.id) },
+        "retention": { "href": format!("/api/configs/{}/retention", config.id) }
     });
     
     Ok(Json(ConfigResponse {
@@ -324,14 +438,23 @@ pub async fn update_config(
     }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct StartJobQuery {
+    /// RFC 3339 timestamp to run the job at instead of right away
+    at: Option<DateTime<Utc>>,
+    /// `low`, `normal` (default) or `high`
+    priority: Option<JobPriority>,
+}
+
 #[instrument(skip(state), fields(config_id = %id))]
 pub async fn start_job(
     State(state): State<crate::api::routes::AppState>,
     Path(id): Path<Uuid>,
+    Query(params): Query<StartJobQuery>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), AppError> {
     info!("Starting job for config: {}", id);
     
-    let job = state.scraper_service.create_job(id).await.map_err(|e| {
+    let job = state.scraper_service.create_job(id, params.at, params.priority.unwrap_or_default()).await.map_err(|e| {
         error!("Failed to create job for config {}: {:?}", id, e);
         match e.downcast::<AppError>() {
             Ok(app_error) => app_error,
@@ -344,6 +467,9 @@ pub async fn start_job(
     let response = serde_json::json!({
         "job_id": job.id,
         "status": job.status.to_string(),
+        "scheduled_for": job.next_run_at,
+        "priority": job.priority,
+        "queue": job.queue_name,
         "_links": {
             "self": { "href": format!("/api/jobs/{}", job.id) },
             "config": { "href": format!("/api/configs/{}", job.config_id) }
@@ -351,4 +477,97 @@ pub async fn start_job(
     });
     
     Ok((StatusCode::CREATED, Json(response)))
-} 
\ No newline at end of file
+} 
+
+#[derive(Debug, Deserialize)]
+pub struct DryRunRequest {
+    url: String,
+    /// Rules to try instead of those stored on the config
+    extraction_rules: Option<Vec<ExtractionRule>>,
+}
+
+/// Crawl one URL with a config and return what would be extracted, without storing anything
+#[instrument(skip(state, payload), fields(config_id = %id, url = %payload.url))]
+pub async fn dry_run(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<DryRunRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    debug!("Dry run of config {} on {}", id, payload.url);
+    
+    let into_app_error = |e: anyhow::Error| match e.downcast::<AppError>() {
+        Ok(app_error) => app_error,
+        Err(other) => AppError::Internal(other.to_string()),
+    };
+    
+    let mut config = state.scraper_service.get_config(id).await.map_err(into_app_error)?;
+    
+    if let Some(extraction_rules) = payload.extraction_rules {
+        config.extraction_rules = sqlx::types::Json(extraction_rules);
+    }
+    validate_extraction_rules(Some(&config.extraction_rules))?;
+    
+    let crawler = state.scraper_worker.crawler().for_config(&config).map_err(into_app_error)?;
+    let url = crawler.normalize_url(&payload.url).map_err(into_app_error)?;
+    
+    // Patterns are not applied, so any URL can be tried. A page that can't be fetched is the site's failure, not ours
+    let (page, links) = crawler.crawl_url(&url, 0, None, &[], &[], None).await.map_err(|e| match into_app_error(e) {
+        AppError::Scraper(msg) | AppError::Internal(msg) => AppError::Upstream(msg),
+        other => other,
+    })?;
+    
+    let mut fields = page.metadata.as_object().cloned().unwrap_or_default();
+    let errors = fields.remove(EXTRACTION_ERRORS_KEY).unwrap_or_else(|| serde_json::json!([]));
+    
+    let response = serde_json::json!({
+        "url": page.url,
+        "normalized_url": page.normalized_url,
+        "http_status": page.http_status,
+        "content_type": page.content_type,
+        "title": page.title,
+        "fields": fields,
+        "errors": errors,
+        "link_count": links.len(),
+        "_links": {
+            "config": { "href": format!("/api/configs/{}", id) }
+        }
+    });
+    
+    Ok(Json(response))
+}
+
+/// What the config's retention policy would delete if the janitor ran now, without deleting anything
+#[instrument(skip(state), fields(config_id = %id))]
+pub async fn retention_report(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let config = state.scraper_service.get_config(id).await.map_err(|e| match e.downcast::<AppError>() {
+        Ok(app_error) => app_error,
+        Err(other) => AppError::Internal(other.to_string()),
+    })?;
+    
+    let report = retention::report(&state.db_pool, id, &config.retention)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to work out the retention report: {}", e)))?;
+    let (unreferenced_objects, unreferenced_bytes) = retention::unreferenced_objects(&state.db_pool)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to count unreferenced objects: {}", e)))?;
+    
+    let response = serde_json::json!({
+        "config_id": id,
+        "retention": config.retention.0,
+        "enabled": config.retention.is_enabled(),
+        "expired": report,
+        "storage": {
+            "unreferenced_objects": unreferenced_objects,
+            "unreferenced_bytes": unreferenced_bytes
+        },
+        "_links": {
+            "self": { "href": format!("/api/configs/{}/retention", id) },
+            "config": { "href": format!("/api/configs/{}", id) }
+        }
+    });
+    
+    Ok(Json(response))
+}
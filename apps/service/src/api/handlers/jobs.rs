@@ -1,12 +1,15 @@
 use axum::{
+    body::Body,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, StatusCode},
+    response::IntoResponse,
     Json,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::application::export::job_export::{ExportFormat, JobExporter};
 use crate::application::scraper::service::ScraperService;
 use crate::utils::error::AppError;
 use crate::api::routes::AppState;
@@ -73,4 +76,66 @@ pub async fn cancel_job(
     });
     
     Ok(Json(response))
-} 
\ No newline at end of file
+}
+
+pub async fn pause_job(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let job = state.scraper_service.pause_job(id).await?;
+    
+    let response = serde_json::json!({
+        "job": job,
+        "_links": {
+            "self": { "href": format!("/api/jobs/{}", job.id) },
+            "config": { "href": format!("/api/configs/{}", job.config_id) }
+        }
+    });
+    
+    Ok(Json(response))
+}
+
+pub async fn resume_job(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let job = state.scraper_service.resume_job(id).await?;
+    
+    let response = serde_json::json!({
+        "job": job,
+        "_links": {
+            "self": { "href": format!("/api/jobs/{}", job.id) },
+            "config": { "href": format!("/api/configs/{}", job.config_id) }
+        }
+    });
+    
+    Ok(Json(response))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportJobQuery {
+    /// `zip` (default), `jsonl` or `tar.zst`
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+/// Download every page of a job with its HTML and Markdown as one archive, streamed as it is written
+pub async fn export_job(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<ExportJobQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let job = state.scraper_service.get_job(id).await?;
+    
+    let filename = format!("job-{}.{}", job.id, params.format.extension());
+    let exporter = JobExporter::new(state.db_pool.clone(), state.storage_client.clone());
+    let body = exporter.export(job, params.format);
+    
+    Ok((
+        [
+            (header::CONTENT_TYPE, params.format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(body),
+    ))
+}
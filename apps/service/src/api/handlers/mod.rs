@@ -2,4 +2,7 @@ pub mod configs;
 pub mod jobs;
 pub mod pages;
 pub mod webhooks;
-pub mod analytics; 
\ No newline at end of file
+pub mod analytics;
+pub mod queue;
+pub mod urls;
+pub mod citations; 
\ No newline at end of file
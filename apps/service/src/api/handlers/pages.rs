@@ -1,6 +1,8 @@
 use axum::{
+    body::Body,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
@@ -9,9 +11,10 @@ use std::sync::Arc;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
-use crate::domain::page::Page;
-use crate::infrastructure::storage::s3_client::S3StorageClient;
-use crate::infrastructure::storage::s3_client::StorageClient;
+use crate::application::scraper::document::DocumentKind;
+use crate::domain::page::{url_hash, Page, PageLink};
+use crate::infrastructure::storage::compression::ContentEncoding;
+use crate::infrastructure::storage::StoredObject;
 use crate::utils::error::AppError;
 use crate::api::routes::AppState;
 
@@ -65,10 +68,13 @@ pub async fn list_pages(
         let crawled_at: DateTime<Utc> = row.get("crawled_at");
         let html_storage_path: Option<String> = row.get("html_storage_path");
         let markdown_storage_path: Option<String> = row.get("markdown_storage_path");
+        let document_storage_path: Option<String> = row.get("document_storage_path");
+        let content_type: Option<String> = row.get("content_type");
         let title: Option<String> = row.get("title");
         let error_message: Option<String> = row.get("error_message");
         let depth: i32 = row.get("depth");
         let parent_url: Option<String> = row.get("parent_url");
+        let metadata: serde_json::Value = row.get("metadata");
         
         serde_json::json!({
             "id": id,
@@ -80,12 +86,14 @@ pub async fn list_pages(
             "crawled_at": crawled_at,
             "html_storage_path": html_storage_path,
             "markdown_storage_path": markdown_storage_path,
+            "document_storage_path": document_storage_path,
+            "content_type": content_type,
             "title": title,
             "error_message": error_message,
             "depth": depth,
             "parent_url": parent_url,
             "http_headers": serde_json::json!({}),
-            "metadata": serde_json::json!({})
+            "metadata": metadata
         })
     }).collect();
     
@@ -121,10 +129,13 @@ pub async fn get_page(
     let crawled_at: DateTime<Utc> = row.get("crawled_at");
     let html_storage_path: Option<String> = row.get("html_storage_path");
     let markdown_storage_path: Option<String> = row.get("markdown_storage_path");
+    let document_storage_path: Option<String> = row.get("document_storage_path");
+    let content_type: Option<String> = row.get("content_type");
     let title: Option<String> = row.get("title");
     let error_message: Option<String> = row.get("error_message");
     let depth: i32 = row.get("depth");
     let parent_url: Option<String> = row.get("parent_url");
+    let metadata: serde_json::Value = row.get("metadata");
     
     // Create a page object manually
     let page = serde_json::json!({
@@ -137,12 +148,14 @@ pub async fn get_page(
         "crawled_at": crawled_at,
         "html_storage_path": html_storage_path,
         "markdown_storage_path": markdown_storage_path,
+        "document_storage_path": document_storage_path,
+        "content_type": content_type,
         "title": title,
         "error_message": error_message,
         "depth": depth,
         "parent_url": parent_url,
         "http_headers": serde_json::json!({}),
-        "metadata": serde_json::json!({})
+        "metadata": metadata
     });
     
     let response = serde_json::json!({
@@ -151,7 +164,11 @@ pub async fn get_page(
             "self": { "href": format!("/api/pages/{}", page_id) },
             "job": { "href": format!("/api/jobs/{}", job_id) },
             "html": { "href": format!("/api/pages/{}/html", page_id) },
-            "markdown": { "href": format!("/api/pages/{}/markdown", page_id) }
+            "markdown": { "href": format!("/api/pages/{}/markdown", page_id) },
+            "document": { "href": format!("/api/pages/{}/document", page_id) },
+            "links": { "href": format!("/api/pages/{}/links", page_id) },
+            "citations": { "href": format!("/api/pages/{}/citations", page_id) },
+            "versions": { "href": format!("/api/urls/{}/versions", url_hash(&normalized_url)) }
         }
     });
     
@@ -161,83 +178,199 @@ pub async fn get_page(
 pub async fn get_page_html(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-) -> Result<Json<serde_json::Value>, AppError> {
-    // Use a simpler query approach that doesn't rely on specific columns
-    let row = sqlx::query("SELECT * FROM pages WHERE id = $1")
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let html_storage_path = get_storage_path(&state.db_pool, id, "html_storage_path").await?
+        .ok_or_else(|| AppError::NotFound("HTML content not available for this page".to_string()))?;
+
+    let object = state.storage_client.get_object_stream(&html_storage_path).await
+        .map_err(|e| AppError::Internal(format!("Failed to retrieve HTML content: {}", e)))?;
+
+    Ok(stream_object(object, "text/html; charset=utf-8", &headers))
+}
+
+pub async fn get_page_markdown(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let markdown_storage_path = get_storage_path(&state.db_pool, id, "markdown_storage_path").await?
+        .ok_or_else(|| AppError::NotFound("Markdown content not available for this page".to_string()))?;
+
+    let object = state.storage_client.get_object_stream(&markdown_storage_path).await
+        .map_err(|e| AppError::Internal(format!("Failed to retrieve Markdown content: {}", e)))?;
+
+    Ok(stream_object(object, "text/markdown; charset=utf-8", &headers))
+}
+
+pub async fn get_page_document(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let row = sqlx::query("SELECT document_storage_path, content_type FROM pages WHERE id = $1")
         .bind(id)
         .fetch_optional(&state.db_pool)
         .await
         .map_err(AppError::from)?
         .ok_or_else(|| AppError::NotFound(format!("Page not found: {}", id)))?;
-    
-    // Extract fields manually
-    let page_id: Uuid = row.get("id");
-    let url: String = row.get("url");
-    let crawled_at: DateTime<Utc> = row.get("crawled_at");
-    let html_storage_path: Option<String> = row.get("html_storage_path");
-    
-    // Check if HTML is available
-    if html_storage_path.is_none() {
-        return Err(AppError::NotFound("HTML content not available for this page".to_string()));
+
+    let document_storage_path: Option<String> = row.get("document_storage_path");
+    let content_type: Option<String> = row.get("content_type");
+
+    // Only pages crawled from PDF, Word, RTF or text documents keep the original file
+    let document_storage_path = document_storage_path
+        .ok_or_else(|| AppError::NotFound("Document not available for this page".to_string()))?;
+
+    let object = state.storage_client.get_object_stream(&document_storage_path).await
+        .map_err(|e| AppError::Internal(format!("Failed to retrieve document: {}", e)))?;
+
+    let content_type = content_type.unwrap_or_else(|| "application/octet-stream".to_string());
+    let extension = DocumentKind::from_mime_type(&content_type).map_or("bin", |kind| kind.extension());
+
+    // Documents are downloaded rather than opened on this origin
+    let mut response = stream_object(object, &content_type, &headers);
+    if let Ok(disposition) = HeaderValue::from_str(&format!("attachment; filename=\"{}.{}\"", id, extension)) {
+        response.headers_mut().insert(header::CONTENT_DISPOSITION, disposition);
     }
-    
-    // Get the HTML content from S3
-    let html_content = state.storage_client.get_object(html_storage_path.unwrap().as_str()).await
-        .map_err(|e| AppError::Internal(format!("Failed to retrieve HTML content: {}", e)))?;
-    
+
+    Ok(response)
+}
+
+/// The storage path held in one of a page's content columns
+async fn get_storage_path(db_pool: &PgPool, id: Uuid, column: &str) -> Result<Option<String>, AppError> {
+    let row = sqlx::query(&format!("SELECT {} FROM pages WHERE id = $1", column))
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await
+        .map_err(AppError::from)?
+        .ok_or_else(|| AppError::NotFound(format!("Page not found: {}", id)))?;
+
+    Ok(row.get(column))
+}
+
+/// Respond with a stored object as it is read, without holding it in memory.
+///
+/// Compressed content is sent as it is stored to clients that accept its
+/// encoding, and decompressed for any other client. The content was crawled
+/// from other sites, so it is sandboxed and its type is not sniffed: scripts
+/// in a stored page can't run with this service's origin.
+fn stream_object(object: StoredObject, content_type: &str, request_headers: &HeaderMap) -> Response {
+    let content_encoding = object.content_encoding.filter(|encoding| accepts_encoding(request_headers, *encoding));
+
+    let body = match content_encoding {
+        Some(_) => object.body,
+        None => object.into_decoded(),
+    };
+
+    let mut response = (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::VARY, "accept-encoding".to_string()),
+            (header::CONTENT_SECURITY_POLICY, "sandbox".to_string()),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        Body::from_stream(body),
+    ).into_response();
+
+    if let Some(encoding) = content_encoding {
+        response.headers_mut().insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding.as_str()));
+    }
+
+    response
+}
+
+/// Whether a request's `Accept-Encoding` header accepts an encoding
+fn accepts_encoding(headers: &HeaderMap, encoding: ContentEncoding) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|item| {
+            let mut params = item.split(';');
+            let coding = params.next().unwrap_or_default().trim();
+            let refused = params.any(|param| {
+                param.trim().strip_prefix("q=").and_then(|q| q.trim().parse::<f32>().ok()) == Some(0.0)
+            });
+
+            coding.eq_ignore_ascii_case(encoding.as_str()) && !refused
+        })
+}
+
+pub async fn get_page_links(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    sqlx::query("SELECT id FROM pages WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&state.db_pool)
+        .await
+        .map_err(AppError::from)?
+        .ok_or_else(|| AppError::NotFound(format!("Page not found: {}", id)))?;
+
+    let rows = sqlx::query(
+        "SELECT url, anchor_text, element, nofollow FROM page_links WHERE page_id = $1 ORDER BY position"
+    )
+    .bind(id)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(AppError::from)?;
+
+    let links: Vec<PageLink> = rows.iter().map(|row| PageLink {
+        url: row.get("url"),
+        anchor_text: row.get("anchor_text"),
+        element: row.get("element"),
+        nofollow: row.get("nofollow"),
+    }).collect();
+
     let response = serde_json::json!({
-        "content": html_content,
-        "page_id": page_id,
-        "url": url,
-        "crawled_at": crawled_at,
+        "links": links,
+        "page_id": id,
         "_links": {
-            "self": { "href": format!("/api/pages/{}/html", page_id) },
-            "page": { "href": format!("/api/pages/{}", page_id) },
-            "markdown": { "href": format!("/api/pages/{}/markdown", page_id) }
+            "self": { "href": format!("/api/pages/{}/links", id) },
+            "page": { "href": format!("/api/pages/{}", id) }
         }
     });
-    
+
     Ok(Json(response))
 }
 
-pub async fn get_page_markdown(
+/// Statute, regulation and case citations found on a page, in the order they first appear
+pub async fn get_page_citations(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    // Use a simpler query approach that doesn't rely on specific columns
-    let row = sqlx::query("SELECT * FROM pages WHERE id = $1")
+    sqlx::query("SELECT id FROM pages WHERE id = $1")
         .bind(id)
         .fetch_optional(&state.db_pool)
         .await
         .map_err(AppError::from)?
         .ok_or_else(|| AppError::NotFound(format!("Page not found: {}", id)))?;
-    
-    // Extract fields manually
-    let page_id: Uuid = row.get("id");
-    let url: String = row.get("url");
-    let crawled_at: DateTime<Utc> = row.get("crawled_at");
-    let markdown_storage_path: Option<String> = row.get("markdown_storage_path");
-    
-    // Check if Markdown is available
-    if markdown_storage_path.is_none() {
-        return Err(AppError::NotFound("Markdown content not available for this page".to_string()));
-    }
-    
-    // Get the Markdown content from S3
-    let markdown_content = state.storage_client.get_object(markdown_storage_path.unwrap().as_str()).await
-        .map_err(|e| AppError::Internal(format!("Failed to retrieve Markdown content: {}", e)))?;
-    
+
+    let rows = sqlx::query(
+        "SELECT kind, citation, normalized, occurrences FROM page_citations WHERE page_id = $1 ORDER BY position"
+    )
+    .bind(id)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(AppError::from)?;
+
+    let citations: Vec<serde_json::Value> = rows.iter().map(|row| serde_json::json!({
+        "kind": row.get::<String, _>("kind"),
+        "citation": row.get::<String, _>("citation"),
+        "normalized": row.get::<String, _>("normalized"),
+        "occurrences": row.get::<i32, _>("occurrences"),
+    })).collect();
+
     let response = serde_json::json!({
-        "content": markdown_content,
-        "page_id": page_id,
-        "url": url,
-        "crawled_at": crawled_at,
+        "citations": citations,
+        "page_id": id,
         "_links": {
-            "self": { "href": format!("/api/pages/{}/markdown", page_id) },
-            "page": { "href": format!("/api/pages/{}", page_id) },
-            "html": { "href": format!("/api/pages/{}/html", page_id) }
+            "self": { "href": format!("/api/pages/{}/citations", id) },
+            "page": { "href": format!("/api/pages/{}", id) }
         }
     });
-    
+
     Ok(Json(response))
-} 
\ No newline at end of file
+}
@@ -0,0 +1,92 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::utils::error::AppError;
+use crate::api::routes::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct ListDeadLettersQuery {
+    limit: Option<usize>,
+    offset: Option<usize>,
+    /// Defaults to the default job queue
+    queue: Option<String>,
+}
+
+pub async fn list_dead_letters(
+    State(state): State<AppState>,
+    Query(params): Query<ListDeadLettersQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let limit = params.limit.unwrap_or(50).min(500);
+    let offset = params.offset.unwrap_or(0);
+    let queue = params.queue.unwrap_or_else(|| state.job_queue.default_queue().to_string());
+    
+    let entries = state.job_queue.list_dead_letters(&queue, offset, limit).await?;
+    let total = state.job_queue.dead_letter_count(&queue).await?;
+    
+    let entries: Vec<serde_json::Value> = entries
+        .into_iter()
+        .map(|entry| {
+            let job_id = entry.job_id.clone();
+            serde_json::json!({
+                "entry": entry,
+                "_links": {
+                    "job": { "href": format!("/api/jobs/{}", job_id) },
+                    "retry": { "href": format!("/api/queue/dead-letter/{}/retry", job_id) }
+                }
+            })
+        })
+        .collect();
+    
+    let response = serde_json::json!({
+        "queue": queue,
+        "entries": entries,
+        "total": total,
+        "limit": limit,
+        "offset": offset,
+        "_links": {
+            "self": { "href": "/api/queue/dead-letter" }
+        }
+    });
+    
+    Ok(Json(response))
+}
+
+pub async fn retry_dead_letter(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let job = state.scraper_service.retry_dead_letter(id).await?;
+    
+    let response = serde_json::json!({
+        "job": job,
+        "_links": {
+            "self": { "href": format!("/api/jobs/{}", job.id) },
+            "config": { "href": format!("/api/configs/{}", job.config_id) }
+        }
+    });
+    
+    Ok(Json(response))
+}
+
+pub async fn delete_dead_letter(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    // Dead-letter lists are kept per queue, fall back to the default one if the job is gone
+    let queue = match state.scraper_service.get_job(id).await {
+        Ok(job) => job.queue_name,
+        Err(_) => state.job_queue.default_queue().to_string(),
+    };
+    
+    // The job itself stays failed, only the dead-letter entry is discarded
+    if !state.job_queue.remove_dead_letter(&queue, &id.to_string()).await? {
+        return Err(AppError::NotFound(format!("Job not in dead-letter queue: {}", id)));
+    }
+    
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,315 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::api::routes::AppState;
+use crate::utils::error::AppError;
+
+/// Lines of context around each hunk of a unified diff
+const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// A run of consecutive crawls of a URL that returned the same content
+#[derive(Debug, Serialize)]
+pub struct PageVersion {
+    /// Position in the timeline, 1 for the first content seen
+    version: i64,
+    content_hash: String,
+    first_seen_at: DateTime<Utc>,
+    last_seen_at: DateTime<Utc>,
+    crawls: i64,
+    page_id: Uuid,
+    job_id: Uuid,
+    #[serde(skip)]
+    markdown_storage_path: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffMode {
+    #[default]
+    Unified,
+    Words,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListVersionsQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    from: Option<String>,
+    to: Option<String>,
+    #[serde(default)]
+    mode: DiffMode,
+    context: Option<usize>,
+}
+
+/// Timeline of a URL's content, most recent version first
+pub async fn list_versions(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+    Query(params): Query<ListVersionsQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let url_hash = parse_url_hash(&hash)?;
+    let limit = params.limit.unwrap_or(50).clamp(1, 1000);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let url = get_url(&state, &url_hash).await?;
+    let versions = get_versions(&state, &url_hash, VersionFilter::default(), limit, offset).await?;
+
+    let response = serde_json::json!({
+        "url": url,
+        "url_hash": url_hash,
+        "versions": versions,
+        "_links": {
+            "self": { "href": format!("/api/urls/{}/versions?limit={}&offset={}", url_hash, limit, offset) },
+            "next": { "href": format!("/api/urls/{}/versions?limit={}&offset={}", url_hash, limit, offset + limit) },
+            "diff": { "href": format!("/api/urls/{}/diff", url_hash) }
+        }
+    });
+
+    Ok(Json(response))
+}
+
+/// Diff the stored Markdown of two versions of a URL, by default the latest against the one before it
+pub async fn diff_versions(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+    Query(params): Query<DiffQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let url_hash = parse_url_hash(&hash)?;
+    let url = get_url(&state, &url_hash).await?;
+
+    let to = match &params.to {
+        Some(content_hash) => find_version(&state, &url_hash, content_hash).await?,
+        None => get_version(&state, &url_hash, VersionFilter::default()).await?
+            .ok_or_else(|| AppError::NotFound(format!("No pages found for URL hash: {}", url_hash)))?,
+    };
+
+    let from = match &params.from {
+        Some(content_hash) => find_version(&state, &url_hash, content_hash).await?,
+        None => get_version(&state, &url_hash, VersionFilter { before: Some(to.version), ..Default::default() }).await?
+            .ok_or_else(|| AppError::InvalidInput(format!("No version of {} precedes {}", url, to.content_hash)))?,
+    };
+
+    let (old, new) = tokio::try_join!(get_markdown(&state, &from), get_markdown(&state, &to))?;
+
+    // Diffing whole documents takes a while, so it runs on a blocking thread
+    let (mode, context) = (params.mode, params.context.unwrap_or(DEFAULT_CONTEXT_LINES));
+    let (from_label, to_label) = (version_label(&from), version_label(&to));
+
+    let (diff, insertions, deletions) = tokio::task::spawn_blocking(move || match mode {
+        DiffMode::Unified => {
+            let diff = TextDiff::from_lines(&old, &new);
+            let (insertions, deletions) = count_changes(&diff);
+            let unified = diff
+                .unified_diff()
+                .context_radius(context)
+                .header(&from_label, &to_label)
+                .to_string();
+
+            (serde_json::Value::String(unified), insertions, deletions)
+        },
+        DiffMode::Words => {
+            let diff = TextDiff::from_words(&old, &new);
+            let (insertions, deletions) = count_changes(&diff);
+
+            (serde_json::json!(word_changes(&diff)), insertions, deletions)
+        }
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Failed to diff versions: {}", e)))?;
+
+    let response = serde_json::json!({
+        "url": url,
+        "url_hash": url_hash,
+        "from": from,
+        "to": to,
+        "mode": match params.mode {
+            DiffMode::Unified => "unified",
+            DiffMode::Words => "words",
+        },
+        "insertions": insertions,
+        "deletions": deletions,
+        "diff": diff,
+        "_links": {
+            "self": { "href": format!("/api/urls/{}/diff?from={}&to={}", url_hash, from.content_hash, to.content_hash) },
+            "versions": { "href": format!("/api/urls/{}/versions", url_hash) },
+            "from": { "href": format!("/api/pages/{}/markdown", from.page_id) },
+            "to": { "href": format!("/api/pages/{}/markdown", to.page_id) }
+        }
+    });
+
+    Ok(Json(response))
+}
+
+fn parse_url_hash(hash: &str) -> Result<String, AppError> {
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::InvalidInput(format!("Invalid URL hash: {}", hash)));
+    }
+
+    Ok(hash.to_ascii_lowercase())
+}
+
+/// Successful crawls of a URL collapsed into versions, as the CTE `versions`.
+///
+/// A version is a run of consecutive crawls with the same content hash,
+/// numbered from 1 for the oldest, and refers to its latest page. Binds the
+/// URL hash.
+const VERSIONS: &str = r#"
+    WITH crawls AS (
+        SELECT id, job_id, content_hash, crawled_at, markdown_storage_path,
+            content_hash IS DISTINCT FROM LAG(content_hash) OVER (ORDER BY crawled_at, id) AS changed
+        FROM pages
+        WHERE url_hash = $1 AND error_message IS NULL AND content_hash <> ''
+    ),
+    numbered AS (
+        SELECT *, SUM(changed::INTEGER) OVER (ORDER BY crawled_at, id) AS version
+        FROM crawls
+    ),
+    versions AS (
+        SELECT version::BIGINT AS version, content_hash,
+            MIN(crawled_at) AS first_seen_at,
+            MAX(crawled_at) AS last_seen_at,
+            COUNT(*) AS crawls,
+            (ARRAY_AGG(id ORDER BY crawled_at DESC, id DESC))[1] AS page_id,
+            (ARRAY_AGG(job_id ORDER BY crawled_at DESC, id DESC))[1] AS job_id,
+            (ARRAY_AGG(markdown_storage_path ORDER BY crawled_at DESC, id DESC)
+                FILTER (WHERE markdown_storage_path IS NOT NULL))[1] AS markdown_storage_path
+        FROM numbered
+        GROUP BY version, content_hash
+    )
+"#;
+
+/// Which versions to load: those with a content hash, those before a version, or all of them
+#[derive(Debug, Default)]
+struct VersionFilter<'a> {
+    content_hash: Option<&'a str>,
+    before: Option<i64>,
+}
+
+/// The URL a hash stands for, as recorded by its first successful crawl
+async fn get_url(state: &AppState, url_hash: &str) -> Result<String, AppError> {
+    sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT normalized_url FROM pages
+        WHERE url_hash = $1 AND error_message IS NULL AND content_hash <> ''
+        ORDER BY crawled_at
+        LIMIT 1
+        "#
+    )
+    .bind(url_hash)
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(AppError::from)?
+    .ok_or_else(|| AppError::NotFound(format!("No pages found for URL hash: {}", url_hash)))
+}
+
+/// Load a page of the versions of a URL, most recent first
+async fn get_versions(
+    state: &AppState,
+    url_hash: &str,
+    filter: VersionFilter<'_>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<PageVersion>, AppError> {
+    let sql = format!(
+        r#"
+        {VERSIONS}
+        SELECT * FROM versions
+        WHERE ($2::TEXT IS NULL OR LOWER(content_hash) = LOWER($2))
+            AND ($3::BIGINT IS NULL OR version < $3)
+        ORDER BY version DESC
+        LIMIT $4 OFFSET $5
+        "#
+    );
+
+    let rows = sqlx::query(&sql)
+        .bind(url_hash)
+        .bind(filter.content_hash)
+        .bind(filter.before)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.db_pool)
+        .await
+        .map_err(AppError::from)?;
+
+    Ok(rows
+        .iter()
+        .map(|row| PageVersion {
+            version: row.get("version"),
+            content_hash: row.get("content_hash"),
+            first_seen_at: row.get("first_seen_at"),
+            last_seen_at: row.get("last_seen_at"),
+            crawls: row.get("crawls"),
+            page_id: row.get("page_id"),
+            job_id: row.get("job_id"),
+            markdown_storage_path: row.get("markdown_storage_path"),
+        })
+        .collect())
+}
+
+/// The most recent version matching a filter
+async fn get_version(state: &AppState, url_hash: &str, filter: VersionFilter<'_>) -> Result<Option<PageVersion>, AppError> {
+    Ok(get_versions(state, url_hash, filter, 1, 0).await?.pop())
+}
+
+/// The most recent version with the given content hash, since content can change back to an earlier version
+async fn find_version(state: &AppState, url_hash: &str, content_hash: &str) -> Result<PageVersion, AppError> {
+    get_version(state, url_hash, VersionFilter { content_hash: Some(content_hash), ..Default::default() }).await?
+        .ok_or_else(|| AppError::NotFound(format!("Version not found: {}", content_hash)))
+}
+
+async fn get_markdown(state: &AppState, version: &PageVersion) -> Result<String, AppError> {
+    let path = version.markdown_storage_path.as_deref().ok_or_else(|| {
+        AppError::NotFound(format!("Markdown content not available for version {}", version.content_hash))
+    })?;
+
+    state.storage_client.get_object(path).await
+        .map_err(|e| AppError::Internal(format!("Failed to retrieve Markdown content: {}", e)))
+}
+
+fn version_label(version: &PageVersion) -> String {
+    format!("{} {}", version.content_hash, version.last_seen_at.to_rfc3339())
+}
+
+fn count_changes<'a>(diff: &TextDiff<'a, 'a, '_, str>) -> (usize, usize) {
+    diff.iter_all_changes().fold((0, 0), |(insertions, deletions), change| match change.tag() {
+        ChangeTag::Insert => (insertions + 1, deletions),
+        ChangeTag::Delete => (insertions, deletions + 1),
+        ChangeTag::Equal => (insertions, deletions),
+    })
+}
+
+/// Word-level changes with consecutive words of the same kind merged into one span
+fn word_changes<'a>(diff: &TextDiff<'a, 'a, '_, str>) -> Vec<serde_json::Value> {
+    let mut spans: Vec<(ChangeTag, String)> = Vec::new();
+
+    for change in diff.iter_all_changes() {
+        match spans.last_mut() {
+            Some((tag, text)) if *tag == change.tag() => text.push_str(change.value()),
+            _ => spans.push((change.tag(), change.value().to_string())),
+        }
+    }
+
+    spans
+        .into_iter()
+        .map(|(tag, text)| {
+            let op = match tag {
+                ChangeTag::Equal => "equal",
+                ChangeTag::Insert => "insert",
+                ChangeTag::Delete => "delete",
+            };
+
+            serde_json::json!({ "op": op, "text": text })
+        })
+        .collect()
+}
@@ -9,7 +9,9 @@ use sqlx::Row;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
-use crate::domain::webhook::{Webhook, WebhookEventType};
+use crate::application::webhook::service::WebhookService;
+use crate::application::webhook::signature;
+use crate::domain::webhook::{Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookEventType};
 use crate::utils::error::AppError;
 use crate::api::routes::AppState;
 
@@ -27,6 +29,7 @@ pub struct CreateWebhookRequest {
     events: Vec<String>,
     headers: Option<serde_json::Value>,
     description: Option<String>,
+    secret: Option<String>,
     is_active: Option<bool>,
 }
 
@@ -39,6 +42,23 @@ pub struct UpdateWebhookRequest {
     is_active: Option<bool>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RotateSecretRequest {
+    secret: Option<String>,
+    grace_period_seconds: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListDeliveriesQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+    status: Option<String>,
+    event_type: Option<String>,
+}
+
+/// How long the previous secret keeps signing deliveries after a rotation by default
+const DEFAULT_SECRET_GRACE_PERIOD_SECONDS: i64 = 24 * 60 * 60;
+
 pub async fn list_webhooks(
     State(state): State<AppState>,
     Query(params): Query<ListWebhooksQuery>,
@@ -47,7 +67,8 @@ pub async fn list_webhooks(
     let offset = params.offset.unwrap_or(0);
     
     // Build the query based on parameters
-    let mut query = "SELECT id, name, url, event_types, secret, active, created_at, updated_at, headers 
+    let mut query = "SELECT id, name, url, event_types, secret, previous_secret, previous_secret_expires_at,
+                    active, created_at, updated_at, headers 
                     FROM webhooks".to_string();
     
     if let Some(config_id) = params.config_id {
@@ -70,25 +91,14 @@ pub async fn list_webhooks(
         let url: String = row.get("url");
         let event_types_raw: Vec<String> = row.get("event_types");
         let secret: Option<String> = row.get("secret");
+        let previous_secret: Option<String> = row.get("previous_secret");
+        let previous_secret_expires_at: Option<DateTime<Utc>> = row.get("previous_secret_expires_at");
         let active: bool = row.get("active");
         let created_at: DateTime<Utc> = row.get("created_at");
         let updated_at: DateTime<Utc> = row.get("updated_at");
         let headers: serde_json::Value = row.get("headers");
         
-        // Convert event_types from text[] to Vec<WebhookEventType>
-        let event_types = event_types_raw.iter()
-            .map(|s| match s.as_str() {
-                "job.created" => WebhookEventType::JobCreated,
-                "job.started" => WebhookEventType::JobStarted,
-                "job.completed" => WebhookEventType::JobCompleted,
-                "job.failed" => WebhookEventType::JobFailed,
-                "job.cancelled" => WebhookEventType::JobCancelled,
-                "page.crawled" => WebhookEventType::PageCrawled,
-                "page.failed" => WebhookEventType::PageFailed,
-                "content.changed" => WebhookEventType::ContentChanged,
-                _ => WebhookEventType::JobCreated, // Default
-            })
-            .collect::<Vec<_>>();
+        let event_types = stored_event_types(&event_types_raw);
         
         Webhook {
             id,
@@ -96,6 +106,8 @@ pub async fn list_webhooks(
             url,
             event_types,
             secret,
+            previous_secret,
+            previous_secret_expires_at,
             active,
             created_at,
             updated_at,
@@ -121,7 +133,8 @@ pub async fn get_webhook(
         r#"
         SELECT 
             id, name, url, event_types, 
-            secret, active, created_at, updated_at, headers as "headers: serde_json::Value"
+            secret, previous_secret, previous_secret_expires_at,
+            active, created_at, updated_at, headers as "headers: serde_json::Value"
         FROM webhooks
         WHERE id = $1
         "#,
@@ -132,21 +145,7 @@ pub async fn get_webhook(
     .map_err(AppError::from)?
     .ok_or_else(|| AppError::NotFound(format!("Webhook not found: {}", id)))?;
     
-    // Convert event_types from text[] to Vec<WebhookEventType>
-    let event_types: Vec<WebhookEventType> = webhook.event_types
-        .iter()
-        .map(|s| match s.as_str() {
-            "job.created" => WebhookEventType::JobCreated,
-            "job.started" => WebhookEventType::JobStarted,
-            "job.completed" => WebhookEventType::JobCompleted,
-            "job.failed" => WebhookEventType::JobFailed,
-            "job.cancelled" => WebhookEventType::JobCancelled,
-            "page.crawled" => WebhookEventType::PageCrawled,
-            "page.failed" => WebhookEventType::PageFailed,
-            "content.changed" => WebhookEventType::ContentChanged,
-            _ => WebhookEventType::JobCreated, // Default
-        })
-        .collect();
+    let event_types = stored_event_types(&webhook.event_types);
     
     // Construct a Webhook manually
     let webhook_obj = Webhook {
@@ -155,6 +154,8 @@ pub async fn get_webhook(
         url: webhook.url,
         event_types,
         secret: webhook.secret,
+        previous_secret: webhook.previous_secret,
+        previous_secret_expires_at: webhook.previous_secret_expires_at,
         active: webhook.active,
         created_at: webhook.created_at,
         updated_at: webhook.updated_at,
@@ -175,10 +176,7 @@ pub async fn create_webhook(
     State(state): State<AppState>,
     Json(payload): Json<CreateWebhookRequest>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    // Convert events to strings
-    let event_types: Vec<String> = payload.events.iter()
-        .map(|e| e.to_string())
-        .collect();
+    let event_types = parse_events(&payload.events)?;
     
     // Create the webhook
     let webhook_id = Uuid::new_v4();
@@ -187,35 +185,22 @@ pub async fn create_webhook(
         INSERT INTO webhooks (id, name, url, event_types, headers, secret, active, created_at, updated_at)
         VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW())
         RETURNING id, name, url, event_types, 
-                  secret, active, created_at, updated_at, headers as "headers: serde_json::Value"
+                  secret, previous_secret, previous_secret_expires_at,
+                  active, created_at, updated_at, headers as "headers: serde_json::Value"
         "#,
         webhook_id,
-        payload.config_id.to_string(),
+        payload.description.unwrap_or_else(|| payload.config_id.to_string()),
         payload.url,
         &event_types as &[String],
         payload.headers.unwrap_or(serde_json::json!({})),
-        payload.description,
+        payload.secret.unwrap_or_else(signature::generate_secret),
         payload.is_active.unwrap_or(true)
     )
     .fetch_one(&state.db_pool)
     .await
     .map_err(AppError::from)?;
     
-    // Convert event_types from text[] to Vec<WebhookEventType>
-    let event_types: Vec<WebhookEventType> = webhook_raw.event_types
-        .iter()
-        .map(|s| match s.as_str() {
-            "job.created" => WebhookEventType::JobCreated,
-            "job.started" => WebhookEventType::JobStarted,
-            "job.completed" => WebhookEventType::JobCompleted,
-            "job.failed" => WebhookEventType::JobFailed,
-            "job.cancelled" => WebhookEventType::JobCancelled,
-            "page.crawled" => WebhookEventType::PageCrawled,
-            "page.failed" => WebhookEventType::PageFailed,
-            "content.changed" => WebhookEventType::ContentChanged,
-            _ => WebhookEventType::JobCreated, // Default
-        })
-        .collect();
+    let event_types = stored_event_types(&webhook_raw.event_types);
     
     // Construct a Webhook manually
     let webhook = Webhook {
@@ -224,6 +209,8 @@ pub async fn create_webhook(
         url: webhook_raw.url,
         event_types,
         secret: webhook_raw.secret,
+        previous_secret: webhook_raw.previous_secret,
+        previous_secret_expires_at: webhook_raw.previous_secret_expires_at,
         active: webhook_raw.active,
         created_at: webhook_raw.created_at,
         updated_at: webhook_raw.updated_at,
@@ -232,6 +219,7 @@ pub async fn create_webhook(
     
     let response = serde_json::json!({
         "webhook": webhook,
+        "secret": webhook.secret,
         "_links": {
             "self": { "href": format!("/api/webhooks/{}", webhook.id) }
         }
@@ -256,10 +244,7 @@ pub async fn update_webhook(
         return Err(AppError::NotFound(format!("Webhook not found: {}", id)));
     }
     
-    // Convert events to strings if provided
-    let event_types = payload.events.as_ref().map(|events| {
-        events.iter().map(|e| e.to_string()).collect::<Vec<String>>()
-    });
+    let event_types = payload.events.as_deref().map(parse_events).transpose()?;
     
     // Update the webhook
     let updated_webhook_raw = sqlx::query!(
@@ -268,12 +253,13 @@ pub async fn update_webhook(
         SET url = COALESCE($1, url),
             event_types = COALESCE($2, event_types),
             headers = COALESCE($3, headers),
-            secret = COALESCE($4, secret),
+            name = COALESCE($4, name),
             active = COALESCE($5, active),
             updated_at = NOW()
         WHERE id = $6
         RETURNING id, name, url, event_types, 
-                  secret, active, created_at, updated_at, headers as "headers: serde_json::Value"
+                  secret, previous_secret, previous_secret_expires_at,
+                  active, created_at, updated_at, headers as "headers: serde_json::Value"
         "#,
         payload.url,
         event_types.as_deref(),
@@ -286,21 +272,7 @@ pub async fn update_webhook(
     .await
     .map_err(AppError::from)?;
     
-    // Convert event_types from text[] to Vec<WebhookEventType>
-    let event_types: Vec<WebhookEventType> = updated_webhook_raw.event_types
-        .iter()
-        .map(|s| match s.as_str() {
-            "job.created" => WebhookEventType::JobCreated,
-            "job.started" => WebhookEventType::JobStarted,
-            "job.completed" => WebhookEventType::JobCompleted,
-            "job.failed" => WebhookEventType::JobFailed,
-            "job.cancelled" => WebhookEventType::JobCancelled,
-            "page.crawled" => WebhookEventType::PageCrawled,
-            "page.failed" => WebhookEventType::PageFailed,
-            "content.changed" => WebhookEventType::ContentChanged,
-            _ => WebhookEventType::JobCreated, // Default
-        })
-        .collect();
+    let event_types = stored_event_types(&updated_webhook_raw.event_types);
     
     // Construct a Webhook manually
     let updated_webhook = Webhook {
@@ -309,6 +281,8 @@ pub async fn update_webhook(
         url: updated_webhook_raw.url,
         event_types,
         secret: updated_webhook_raw.secret,
+        previous_secret: updated_webhook_raw.previous_secret,
+        previous_secret_expires_at: updated_webhook_raw.previous_secret_expires_at,
         active: updated_webhook_raw.active,
         created_at: updated_webhook_raw.created_at,
         updated_at: updated_webhook_raw.updated_at,
@@ -347,4 +321,212 @@ pub async fn delete_webhook(
         .map_err(AppError::from)?;
     
     Ok(StatusCode::NO_CONTENT)
-} 
\ No newline at end of file
+}
+
+pub async fn rotate_webhook_secret(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<RotateSecretRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let mut webhook = fetch_webhook(&state.db_pool, id).await?;
+    
+    let grace_period_seconds = payload.grace_period_seconds.unwrap_or(DEFAULT_SECRET_GRACE_PERIOD_SECONDS);
+    if grace_period_seconds < 0 {
+        return Err(AppError::InvalidInput("grace_period_seconds must not be negative".to_string()));
+    }
+    
+    webhook.rotate_secret(payload.secret.unwrap_or_else(signature::generate_secret), grace_period_seconds);
+    
+    sqlx::query(
+        r#"
+        UPDATE webhooks
+        SET secret = $1, previous_secret = $2, previous_secret_expires_at = $3, updated_at = $4
+        WHERE id = $5
+        "#
+    )
+    .bind(&webhook.secret)
+    .bind(&webhook.previous_secret)
+    .bind(webhook.previous_secret_expires_at)
+    .bind(webhook.updated_at)
+    .bind(webhook.id)
+    .execute(&state.db_pool)
+    .await
+    .map_err(AppError::from)?;
+    
+    let response = serde_json::json!({
+        "webhook": webhook,
+        "secret": webhook.secret,
+        "_links": {
+            "self": { "href": format!("/api/webhooks/{}", webhook.id) }
+        }
+    });
+    
+    Ok(Json(response))
+}
+
+pub async fn list_deliveries(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<ListDeliveriesQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let limit = params.limit.unwrap_or(10);
+    let offset = params.offset.unwrap_or(0);
+    
+    // Make sure the webhook exists so an unknown id is a 404 rather than an empty list
+    fetch_webhook(&state.db_pool, id).await?;
+    
+    let mut query_builder = sqlx::QueryBuilder::new("SELECT * FROM webhook_deliveries WHERE webhook_id = ");
+    query_builder.push_bind(id);
+    
+    if let Some(status) = &params.status {
+        let status: WebhookDeliveryStatus = status.parse().map_err(AppError::InvalidInput)?;
+        query_builder.push(" AND status = ");
+        query_builder.push_bind(status.to_string());
+    }
+    
+    if let Some(event_type) = &params.event_type {
+        let event_type: WebhookEventType = event_type.parse().map_err(AppError::InvalidInput)?;
+        query_builder.push(" AND event_type = ");
+        query_builder.push_bind(event_type.to_string());
+    }
+    
+    query_builder.push(" ORDER BY created_at DESC LIMIT ");
+    query_builder.push_bind(limit);
+    query_builder.push(" OFFSET ");
+    query_builder.push_bind(offset);
+    
+    let deliveries = query_builder
+        .build_query_as::<WebhookDelivery>()
+        .fetch_all(&state.db_pool)
+        .await
+        .map_err(AppError::from)?;
+    
+    let response = serde_json::json!({
+        "deliveries": deliveries,
+        "_links": {
+            "self": { "href": format!("/api/webhooks/{}/deliveries", id) },
+            "webhook": { "href": format!("/api/webhooks/{}", id) }
+        }
+    });
+    
+    Ok(Json(response))
+}
+
+pub async fn get_delivery(
+    State(state): State<AppState>,
+    Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let delivery = fetch_delivery(&state.db_pool, id, delivery_id).await?;
+    
+    let response = serde_json::json!({
+        "delivery": delivery,
+        "_links": {
+            "self": { "href": format!("/api/webhooks/{}/deliveries/{}", id, delivery_id) },
+            "redeliver": { "href": format!("/api/webhooks/{}/deliveries/{}/redeliver", id, delivery_id) },
+            "webhook": { "href": format!("/api/webhooks/{}", id) }
+        }
+    });
+    
+    Ok(Json(response))
+}
+
+pub async fn redeliver(
+    State(state): State<AppState>,
+    Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let webhook = fetch_webhook(&state.db_pool, id).await?;
+    
+    let original = fetch_delivery(&state.db_pool, id, delivery_id).await?;
+    
+    let delivery = send_now(&state, &webhook, WebhookService::build_redelivery(&original)).await?;
+    
+    let response = serde_json::json!({
+        "delivery": delivery,
+        "_links": {
+            "self": { "href": format!("/api/webhooks/{}/deliveries/{}", id, delivery.id) },
+            "original": { "href": format!("/api/webhooks/{}/deliveries/{}", id, delivery_id) },
+            "deliveries": { "href": format!("/api/webhooks/{}/deliveries", id) }
+        }
+    });
+    
+    Ok(Json(response))
+}
+
+pub async fn ping_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let webhook = fetch_webhook(&state.db_pool, id).await?;
+    
+    let delivery = send_now(&state, &webhook, WebhookService::build_ping(&webhook)).await?;
+    
+    let response = serde_json::json!({
+        "delivery": delivery,
+        "_links": {
+            "self": { "href": format!("/api/webhooks/{}/deliveries/{}", id, delivery.id) },
+            "webhook": { "href": format!("/api/webhooks/{}", id) },
+            "deliveries": { "href": format!("/api/webhooks/{}/deliveries", id) }
+        }
+    });
+    
+    Ok(Json(response))
+}
+
+/// Record a delivery and send it synchronously so the caller sees the receiver's response.
+///
+/// The delivery is recorded with a lease so the background dispatcher leaves it
+/// alone; if the attempt fails it is retried on the normal backoff schedule.
+async fn send_now(
+    state: &AppState,
+    webhook: &Webhook,
+    mut delivery: WebhookDelivery,
+) -> Result<WebhookDelivery, AppError> {
+    delivery.next_retry_at = Some(state.webhook_dispatcher.lease_until());
+    state.webhook_service.record_delivery(&delivery).await?;
+    state.webhook_dispatcher.deliver(webhook, &mut delivery).await?;
+    Ok(delivery)
+}
+
+/// Check the events a webhook subscribes to, returning their names as stored
+fn parse_events(events: &[String]) -> Result<Vec<String>, AppError> {
+    events
+        .iter()
+        .map(|event| match event.parse().map_err(AppError::InvalidInput)? {
+            WebhookEventType::Ping => Err(AppError::InvalidInput("ping is sent on demand and can't be subscribed to".to_string())),
+            event_type => Ok(event_type.to_string()),
+        })
+        .collect()
+}
+
+/// The event types of a webhook row, skipping unknown names like `Webhook::from_row`
+fn stored_event_types(event_types: &[String]) -> Vec<WebhookEventType> {
+    event_types.iter().filter_map(|event| event.parse().ok()).collect()
+}
+
+async fn fetch_webhook(db_pool: &PgPool, id: Uuid) -> Result<Webhook, AppError> {
+    sqlx::query_as::<_, Webhook>(
+        r#"
+        SELECT id, name, url, event_types, secret, previous_secret, previous_secret_expires_at,
+               active, created_at, updated_at, headers
+        FROM webhooks
+        WHERE id = $1
+        "#
+    )
+    .bind(id)
+    .fetch_optional(db_pool)
+    .await
+    .map_err(AppError::from)?
+    .ok_or_else(|| AppError::NotFound(format!("Webhook not found: {}", id)))
+}
+
+async fn fetch_delivery(db_pool: &PgPool, webhook_id: Uuid, delivery_id: Uuid) -> Result<WebhookDelivery, AppError> {
+    sqlx::query_as::<_, WebhookDelivery>(
+        "SELECT * FROM webhook_deliveries WHERE id = $1 AND webhook_id = $2"
+    )
+    .bind(delivery_id)
+    .bind(webhook_id)
+    .fetch_optional(db_pool)
+    .await
+    .map_err(AppError::from)?
+    .ok_or_else(|| AppError::NotFound(format!("Delivery not found: {}", delivery_id)))
+}
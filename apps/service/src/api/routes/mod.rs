@@ -11,8 +11,10 @@ use crate::api::handlers;
 use crate::application::scraper::service::ScraperService;
 use crate::application::scraper::worker::ScraperWorker;
 use crate::application::scheduler::service::SchedulerService;
+use crate::application::webhook::dispatcher::WebhookDispatcher;
+use crate::application::webhook::service::WebhookService;
 use crate::infrastructure::queue::redis_queue::RedisJobQueue;
-use crate::infrastructure::storage::s3_client::S3StorageClient;
+use crate::infrastructure::storage::StorageClient;
 use crate::infrastructure::queue::redis_client::RedisClient;
 
 pub mod health;
@@ -23,23 +25,27 @@ pub struct AppState {
     pub db_pool: PgPool,
     pub scraper_service: Arc<ScraperService>,
     pub job_queue: Arc<RedisJobQueue>,
-    pub storage_client: Arc<S3StorageClient>,
+    pub storage_client: Arc<dyn StorageClient>,
     pub scraper_worker: Arc<ScraperWorker>,
     pub scheduler: Arc<SchedulerService>,
     pub redis_client: Arc<RedisClient>,
+    pub webhook_service: Arc<WebhookService>,
+    pub webhook_dispatcher: Arc<WebhookDispatcher>,
 }
 
 pub async fn serve(
     port: u16,
     db_pool: PgPool,
     job_queue: Arc<RedisJobQueue>,
-    storage_client: Arc<S3StorageClient>,
+    storage_client: Arc<dyn StorageClient>,
     scraper_worker: Arc<ScraperWorker>,
     scheduler: Arc<SchedulerService>,
     redis_client: Arc<RedisClient>,
+    webhook_service: Arc<WebhookService>,
+    webhook_dispatcher: Arc<WebhookDispatcher>,
 ) -> anyhow::Result<()> {
     // Create services
-    let scraper_service = Arc::new(ScraperService::new(db_pool.clone(), job_queue.clone()));
+    let scraper_service = Arc::new(ScraperService::new(db_pool.clone(), job_queue.clone(), webhook_service.clone()));
     
     // Create shared state
     let state = AppState {
@@ -50,6 +56,8 @@ pub async fn serve(
         scraper_worker,
         scheduler,
         redis_client,
+        webhook_service,
+        webhook_dispatcher,
     };
     
     // Build the router with state
@@ -63,17 +71,37 @@ pub async fn serve(
         .route("/api/configs/{id}", get(handlers::configs::get_config))
         .route("/api/configs/{id}", put(handlers::configs::update_config))
         .route("/api/configs/{id}/start", post(handlers::configs::start_job))
+        .route("/api/configs/{id}/dry-run", post(handlers::configs::dry_run))
+        .route("/api/configs/{id}/retention", get(handlers::configs::retention_report))
         
         // Job routes
         .route("/api/jobs", get(handlers::jobs::list_jobs))
         .route("/api/jobs/{id}", get(handlers::jobs::get_job))
         .route("/api/jobs/{id}/cancel", post(handlers::jobs::cancel_job))
+        .route("/api/jobs/{id}/pause", post(handlers::jobs::pause_job))
+        .route("/api/jobs/{id}/resume", post(handlers::jobs::resume_job))
+        .route("/api/jobs/{id}/export", get(handlers::jobs::export_job))
+        
+        // Queue routes
+        .route("/api/queue/dead-letter", get(handlers::queue::list_dead_letters))
+        .route("/api/queue/dead-letter/{id}", delete(handlers::queue::delete_dead_letter))
+        .route("/api/queue/dead-letter/{id}/retry", post(handlers::queue::retry_dead_letter))
         
         // Page routes
         .route("/api/pages", get(handlers::pages::list_pages))
         .route("/api/pages/{id}", get(handlers::pages::get_page))
         .route("/api/pages/{id}/html", get(handlers::pages::get_page_html))
         .route("/api/pages/{id}/markdown", get(handlers::pages::get_page_markdown))
+        .route("/api/pages/{id}/document", get(handlers::pages::get_page_document))
+        .route("/api/pages/{id}/links", get(handlers::pages::get_page_links))
+        .route("/api/pages/{id}/citations", get(handlers::pages::get_page_citations))
+        
+        // Citation routes
+        .route("/api/citations", get(handlers::citations::search_citations))
+        
+        // URL version routes
+        .route("/api/urls/{hash}/versions", get(handlers::urls::list_versions))
+        .route("/api/urls/{hash}/diff", get(handlers::urls::diff_versions))
         
         // Webhook routes
         .route("/api/webhooks", get(handlers::webhooks::list_webhooks))
@@ -81,6 +109,11 @@ pub async fn serve(
         .route("/api/webhooks/{id}", get(handlers::webhooks::get_webhook))
         .route("/api/webhooks/{id}", put(handlers::webhooks::update_webhook))
         .route("/api/webhooks/{id}", delete(handlers::webhooks::delete_webhook))
+        .route("/api/webhooks/{id}/rotate-secret", post(handlers::webhooks::rotate_webhook_secret))
+        .route("/api/webhooks/{id}/deliveries", get(handlers::webhooks::list_deliveries))
+        .route("/api/webhooks/{id}/deliveries/{delivery_id}", get(handlers::webhooks::get_delivery))
+        .route("/api/webhooks/{id}/deliveries/{delivery_id}/redeliver", post(handlers::webhooks::redeliver))
+        .route("/api/webhooks/{id}/ping", post(handlers::webhooks::ping_webhook))
         
         // Analytics routes
         .route("/api/analytics/jobs", get(handlers::analytics::get_job_stats))
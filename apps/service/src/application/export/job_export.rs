@@ -0,0 +1,496 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
+use sqlx::{PgPool, Row};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+use zip::write::{SimpleFileOptions, StreamWriter};
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::domain::job::Job;
+use crate::infrastructure::storage::compression::{self, ContentEncoding};
+use crate::infrastructure::storage::{ObjectStream, StorageClient};
+
+/// Pages read from the database at a time
+const PAGE_BATCH_SIZE: i64 = 100;
+
+/// Chunks of the export waiting to be sent before reading more pages
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Format of a job export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum ExportFormat {
+    /// A zip archive with a directory per page
+    #[default]
+    #[serde(rename = "zip")]
+    Zip,
+    /// One JSON object per line: the manifest, then each page with its content
+    #[serde(rename = "jsonl")]
+    Jsonl,
+    /// A zstd compressed tar archive laid out like the zip archive
+    #[serde(rename = "tar.zst")]
+    TarZst,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Zip => "zip",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::TarZst => "tar.zst",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Zip => "application/zip",
+            ExportFormat::Jsonl => "application/x-ndjson",
+            ExportFormat::TarZst => "application/zstd",
+        }
+    }
+}
+
+/// Bytes written by an archive writer, handed on to the response after each page
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Bytes {
+        Bytes::from(std::mem::take(&mut *self.0.lock().unwrap()))
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Archive {
+    Zip(Box<ZipWriter<StreamWriter<SharedBuffer>>>),
+    Tar(tar::Builder<SharedBuffer>),
+    Jsonl,
+}
+
+impl Archive {
+    fn add_file(&mut self, path: &str, content: &[u8], modified_at: DateTime<Utc>) -> Result<()> {
+        match self {
+            Archive::Zip(zip) => {
+                let options = SimpleFileOptions::default()
+                    .compression_method(CompressionMethod::Deflated)
+                    .large_file(content.len() as u64 >= u32::MAX as u64);
+                zip.start_file(path, options)?;
+                zip.write_all(content)?;
+            },
+            Archive::Tar(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(content.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(modified_at.timestamp().max(0) as u64);
+                tar.append_data(&mut header, path, content)?;
+            },
+            Archive::Jsonl => return Err(anyhow!("A JSON Lines export has no files")),
+        }
+
+        Ok(())
+    }
+
+    /// Write files on a blocking thread, as deflating them is CPU bound
+    async fn add_files(mut self, files: Vec<(String, Vec<u8>)>, modified_at: DateTime<Utc>) -> Result<Self> {
+        tokio::task::spawn_blocking(move || {
+            for (path, content) in &files {
+                self.add_file(path, content, modified_at)?;
+            }
+
+            Ok(self)
+        })
+        .await?
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Archive::Zip(zip) => {
+                zip.finish()?;
+            },
+            Archive::Tar(tar) => {
+                tar.into_inner()?;
+            },
+            Archive::Jsonl => {},
+        }
+
+        Ok(())
+    }
+}
+
+/// Streams all pages of a job with their stored HTML and Markdown into one archive.
+///
+/// Pages are read a batch at a time and written as they are read, so an
+/// export holds the content of one page in memory however large the job is;
+/// only the manifest, a short entry per page, grows with the job. Archives hold
+/// `pages/{page_id}/page.json` with the page's metadata, `content.html` and
+/// `content.md`, and end with a `manifest.json` listing every page and any
+/// content that could not be read.
+#[derive(Clone)]
+pub struct JobExporter {
+    db_pool: PgPool,
+    storage_client: Arc<dyn StorageClient>,
+}
+
+impl JobExporter {
+    pub fn new(db_pool: PgPool, storage_client: Arc<dyn StorageClient>) -> Self {
+        Self { db_pool, storage_client }
+    }
+
+    /// Start exporting a job, returning the export as it is written.
+    ///
+    /// An error part way through ends the stream with that error, so the
+    /// client sees a truncated download rather than an archive missing pages.
+    pub fn export(&self, job: Job, format: ExportFormat) -> ObjectStream {
+        let (tx, rx) = mpsc::channel::<std::io::Result<Bytes>>(CHANNEL_CAPACITY);
+        let exporter = self.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = exporter.write(&job, format, &tx).await {
+                error!("Error exporting job {}: {}", job.id, e);
+                let _ = tx.send(Err(std::io::Error::other(e.to_string()))).await;
+            }
+        });
+
+        let body = stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (chunk, rx)) }).boxed();
+
+        match format {
+            ExportFormat::TarZst => compression::encode(body, ContentEncoding::Zstd),
+            _ => body,
+        }
+    }
+
+    async fn write(&self, job: &Job, format: ExportFormat, tx: &mpsc::Sender<std::io::Result<Bytes>>) -> Result<()> {
+        let buffer = SharedBuffer::default();
+        let mut archive = match format {
+            ExportFormat::Zip => Archive::Zip(Box::new(ZipWriter::new_stream(buffer.clone()))),
+            ExportFormat::TarZst => Archive::Tar(tar::Builder::new(buffer.clone())),
+            ExportFormat::Jsonl => Archive::Jsonl,
+        };
+
+        let exported_at = Utc::now();
+        let mut manifest_pages = Vec::new();
+
+        if format == ExportFormat::Jsonl {
+            let page_count: i64 = sqlx::query("SELECT COUNT(*) AS count FROM pages WHERE job_id = $1")
+                .bind(job.id)
+                .fetch_one(&self.db_pool)
+                .await?
+                .get("count");
+
+            let manifest = serde_json::json!({
+                "type": "manifest",
+                "job": job,
+                "format": format.extension(),
+                "exported_at": exported_at,
+                "page_count": page_count,
+            });
+            send(tx, json_line(&manifest)?).await?;
+        }
+
+        let mut after: Option<(DateTime<Utc>, Uuid)> = None;
+
+        loop {
+            let rows = sqlx::query(
+                r#"
+                SELECT id, job_id, url, normalized_url, url_hash, title, content_type, content_hash,
+                    http_status, http_headers, crawled_at, depth, parent_url, error_message, metadata,
+                    html_storage_path, markdown_storage_path
+                FROM pages
+                WHERE job_id = $1 AND ($2::TIMESTAMPTZ IS NULL OR (crawled_at, id) > ($2, $3))
+                ORDER BY crawled_at, id
+                LIMIT $4
+                "#
+            )
+            .bind(job.id)
+            .bind(after.map(|(crawled_at, _)| crawled_at))
+            .bind(after.map(|(_, id)| id))
+            .bind(PAGE_BATCH_SIZE)
+            .fetch_all(&self.db_pool)
+            .await?;
+
+            for row in &rows {
+                let page_id: Uuid = row.get("id");
+                let crawled_at: DateTime<Utc> = row.get("crawled_at");
+                after = Some((crawled_at, page_id));
+
+                let page = serde_json::json!({
+                    "id": page_id,
+                    "job_id": row.get::<Uuid, _>("job_id"),
+                    "url": row.get::<String, _>("url"),
+                    "normalized_url": row.get::<String, _>("normalized_url"),
+                    "url_hash": row.get::<String, _>("url_hash"),
+                    "title": row.get::<Option<String>, _>("title"),
+                    "content_type": row.get::<Option<String>, _>("content_type"),
+                    "content_hash": row.get::<String, _>("content_hash"),
+                    "http_status": row.get::<i32, _>("http_status"),
+                    "http_headers": row.get::<serde_json::Value, _>("http_headers"),
+                    "crawled_at": crawled_at,
+                    "depth": row.get::<i32, _>("depth"),
+                    "parent_url": row.get::<Option<String>, _>("parent_url"),
+                    "error_message": row.get::<Option<String>, _>("error_message"),
+                    "metadata": row.get::<serde_json::Value, _>("metadata"),
+                });
+
+                let mut missing = Vec::new();
+                let html = self.read_content(row.get("html_storage_path"), page_id, "html", &mut missing).await;
+                let markdown = self.read_content(row.get("markdown_storage_path"), page_id, "markdown", &mut missing).await;
+
+                if format == ExportFormat::Jsonl {
+                    let line = serde_json::json!({
+                        "type": "page",
+                        "page": page,
+                        "html": html.as_deref().map(String::from_utf8_lossy),
+                        "markdown": markdown.as_deref().map(String::from_utf8_lossy),
+                        "missing": missing,
+                    });
+                    send(tx, json_line(&line)?).await?;
+                    continue;
+                }
+
+                let directory = format!("pages/{}", page_id);
+                let mut files = vec![(format!("{}/page.json", directory), serde_json::to_vec_pretty(&page)?)];
+
+                if let Some(html) = html {
+                    files.push((format!("{}/content.html", directory), html));
+                }
+
+                if let Some(markdown) = markdown {
+                    files.push((format!("{}/content.md", directory), markdown));
+                }
+
+                manifest_pages.push(serde_json::json!({
+                    "id": page_id,
+                    "url": page["url"],
+                    "files": files.iter().map(|(path, _)| path.as_str()).collect::<Vec<_>>(),
+                    "missing": missing,
+                }));
+
+                archive = archive.add_files(files, crawled_at).await?;
+                send(tx, buffer.take()).await?;
+            }
+
+            if (rows.len() as i64) < PAGE_BATCH_SIZE {
+                break;
+            }
+        }
+
+        if format != ExportFormat::Jsonl {
+            let manifest = serde_json::json!({
+                "job": job,
+                "format": format.extension(),
+                "exported_at": exported_at,
+                "page_count": manifest_pages.len(),
+                "pages": manifest_pages,
+            });
+            let manifest = vec![("manifest.json".to_string(), serde_json::to_vec_pretty(&manifest)?)];
+            let archive = archive.add_files(manifest, exported_at).await?;
+            tokio::task::spawn_blocking(move || archive.finish()).await??;
+            send(tx, buffer.take()).await?;
+        }
+
+        debug!("Exported job {} as {}", job.id, format.extension());
+        Ok(())
+    }
+
+    /// Read a stored object of a page, noting it as missing if it can't be read
+    async fn read_content(&self, path: Option<String>, page_id: Uuid, kind: &str, missing: &mut Vec<String>) -> Option<Vec<u8>> {
+        let path = path?;
+
+        match self.storage_client.get_object_bytes(&path).await {
+            Ok(content) => Some(content),
+            Err(e) => {
+                warn!("Error reading the {} of page {} for export: {}", kind, page_id, e);
+                missing.push(kind.to_string());
+                None
+            }
+        }
+    }
+}
+
+fn json_line(value: &serde_json::Value) -> Result<Bytes> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    Ok(Bytes::from(line))
+}
+
+/// Hand a chunk to the response, failing once the client has gone so the export stops
+async fn send(tx: &mpsc::Sender<std::io::Result<Bytes>>, chunk: Bytes) -> Result<()> {
+    if chunk.is_empty() {
+        return Ok(());
+    }
+
+    tx.send(Ok(chunk)).await.map_err(|_| anyhow!("The client closed the connection"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::settings::Storage as StorageConfig;
+    use crate::infrastructure::storage::fs_client::FsStorageClient;
+    use std::collections::BTreeMap;
+    use std::io::{Cursor, Read};
+
+    /// Export a job with a fully stored page and one whose HTML has gone missing
+    async fn export(pool: &PgPool, format: ExportFormat) -> (Job, [Uuid; 2], Vec<u8>) {
+        let root = std::env::temp_dir().join(format!("job-export-{}", Uuid::new_v4()));
+        let storage_client = FsStorageClient::new(&StorageConfig::fs(root.to_str().unwrap())).await.unwrap();
+        storage_client.put_object("pages/a.html", b"<h1>A</h1>", "text/html").await.unwrap();
+        storage_client.put_object("pages/a.md", b"# A", "text/markdown").await.unwrap();
+
+        let config_id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO scraper_configs (id, name, base_url, include_patterns, exclude_patterns, max_depth, user_agent,
+                request_delay_ms, max_concurrent_requests, created_at, updated_at)
+            VALUES ($1, 'test', 'https://example.com/', '{}', '{}', 3, 'test', 0, 1, NOW(), NOW())
+            "#
+        )
+        .bind(config_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let job = Job::new(config_id, "default".to_string());
+        sqlx::query("INSERT INTO jobs (id, config_id, status, created_at, updated_at) VALUES ($1, $2, 'completed', NOW(), NOW())")
+            .bind(job.id)
+            .bind(config_id)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let page_ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let pages = [
+            (page_ids[0], "https://example.com/a", Some("pages/a.html"), Some("pages/a.md"), 2.0),
+            (page_ids[1], "https://example.com/b", Some("pages/gone.html"), None, 1.0),
+        ];
+
+        for (page_id, url, html_path, markdown_path, seconds_ago) in pages {
+            sqlx::query(
+                r#"
+                INSERT INTO pages (id, job_id, url, normalized_url, url_hash, content_hash, http_status, crawled_at, depth,
+                    html_storage_path, markdown_storage_path)
+                VALUES ($1, $2, $3, $3, $3, 'hash', 200, NOW() - make_interval(secs => $4), 0, $5, $6)
+                "#
+            )
+            .bind(page_id)
+            .bind(job.id)
+            .bind(url)
+            .bind(seconds_ago)
+            .bind(html_path)
+            .bind(markdown_path)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+
+        let exporter = JobExporter::new(pool.clone(), Arc::new(storage_client));
+        let mut body = exporter.export(job.clone(), format);
+        if format == ExportFormat::TarZst {
+            body = compression::decode(body, ContentEncoding::Zstd);
+        }
+
+        let mut content = Vec::new();
+        while let Some(chunk) = body.next().await {
+            content.extend_from_slice(&chunk.unwrap());
+        }
+
+        (job, page_ids, content)
+    }
+
+    fn assert_archive(files: BTreeMap<String, Vec<u8>>, job: &Job, [a, b]: [Uuid; 2]) {
+        let paths: Vec<String> = vec![
+            "manifest.json".to_string(),
+            format!("pages/{}/content.html", a),
+            format!("pages/{}/content.md", a),
+            format!("pages/{}/page.json", a),
+            format!("pages/{}/page.json", b),
+        ];
+        let mut expected = paths.clone();
+        expected.sort();
+        assert_eq!(files.keys().cloned().collect::<Vec<_>>(), expected);
+
+        assert_eq!(files[&paths[1]], b"<h1>A</h1>");
+        assert_eq!(files[&paths[2]], b"# A");
+
+        let page: serde_json::Value = serde_json::from_slice(&files[&paths[4]]).unwrap();
+        assert_eq!(page["url"], "https://example.com/b");
+
+        let manifest: serde_json::Value = serde_json::from_slice(&files["manifest.json"]).unwrap();
+        assert_eq!(manifest["job"]["id"], job.id.to_string());
+        assert_eq!(manifest["page_count"], 2);
+        assert_eq!(manifest["pages"][0]["id"], a.to_string());
+        assert_eq!(manifest["pages"][0]["files"].as_array().unwrap().len(), 3);
+        assert_eq!(manifest["pages"][1]["missing"], serde_json::json!(["html"]));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn exports_a_zip_archive(pool: PgPool) {
+        let (job, page_ids, content) = export(&pool, ExportFormat::Zip).await;
+
+        let mut zip = zip::ZipArchive::new(Cursor::new(content)).unwrap();
+        let mut files = BTreeMap::new();
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i).unwrap();
+            let mut content = Vec::new();
+            file.read_to_end(&mut content).unwrap();
+            files.insert(file.name().to_string(), content);
+        }
+
+        assert_archive(files, &job, page_ids);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn exports_a_tar_zst_archive(pool: PgPool) {
+        let (job, page_ids, content) = export(&pool, ExportFormat::TarZst).await;
+
+        let mut tar = tar::Archive::new(Cursor::new(content));
+        let mut files = BTreeMap::new();
+        for entry in tar.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content).unwrap();
+            files.insert(entry.path().unwrap().to_string_lossy().into_owned(), content);
+        }
+
+        assert_archive(files, &job, page_ids);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn exports_json_lines(pool: PgPool) {
+        let (job, [a, b], content) = export(&pool, ExportFormat::Jsonl).await;
+
+        let lines: Vec<serde_json::Value> = content
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+
+        assert_eq!(lines[0]["type"], "manifest");
+        assert_eq!(lines[0]["job"]["id"], job.id.to_string());
+        assert_eq!(lines[0]["page_count"], 2);
+
+        assert_eq!(lines[1]["page"]["id"], a.to_string());
+        assert_eq!(lines[1]["html"], "<h1>A</h1>");
+        assert_eq!(lines[1]["markdown"], "# A");
+
+        assert_eq!(lines[2]["page"]["id"], b.to_string());
+        assert_eq!(lines[2]["html"], serde_json::Value::Null);
+        assert_eq!(lines[2]["missing"], serde_json::json!(["html"]));
+    }
+}
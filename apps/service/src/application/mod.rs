@@ -1,4 +1,6 @@
+pub mod export;
 pub mod scraper;
 pub mod scheduler;
+pub mod storage;
 // pub mod analytics;
-// pub mod webhook; 
\ No newline at end of file
+pub mod webhook; 
\ No newline at end of file
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, error, info};
+
+use crate::config::settings::Scheduler as SchedulerConfig;
+use crate::infrastructure::queue::redis_queue::RedisJobQueue;
+
+/// Maximum number of scheduled jobs moved into the live queue per round trip
+const PROMOTE_BATCH_SIZE: usize = 100;
+
+/// Moves jobs from `scheduled:{queue}` into `queue:{queue}` once they are due,
+/// for every queue that has been used so far.
+///
+/// Unlike the cron scheduler this always runs, since one-off jobs can be
+/// scheduled through the API even when cron scheduling is disabled.
+pub struct JobPromoter {
+    job_queue: Arc<RedisJobQueue>,
+    config: SchedulerConfig,
+    running: bool,
+}
+
+impl JobPromoter {
+    pub fn new(job_queue: Arc<RedisJobQueue>, config: SchedulerConfig) -> Self {
+        Self {
+            job_queue,
+            config,
+            running: false,
+        }
+    }
+
+    pub async fn start(&mut self) -> Result<()> {
+        if self.running {
+            return Ok(());
+        }
+
+        self.running = true;
+        info!("Starting scheduled job promoter");
+
+        while self.running {
+            match self.promote().await {
+                Ok(0) => {}
+                Ok(count) => info!("Promoted {} scheduled jobs", count),
+                Err(e) => error!("Error promoting scheduled jobs: {}", e),
+            }
+
+            sleep(Duration::from_secs(self.config.promote_interval_seconds)).await;
+        }
+
+        info!("Scheduled job promoter stopped");
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        info!("Stopping scheduled job promoter");
+        self.running = false;
+    }
+
+    /// Promote all due jobs of every known queue in batches and return how many were moved
+    async fn promote(&self) -> Result<usize> {
+        let mut total = 0;
+
+        for queue in self.job_queue.known_queues().await? {
+            loop {
+                let promoted = self.job_queue.promote_due(&queue, PROMOTE_BATCH_SIZE).await?;
+                total += promoted;
+
+                if promoted < PROMOTE_BATCH_SIZE {
+                    break;
+                }
+
+                debug!("Promoted a full batch of scheduled jobs in queue {}, checking for more", queue);
+            }
+        }
+
+        Ok(total)
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use cron::Schedule;
+use sqlx::types::Json;
 use sqlx::PgPool;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -11,14 +12,17 @@ use uuid::Uuid;
 
 use crate::config::settings::Scheduler as SchedulerConfig;
 use crate::domain::job::Job;
-use crate::domain::scraper_config::ScraperConfig;
-use crate::infrastructure::queue::redis_queue::{JobQueue, RedisJobQueue};
+use crate::domain::scraper_config::{CanonicalizationRules, CrawlMode, ExtractionRule, RetentionPolicy, ScraperConfig};
+use crate::infrastructure::queue::redis_queue::RedisJobQueue;
 use crate::utils::error::AppError;
-use crate::domain::job::JobStatus;
+use crate::domain::job::{JobPriority, JobStatus};
+use crate::domain::webhook::WebhookEventType;
+use crate::application::webhook::service::WebhookService;
 
 pub struct SchedulerService {
     db_pool: PgPool,
     job_queue: Arc<RedisJobQueue>,
+    webhook_service: Arc<WebhookService>,
     config: SchedulerConfig,
     running: bool,
 }
@@ -27,11 +31,13 @@ impl SchedulerService {
     pub fn new(
         db_pool: PgPool,
         job_queue: Arc<RedisJobQueue>,
+        webhook_service: Arc<WebhookService>,
         config: SchedulerConfig,
     ) -> Self {
         Self {
             db_pool,
             job_queue,
+            webhook_service,
             config,
             running: false,
         }
@@ -80,7 +86,11 @@ impl SchedulerService {
                 max_pages_per_job, respect_robots_txt, user_agent, 
                 request_delay_ms, max_concurrent_requests, schedule, 
                 headers as "headers: serde_json::Value", 
-                created_at, updated_at, active
+                created_at, updated_at, active, queue_name,
+                crawl_mode as "crawl_mode: CrawlMode",
+                canonicalization as "canonicalization: Json<CanonicalizationRules>",
+                extraction_rules as "extraction_rules: Json<Vec<ExtractionRule>>",
+                retention as "retention: Json<RetentionPolicy>"
             FROM scraper_configs
             WHERE active = true AND schedule IS NOT NULL
             "#
@@ -111,7 +121,7 @@ impl SchedulerService {
                         
                         if should_run {
                             info!("Scheduling job for config: {}", config.name);
-                            self.create_job(config.id).await?;
+                            self.create_job(&config).await?;
                         }
                     }
                     Err(e) => {
@@ -134,7 +144,9 @@ impl SchedulerService {
                 created_at, updated_at, started_at, completed_at, 
                 error_message, pages_crawled, pages_failed, pages_skipped, 
                 next_run_at, worker_id, 
-                metadata as "metadata: serde_json::Value"
+                metadata as "metadata: serde_json::Value",
+                attempts, heartbeat_at,
+                priority as "priority: JobPriority", queue_name
             FROM jobs
             WHERE config_id = $1
             ORDER BY created_at DESC
@@ -148,9 +160,12 @@ impl SchedulerService {
         Ok(job)
     }
     
-    async fn create_job(&self, config_id: Uuid) -> Result<Job> {
+    /// Create a low priority job, so scheduled runs give way to jobs started by hand
+    async fn create_job(&self, config: &ScraperConfig) -> Result<Job> {
         // Create job
-        let job = Job::new(config_id);
+        let queue_name = config.queue_name.clone().unwrap_or_else(|| self.job_queue.default_queue().to_string());
+        let mut job = Job::new(config.id, queue_name);
+        job.priority = JobPriority::Low;
         
         // Save to database
         sqlx::query!(
@@ -159,9 +174,9 @@ impl SchedulerService {
                 id, config_id, status, created_at, updated_at, 
                 started_at, completed_at, error_message, 
                 pages_crawled, pages_failed, pages_skipped, 
-                next_run_at, worker_id, metadata
+                next_run_at, worker_id, metadata, priority, queue_name
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             "#,
             job.id,
             job.config_id,
@@ -176,13 +191,17 @@ impl SchedulerService {
             job.pages_skipped,
             job.next_run_at,
             job.worker_id,
-            job.metadata
+            job.metadata,
+            job.priority.to_string(),
+            job.queue_name
         )
         .execute(&self.db_pool)
         .await?;
         
         // Enqueue job
-        self.job_queue.enqueue("scraper_jobs", &job.id).await?;
+        self.job_queue.enqueue_with_priority(&job.queue_name, &job.id, job.priority).await?;
+        
+        self.webhook_service.notify_job(WebhookEventType::JobCreated, &job).await;
         
         Ok(job)
     }
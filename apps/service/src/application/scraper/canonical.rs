@@ -0,0 +1,184 @@
+use scraper::{Html, Selector};
+use std::sync::OnceLock;
+use url::Url;
+
+use crate::domain::scraper_config::CanonicalizationRules;
+
+/// Query parameters added by analytics and ad platforms, where a trailing `*` matches any suffix
+const TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "gclid", "gclsrc", "dclid", "gbraid", "wbraid", "fbclid", "msclkid", "yclid", "twclid", "igshid",
+    "mc_cid", "mc_eid", "_ga", "_gl", "_hsenc", "_hsmi", "mkt_tok", "oly_anon_id", "oly_enc_id", "vero_id",
+];
+
+/// Rewrite a URL into its canonical form under `rules`.
+///
+/// Parsing with the `url` crate already lowercases the host and drops a
+/// default port, so only the fragment, a trailing dot of the host and the
+/// rules are left to apply here.
+pub fn canonicalize(mut url: Url, rules: &CanonicalizationRules) -> String {
+    url.set_fragment(None);
+
+    // A fully qualified host names the same site
+    if let Some(host) = url.host_str().filter(|host| host.len() > 1 && host.ends_with('.')) {
+        let host = host.trim_end_matches('.').to_string();
+        let _ = url.set_host(Some(&host));
+    }
+
+    let query = url.query().map(|query| {
+        let mut params: Vec<&str> = query
+            .split('&')
+            .filter(|param| !param.is_empty() && !is_tracking_param(param_name(param), rules))
+            .collect();
+
+        // The sort is stable, so repeated parameters keep their order
+        if rules.sort_query_params {
+            params.sort_by_key(|param| param_name(param));
+        }
+
+        params.join("&")
+    });
+
+    if let Some(query) = query {
+        url.set_query((!query.is_empty()).then_some(query.as_str()));
+    }
+
+    if rules.strip_trailing_slash && url.path().len() > 1 && url.path().ends_with('/') {
+        let path = url.path().trim_end_matches('/').to_string();
+        url.set_path(if path.is_empty() { "/" } else { &path });
+    }
+
+    url.to_string()
+}
+
+fn param_name(param: &str) -> &str {
+    param.split('=').next().unwrap_or(param)
+}
+
+fn is_tracking_param(name: &str, rules: &CanonicalizationRules) -> bool {
+    let defaults = TRACKING_PARAMS.iter().copied().filter(|_| rules.strip_tracking_params);
+    let extra = rules.tracking_params.iter().map(String::as_str);
+
+    defaults.chain(extra).any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => name.len() >= prefix.len() && name[..prefix.len()].eq_ignore_ascii_case(prefix),
+        None => name.eq_ignore_ascii_case(pattern),
+    })
+}
+
+/// The target of the `<link rel="canonical">` in the head of an HTML document
+pub fn find_canonical_link(document: &Html) -> Option<String> {
+    static CANONICAL: OnceLock<Selector> = OnceLock::new();
+    let canonical = CANONICAL.get_or_init(|| Selector::parse("head link[rel][href]").unwrap());
+
+    document
+        .select(canonical)
+        .find(|element| element.attr("rel").is_some_and(is_canonical_rel))
+        .and_then(|element| element.attr("href"))
+        .map(|href| href.trim().to_string())
+        .filter(|href| !href.is_empty())
+}
+
+/// The target of a `rel="canonical"` entry of a `Link` header, as sent for PDFs and other documents
+pub fn find_canonical_header(value: &str) -> Option<String> {
+    value.split('<').skip(1).find_map(|link| {
+        let (target, parameters) = link.split_once('>')?;
+
+        let canonical = parameters.split(';').any(|parameter| {
+            parameter
+                .split_once('=')
+                .filter(|(name, _)| name.trim().eq_ignore_ascii_case("rel"))
+                .is_some_and(|(_, rel)| is_canonical_rel(rel.trim().trim_matches('"')))
+        });
+
+        canonical.then(|| target.trim().to_string()).filter(|target| !target.is_empty())
+    })
+}
+
+fn is_canonical_rel(rel: &str) -> bool {
+    rel.split_ascii_whitespace().any(|token| token.eq_ignore_ascii_case("canonical"))
+}
+
+/// Whether two URLs are on the same site, treating `www.` as part of the bare domain
+pub fn same_site(a: &Url, b: &Url) -> bool {
+    let host = |url: &Url| url.host_str().map(|host| host.strip_prefix("www.").unwrap_or(host).to_string());
+
+    host(a).is_some() && host(a) == host(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(url: &str, rules: &CanonicalizationRules) -> String {
+        canonicalize(Url::parse(url).unwrap(), rules)
+    }
+
+    fn all_rules() -> CanonicalizationRules {
+        CanonicalizationRules {
+            strip_tracking_params: true,
+            tracking_params: vec!["sessionid".to_string(), "ref_*".to_string()],
+            sort_query_params: true,
+            strip_trailing_slash: true,
+            honor_canonical_link: true,
+        }
+    }
+
+    #[test]
+    fn default_rules_only_drop_the_fragment_and_normalise_the_host() {
+        let rules = CanonicalizationRules::default();
+
+        assert_eq!(canonical("HTTPS://Example.COM.:443/a/?b=2&utm_source=x&a=1#top", &rules), "https://example.com/a/?b=2&utm_source=x&a=1");
+        assert_eq!(canonical("http://example.com", &rules), "http://example.com/");
+    }
+
+    #[test]
+    fn applies_every_rule() {
+        let rules = all_rules();
+
+        assert_eq!(
+            canonical("https://example.com/docs/?b=2&UTM_Source=x&sessionid=9&a=1&ref_src=t&a=0&gclid=1", &rules),
+            "https://example.com/docs?a=1&a=0&b=2"
+        );
+        assert_eq!(canonical("https://example.com/?utm_medium=email", &rules), "https://example.com/");
+        assert_eq!(canonical("https://example.com///", &rules), "https://example.com/");
+    }
+
+    #[test]
+    fn keeps_parameters_that_only_look_like_tracking() {
+        let rules = all_rules();
+
+        assert_eq!(canonical("https://example.com/?utm=1&referrer=2&gclid_x=3", &rules), "https://example.com/?gclid_x=3&referrer=2&utm=1");
+        assert_eq!(canonical("https://example.com/?sessionid=1", &CanonicalizationRules::default()), "https://example.com/?sessionid=1");
+    }
+
+    #[test]
+    fn finds_the_canonical_link_in_the_head() {
+        let document = Html::parse_document(
+            r#"<html><head><link rel="alternate" href="/en"><link rel="Canonical" href=" /page "></head>
+            <body><link rel="canonical" href="/body"></body></html>"#,
+        );
+        assert_eq!(find_canonical_link(&document).as_deref(), Some("/page"));
+
+        let document = Html::parse_document(r#"<head><link rel="canonical" href=""></head>"#);
+        assert_eq!(find_canonical_link(&document), None);
+    }
+
+    #[test]
+    fn finds_the_canonical_link_header() {
+        assert_eq!(
+            find_canonical_header(r#"<https://example.com/a.pdf>; rel="alternate", <https://example.com/b.pdf>; rel="canonical""#).as_deref(),
+            Some("https://example.com/b.pdf")
+        );
+        assert_eq!(find_canonical_header("<https://example.com/a.pdf>; REL=canonical").as_deref(), Some("https://example.com/a.pdf"));
+        assert_eq!(find_canonical_header("<https://example.com/a.pdf>; rel=\"next\""), None);
+        assert_eq!(find_canonical_header("rel=canonical"), None);
+    }
+
+    #[test]
+    fn treats_www_as_the_same_site() {
+        let url = |url: &str| Url::parse(url).unwrap();
+
+        assert!(same_site(&url("https://www.example.com/a"), &url("http://example.com/b")));
+        assert!(!same_site(&url("https://example.com/"), &url("https://blog.example.com/")));
+        assert!(!same_site(&url("data:text/plain,a"), &url("data:text/plain,a")));
+    }
+}
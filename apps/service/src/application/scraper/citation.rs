@@ -0,0 +1,212 @@
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::domain::page::{CitationKind, PageCitation};
+
+/// Most distinct citations kept for one page
+const MAX_CITATIONS_PER_PAGE: usize = 1000;
+
+/// Division of the High Court written after an England and Wales neutral citation, e.g. `[2020] EWHC 123 (Ch)`
+const EWHC_DIVISIONS: &str = "Ch|QB|KB|Admin|Fam|Comm|Pat|TCC|IPEC|Costs|Admlty|Mercantile";
+
+/// States whose courts use `<year> <state> <number>` neutral citations
+const STATE_NEUTRAL_CODES: &str = "AR|CO|IL|ME|MT|ND|OK|SD|UT|VT|WI|WY";
+
+/// Find the citations of statutes, regulations and cases in the text of a page.
+///
+/// Recognised are U.S. Code sections (`42 U.S.C. § 1983`), Code of Federal
+/// Regulations sections and parts (`29 C.F.R. § 1910.1200`), neutral
+/// citations (`[2019] UKSC 41`, `2019 WI 12`, `2019-Ohio-1234`), the main
+/// federal reporters (`410 U.S. 113`) and ECLI identifiers. Each citation is
+/// returned once, in the order of its first appearance, with a normalised form
+/// that is the same however it was written.
+pub fn extract(text: &str) -> Vec<PageCitation> {
+    let mut found: Vec<(usize, PageCitation)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for (kind, regex, normalize) in patterns() {
+        for captures in regex.captures_iter(text) {
+            let whole = captures.get(0).unwrap();
+            let normalized = normalize(&captures);
+
+            match index.get(&normalized) {
+                Some(&i) => found[i].1.occurrences += 1,
+                None => {
+                    index.insert(normalized.clone(), found.len());
+                    found.push((whole.start(), PageCitation {
+                        kind: *kind,
+                        citation: collapse_whitespace(whole.as_str().trim_end_matches('.')),
+                        normalized,
+                        occurrences: 1,
+                    }));
+                },
+            }
+        }
+    }
+
+    found.sort_by_key(|(start, _)| *start);
+    found.into_iter().map(|(_, citation)| citation).take(MAX_CITATIONS_PER_PAGE).collect()
+}
+
+/// The normalised form of the first citation in `text`, used to look up a citation however it is written
+pub fn normalize(text: &str) -> Option<String> {
+    extract(text).into_iter().next().map(|citation| citation.normalized)
+}
+
+type Normalizer = fn(&Captures) -> String;
+
+fn patterns() -> &'static [(CitationKind, Regex, Normalizer)] {
+    static PATTERNS: OnceLock<Vec<(CitationKind, Regex, Normalizer)>> = OnceLock::new();
+
+    PATTERNS.get_or_init(|| {
+        let patterns: [(CitationKind, String, Normalizer); 6] = [
+            (
+                CitationKind::Statute,
+                r"\b(\d{1,3})\s*(?i:U\.?\s?S\.?\s?C\.?(?:\s?A\.?|\s?S\.?)?)\s*(?:§§?|[Ss]ec(?:tion|s?\.))?\s*(\d+[A-Za-z0-9]*(?:-[A-Za-z0-9]+)*(?:\([A-Za-z0-9]{1,4}\))*)".to_string(),
+                |c| format!("{} U.S.C. § {}", &c[1], &c[2]),
+            ),
+            (
+                CitationKind::Regulation,
+                r"\b(\d{1,2})\s*(?i:C\.?\s?F\.?\s?R\.?)\s*(§§?|[Pp]arts?|[Ss]ec(?:tion|s?\.))?\s*(\d+(?:\.\d+[A-Za-z]?(?:-\d+)?)?(?:\([A-Za-z0-9]{1,4}\))*)".to_string(),
+                |c| {
+                    let part = c.get(2).is_some_and(|marker| marker.as_str().to_ascii_lowercase().starts_with("part"));
+                    let marker = if part && !c[3].contains('.') { "Part" } else { "§" };
+                    format!("{} C.F.R. {} {}", &c[1], marker, &c[3])
+                },
+            ),
+            (
+                CitationKind::Case,
+                format!(r"\[(\d{{4}})\]\s+([A-Z]{{2,8}})(?:\s+(Civ|Crim))?\s+(\d{{1,5}})\b(?:\s*\(({})\))?", EWHC_DIVISIONS),
+                |c| {
+                    let mut citation = format!("[{}] {}", &c[1], &c[2]);
+                    if let Some(division) = c.get(3) {
+                        citation.push(' ');
+                        citation.push_str(division.as_str());
+                    }
+                    citation.push(' ');
+                    citation.push_str(&c[4]);
+                    if let Some(division) = c.get(5) {
+                        citation.push_str(&format!(" ({})", division.as_str()));
+                    }
+                    citation
+                },
+            ),
+            (
+                CitationKind::Case,
+                format!(r"\b((?:19|20)\d{{2}})(?:\s+({})\s+|-(Ohio|NMSC|NMCA)-)(\d{{1,6}})\b", STATE_NEUTRAL_CODES),
+                |c| match c.get(3) {
+                    Some(court) => format!("{}-{}-{}", &c[1], court.as_str(), &c[4]),
+                    None => format!("{} {} {}", &c[1], &c[2], &c[4]),
+                },
+            ),
+            (
+                CitationKind::Case,
+                r"\b(\d{1,4})\s+(U\.\s?S\.|S\.\s?Ct\.|L\.\s?Ed\.(?:\s?2d)?|F\.\s?Supp\.(?:\s?[23]d)?|F\.\s?App'x|F\.(?:\s?(?:2d|3d|4th))?)\s+(\d{1,5})\b".to_string(),
+                |c| format!("{} {} {}", &c[1], reporter(&c[2]), &c[3]),
+            ),
+            (
+                CitationKind::Case,
+                r"(?i)\bECLI:[a-z]{2}:[a-z0-9.]{1,7}:\d{4}:[a-z0-9.]*[a-z0-9]".to_string(),
+                |c| c[0].to_ascii_uppercase(),
+            ),
+        ];
+
+        patterns
+            .into_iter()
+            .map(|(kind, pattern, normalize)| (kind, Regex::new(&pattern).unwrap(), normalize))
+            .collect()
+    })
+}
+
+/// The Bluebook abbreviation of a federal reporter, whatever its spacing
+fn reporter(abbreviation: &str) -> &'static str {
+    match abbreviation.replace(' ', "").as_str() {
+        "U.S." => "U.S.",
+        "S.Ct." => "S. Ct.",
+        "L.Ed." => "L. Ed.",
+        "L.Ed.2d" => "L. Ed. 2d",
+        "F.Supp." => "F. Supp.",
+        "F.Supp.2d" => "F. Supp. 2d",
+        "F.Supp.3d" => "F. Supp. 3d",
+        "F.App'x" => "F. App'x",
+        "F.2d" => "F.2d",
+        "F.3d" => "F.3d",
+        "F.4th" => "F.4th",
+        _ => "F.",
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(text: &str) -> Vec<String> {
+        extract(text).into_iter().map(|citation| citation.normalized).collect()
+    }
+
+    #[test]
+    fn normalises_statutes_however_they_are_written() {
+        for text in ["42 U.S.C. § 1983", "42 USC 1983", "42 u.s.c. sec. 1983", "42 U.S.C.A. §1983", "42 U. S. C. Section 1983"] {
+            assert_eq!(normalize(text).as_deref(), Some("42 U.S.C. § 1983"), "{}", text);
+        }
+
+        assert_eq!(normalize("15 U.S.C. § 78j(b)").as_deref(), Some("15 U.S.C. § 78j(b)"));
+        assert_eq!(normalize("18 U.S.C. § 1030(a)(2)(C)").as_deref(), Some("18 U.S.C. § 1030(a)(2)(C)"));
+    }
+
+    #[test]
+    fn tells_regulation_parts_from_sections() {
+        assert_eq!(normalize("29 C.F.R. § 1910.1200").as_deref(), Some("29 C.F.R. § 1910.1200"));
+        assert_eq!(normalize("29 CFR 1910.1200(h)").as_deref(), Some("29 C.F.R. § 1910.1200(h)"));
+        assert_eq!(normalize("40 C.F.R. Part 60").as_deref(), Some("40 C.F.R. Part 60"));
+        assert_eq!(normalize("40 CFR parts 60").as_deref(), Some("40 C.F.R. Part 60"));
+    }
+
+    #[test]
+    fn recognises_neutral_citations() {
+        assert_eq!(normalize("[2019]  UKSC 41").as_deref(), Some("[2019] UKSC 41"));
+        assert_eq!(normalize("[2020] EWCA Civ 1234").as_deref(), Some("[2020] EWCA Civ 1234"));
+        assert_eq!(normalize("[2021] EWHC 12 (Ch)").as_deref(), Some("[2021] EWHC 12 (Ch)"));
+        assert_eq!(normalize("2019 WI 12").as_deref(), Some("2019 WI 12"));
+        assert_eq!(normalize("2019-Ohio-1234").as_deref(), Some("2019-Ohio-1234"));
+        assert_eq!(normalize("in 2019 we filed 12 briefs"), None);
+    }
+
+    #[test]
+    fn spaces_reporters_the_bluebook_way() {
+        assert_eq!(normalize("410 U.S. 113").as_deref(), Some("410 U.S. 113"));
+        assert_eq!(normalize("93 S.Ct. 705").as_deref(), Some("93 S. Ct. 705"));
+        assert_eq!(normalize("35 L.Ed.2d 147").as_deref(), Some("35 L. Ed. 2d 147"));
+        assert_eq!(normalize("123 F. 3d 456").as_deref(), Some("123 F.3d 456"));
+        assert_eq!(normalize("99 F. Supp. 2d 1").as_deref(), Some("99 F. Supp. 2d 1"));
+    }
+
+    #[test]
+    fn uppercases_ecli_identifiers() {
+        assert_eq!(normalize("ecli:nl:hr:2019:1234.").as_deref(), Some("ECLI:NL:HR:2019:1234"));
+        assert_eq!(extract("ECLI:EU:C:2014:317")[0].kind, CitationKind::Case);
+    }
+
+    #[test]
+    fn counts_repeats_and_keeps_the_order_of_first_appearance() {
+        let citations = extract("See 410 U.S. 113 and 42 U.S.C. § 1983. Later, 42 USC 1983 again and 410 U. S. 113.");
+
+        let summary: Vec<(&str, &str, i32)> = citations
+            .iter()
+            .map(|citation| (citation.citation.as_str(), citation.normalized.as_str(), citation.occurrences))
+            .collect();
+        assert_eq!(summary, vec![("410 U.S. 113", "410 U.S. 113", 2), ("42 U.S.C. § 1983", "42 U.S.C. § 1983", 2)]);
+        assert_eq!(citations[1].kind, CitationKind::Statute);
+    }
+
+    #[test]
+    fn finds_nothing_in_plain_text() {
+        assert!(normalized("The meeting on 3 May covered 12 items in room 410.").is_empty());
+        assert_eq!(normalize(""), None);
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::{Client as HttpClient, Response, StatusCode};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, Semaphore};
@@ -8,10 +9,19 @@ use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 use url::Url;
 use regex::Regex;
+use encoding_rs::{Encoding, UTF_8};
 use md5;
 use uuid;
 
-use crate::domain::page::Page;
+use crate::application::scraper::canonical;
+use crate::application::scraper::citation;
+use crate::application::scraper::document::{self, DocumentKind, ExtractedDocument};
+use crate::application::scraper::extraction::{ExtractedFields, FieldExtractor, EXTRACTION_ERRORS_KEY};
+use crate::application::scraper::html::{self, HtmlLink, HtmlPage, RobotsDirectives};
+use crate::application::scraper::robots::{RobotsTxt, MAX_ROBOTS_TXT_BYTES};
+use crate::application::scraper::sitemap::{self, Sitemap, SitemapUrl, MAX_SITEMAP_BYTES};
+use crate::domain::page::{Page, PageLink};
+use crate::domain::scraper_config::{CanonicalizationRules, ExtractionRule, ScraperConfig};
 use crate::utils::error::AppError;
 
 /// Configuration for the crawler
@@ -29,8 +39,16 @@ pub struct CrawlerConfig {
     pub request_timeout_secs: u64,
     /// Whether to respect robots.txt
     pub respect_robots_txt: bool,
+    /// How long a fetched robots.txt is cached per host (in seconds)
+    pub robots_txt_cache_ttl_secs: u64,
+    /// Upper bound for a robots.txt Crawl-delay (in milliseconds)
+    pub max_crawl_delay_ms: u64,
     /// Maximum size of a page to download (in bytes)
     pub max_page_size_bytes: usize,
+    /// How URLs are rewritten before they are queued and recorded
+    pub canonicalization: CanonicalizationRules,
+    /// Rules extracting structured fields into page metadata
+    pub extraction_rules: Vec<ExtractionRule>,
 }
 
 impl Default for CrawlerConfig {
@@ -42,41 +60,165 @@ impl Default for CrawlerConfig {
             user_agent: "FortaiLegalScraper/1.0".to_string(),
             request_timeout_secs: 30,
             respect_robots_txt: true,
+            robots_txt_cache_ttl_secs: 24 * 60 * 60, // 24 hours
+            max_crawl_delay_ms: 60 * 1000,
             max_page_size_bytes: 10 * 1024 * 1024, // 10 MB
+            canonicalization: CanonicalizationRules::default(),
+            extraction_rules: Vec::new(),
         }
     }
 }
 
+/// Validators of an earlier response for a URL, sent as conditional request headers
+#[derive(Debug, Clone, Default)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Read the validators from the stored response headers of a page
+    pub fn from_headers(headers: &serde_json::Value) -> Option<Self> {
+        let header = |name: &str| headers.get(name).and_then(|v| v.as_str()).map(str::to_string);
+        
+        let validators = Self {
+            etag: header("etag"),
+            last_modified: header("last-modified"),
+        };
+        
+        (validators.etag.is_some() || validators.last_modified.is_some()).then_some(validators)
+    }
+}
+
 /// Crawler for scraping websites
 #[derive(Clone)]
 pub struct Crawler {
     http_client: HttpClient,
     config: CrawlerConfig,
+    headers: HeaderMap,
     domain_delays: Arc<Mutex<HashMap<String, Instant>>>,
     semaphore: Arc<Semaphore>,
-    robots_txt_cache: Arc<Mutex<HashMap<String, RobotsTxt>>>,
+    robots_txt_cache: RobotsTxtCache,
+    extractor: Arc<FieldExtractor>,
 }
 
+/// The robots.txt of each origin, behind a lock of its own so fetching one host's file doesn't hold up the others
+type RobotsTxtCache = Arc<Mutex<HashMap<String, Arc<Mutex<Option<Arc<RobotsTxt>>>>>>>;
+
+/// How soon an unreachable robots.txt is fetched again (in seconds)
+const ROBOTS_TXT_RETRY_SECS: u64 = 5 * 60;
+
+/// Sitemap locations tried in addition to those listed in robots.txt
+const WELL_KNOWN_SITEMAPS: [&str; 2] = ["/sitemap.xml", "/sitemap_index.xml"];
+/// How many levels of sitemap indexes are followed
+const MAX_SITEMAP_INDEX_DEPTH: usize = 3;
+/// Maximum number of sitemap files fetched for one crawl
+const MAX_SITEMAP_FILES: usize = 1000;
+/// Maximum number of URLs collected from the sitemaps of one crawl
+const MAX_SITEMAP_URLS: usize = 500_000;
+
 impl Crawler {
     /// Create a new crawler with the given configuration
     pub fn new(config: CrawlerConfig) -> Result<Self> {
+        Self::with_headers(
+            config,
+            HeaderMap::new(),
+            Arc::new(Mutex::new(HashMap::new())),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+    }
+    
+    fn with_headers(
+        config: CrawlerConfig,
+        headers: HeaderMap,
+        domain_delays: Arc<Mutex<HashMap<String, Instant>>>,
+        robots_txt_cache: RobotsTxtCache,
+    ) -> Result<Self> {
+        // Headers set on the config win over the user agent
         let http_client = HttpClient::builder()
             .timeout(Duration::from_secs(config.request_timeout_secs))
             .user_agent(&config.user_agent)
+            .default_headers(headers.clone())
             .build()?;
         
-        let semaphore = Arc::new(Semaphore::new(config.max_concurrent_requests));
+        let semaphore = Arc::new(Semaphore::new(config.max_concurrent_requests.max(1)));
+        let extractor = Arc::new(FieldExtractor::new(&config.extraction_rules)?);
         
         Ok(Self {
             http_client,
             config,
-            domain_delays: Arc::new(Mutex::new(HashMap::new())),
+            headers,
+            domain_delays,
             semaphore,
-            robots_txt_cache: Arc::new(Mutex::new(HashMap::new())),
+            robots_txt_cache,
+            extractor,
         })
     }
     
-    /// Crawl a URL and return the page and any discovered URLs
+    /// Create a crawler for a job of the given scraper config.
+    ///
+    /// The config's user agent, headers, delay, concurrency, robots.txt
+    /// setting, canonicalisation and extraction rules replace the defaults of this crawler. The per-domain delays and
+    /// the robots.txt cache are shared with this crawler, so jobs crawling the
+    /// same site on one worker still space out their requests.
+    pub fn for_config(&self, scraper_config: &ScraperConfig) -> Result<Self> {
+        let mut config = self.config.clone();
+        
+        if !scraper_config.user_agent.trim().is_empty() {
+            config.user_agent = scraper_config.user_agent.clone();
+        }
+        config.delay_between_requests_ms = scraper_config.request_delay_ms.max(0) as u64;
+        config.max_concurrent_requests = scraper_config.max_concurrent_requests.max(1) as usize;
+        config.respect_robots_txt = scraper_config.respect_robots_txt;
+        config.canonicalization = scraper_config.canonicalization.0.clone();
+        config.extraction_rules = scraper_config.extraction_rules.0.clone();
+        
+        let headers = Self::parse_headers(&scraper_config.headers)?;
+        
+        Self::with_headers(config, headers, self.domain_delays.clone(), self.robots_txt_cache.clone())
+    }
+    
+    /// Convert the JSON object of a config's headers to a header map
+    pub fn parse_headers(headers: &serde_json::Value) -> Result<HeaderMap, AppError> {
+        let mut header_map = HeaderMap::new();
+        
+        let entries = match headers {
+            serde_json::Value::Object(entries) => entries,
+            serde_json::Value::Null => return Ok(header_map),
+            _ => return Err(AppError::InvalidInput("Config headers must be a JSON object".to_string())),
+        };
+        
+        for (name, value) in entries {
+            let value = value.as_str()
+                .ok_or_else(|| AppError::InvalidInput(format!("Value of header {} must be a string", name)))?;
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| AppError::InvalidInput(format!("Invalid header name {}: {}", name, e)))?;
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|e| AppError::InvalidInput(format!("Invalid value for header {}: {}", name, e)))?;
+            
+            header_map.insert(header_name, header_value);
+        }
+        
+        Ok(header_map)
+    }
+    
+    /// User agent sent with requests, also used to pick the robots.txt rules
+    pub fn user_agent(&self) -> &str {
+        self.headers
+            .get(reqwest::header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or(&self.config.user_agent)
+    }
+    
+    /// Maximum number of requests this crawler runs at the same time
+    pub fn max_concurrent_requests(&self) -> usize {
+        self.config.max_concurrent_requests.max(1)
+    }
+    
+    /// Crawl a URL and return the page and any discovered URLs.
+    ///
+    /// With `validators` the request is conditional. If the server answers 304
+    /// the page has status 304 and no content, and no URLs are discovered.
     pub async fn crawl_url(
         &self,
         url: &str,
@@ -84,7 +226,8 @@ impl Crawler {
         parent_url: Option<String>,
         include_patterns: &[String],
         exclude_patterns: &[String],
-    ) -> Result<(Page, Vec<String>)> {
+        validators: Option<&Validators>,
+    ) -> Result<(Page, Vec<PageLink>)> {
         debug!("Crawling URL: {}", url);
         
         // Normalize the URL
@@ -95,26 +238,133 @@ impl Crawler {
             return Err(AppError::InvalidInput(format!("URL does not match include/exclude patterns: {}", url)).into());
         }
         
-        // Extract the domain and path
+        // Extract the domain
         let domain = self.extract_domain(&normalized_url)?;
-        let path = self.extract_path(&normalized_url)?;
         
-        // Check robots.txt
+        // Check robots.txt, which may also ask for a longer delay between requests
+        let mut crawl_delay = None;
         if self.config.respect_robots_txt {
-            let allowed = self.is_allowed_by_robots_txt(&domain, &path).await?;
-            if !allowed {
+            let robots = self.robots_txt(&normalized_url).await?;
+            
+            if !robots.is_allowed(self.user_agent(), &normalized_url) {
+                if robots.is_unreachable() {
+                    return Err(AppError::Scraper(format!("robots.txt is unreachable, not crawling: {}", url)).into());
+                }
                 return Err(AppError::InvalidInput(format!("URL is disallowed by robots.txt: {}", url)).into());
             }
+            
+            crawl_delay = robots.crawl_delay(self.user_agent());
         }
         
         // Apply rate limiting
-        self.apply_rate_limiting(&domain).await;
+        self.apply_rate_limiting(&domain, crawl_delay).await;
+        
+        // Hold a permit while the request and body download are in flight
+        let _permit = self.semaphore.acquire().await?;
         
         // Make the request
-        let response = self.make_request_with_retries(&normalized_url).await?;
+        let response = self.make_request_with_retries(&normalized_url, validators).await?;
         
         // Process the response
-        self.process_response(response, &normalized_url, depth, parent_url).await
+        self.process_response(response, &normalized_url, depth, parent_url, validators.is_some()).await
+    }
+    
+    /// Find the sitemaps of a site from the `Sitemap:` lines of its robots.txt and the well-known locations
+    pub async fn discover_sitemaps(&self, base_url: &str) -> Result<Vec<String>> {
+        let parsed_url = Url::parse(base_url).map_err(|e| AppError::InvalidInput(format!("Invalid URL: {}, error: {}", base_url, e)))?;
+        
+        let mut sitemaps = self.robots_txt(base_url).await?.sitemaps();
+        
+        for path in WELL_KNOWN_SITEMAPS {
+            if let Ok(sitemap_url) = parsed_url.join(path) {
+                let sitemap_url = sitemap_url.to_string();
+                if !sitemaps.contains(&sitemap_url) {
+                    sitemaps.push(sitemap_url);
+                }
+            }
+        }
+        
+        Ok(sitemaps)
+    }
+    
+    /// Fetch sitemaps, following sitemap indexes, and return the URLs they list.
+    ///
+    /// Sitemaps that are missing or can't be parsed are skipped, so a site
+    /// without the well-known sitemaps simply contributes no URLs.
+    pub async fn fetch_sitemap_urls(&self, sitemaps: Vec<String>) -> Vec<SitemapUrl> {
+        let mut pending: VecDeque<(String, usize)> = sitemaps.into_iter().map(|sitemap_url| (sitemap_url, 0)).collect();
+        let mut fetched = HashSet::new();
+        let mut urls = Vec::new();
+        
+        while let Some((sitemap_url, depth)) = pending.pop_front() {
+            if fetched.len() >= MAX_SITEMAP_FILES {
+                warn!("Fetched {} sitemaps, ignoring the remaining {}", fetched.len(), pending.len() + 1);
+                break;
+            }
+            
+            if !fetched.insert(sitemap_url.clone()) {
+                continue;
+            }
+            
+            match self.fetch_sitemap(&sitemap_url).await {
+                Ok(Some(Sitemap::UrlSet(mut entries))) => {
+                    debug!("Sitemap {} lists {} URLs", sitemap_url, entries.len());
+                    entries.truncate(MAX_SITEMAP_URLS - urls.len());
+                    urls.extend(entries);
+                    
+                    if urls.len() >= MAX_SITEMAP_URLS {
+                        warn!("Collected {} URLs from sitemaps, ignoring the rest", urls.len());
+                        break;
+                    }
+                },
+                Ok(Some(Sitemap::Index(children))) if depth < MAX_SITEMAP_INDEX_DEPTH => {
+                    debug!("Sitemap index {} lists {} sitemaps", sitemap_url, children.len());
+                    pending.extend(children.into_iter().map(|child| (child, depth + 1)));
+                },
+                Ok(Some(Sitemap::Index(_))) => {
+                    warn!("Sitemap index {} is nested too deeply, skipping it", sitemap_url);
+                },
+                Ok(None) => {},
+                Err(e) => warn!("Failed to fetch sitemap {}: {}", sitemap_url, e),
+            }
+        }
+        
+        urls
+    }
+    
+    /// Fetch and parse a single sitemap, returning `None` if it doesn't exist or may not be crawled
+    async fn fetch_sitemap(&self, sitemap_url: &str) -> Result<Option<Sitemap>> {
+        let domain = self.extract_domain(sitemap_url)?;
+        
+        let mut crawl_delay = None;
+        if self.config.respect_robots_txt {
+            let robots = self.robots_txt(sitemap_url).await?;
+            
+            if !robots.is_allowed(self.user_agent(), sitemap_url) {
+                debug!("Sitemap {} is disallowed by robots.txt", sitemap_url);
+                return Ok(None);
+            }
+            
+            crawl_delay = robots.crawl_delay(self.user_agent());
+        }
+        
+        self.apply_rate_limiting(&domain, crawl_delay).await;
+        
+        let response = self.make_request_with_retries(sitemap_url, None).await?;
+        
+        if response.status().is_client_error() {
+            debug!("Sitemap {} is unavailable ({})", sitemap_url, response.status());
+            return Ok(None);
+        }
+        
+        if !response.status().is_success() {
+            return Err(AppError::Scraper(format!("HTTP error: {}", response.status())).into());
+        }
+        
+        // A gzipped sitemap is held to the same limit once it is decompressed
+        let body = Self::read_body(response, MAX_SITEMAP_BYTES).await?;
+        
+        sitemap::parse_sitemap(&body).map(Some)
     }
     
     /// Check if a URL should be crawled based on include/exclude patterns
@@ -146,20 +396,35 @@ impl Crawler {
         true
     }
     
-    /// Normalize a URL by removing fragments and normalizing the path
+    /// Normalize a URL by removing fragments and applying the canonicalisation rules
     pub fn normalize_url(&self, url: &str) -> Result<String> {
         let parsed_url = Url::parse(url).map_err(|e| AppError::InvalidInput(format!("Invalid URL: {}, error: {}", url, e)))?;
         
-        // Create a new URL without the fragment
-        let mut normalized = parsed_url.clone();
-        normalized.set_fragment(None);
-        
-        // Ensure the path ends with a trailing slash if it's just a domain
-        if normalized.path() == "" || normalized.path() == "/" {
-            normalized.set_path("/");
+        Ok(canonical::canonicalize(parsed_url, &self.config.canonicalization))
+    }
+    
+    /// The URL a page declares as canonical through a `Link` header or `<link rel="canonical">`, or its own URL.
+    ///
+    /// A canonical URL on another site is ignored, so a page can't claim to
+    /// be the copy of a page that belongs to someone else.
+    fn canonical_url(&self, url: &str, link_header: Option<&str>, canonical_link: Option<&str>) -> String {
+        if !self.config.canonicalization.honor_canonical_link {
+            return url.to_string();
         }
         
-        Ok(normalized.to_string())
+        let Ok(page_url) = Url::parse(url) else {
+            return url.to_string();
+        };
+        
+        let href = link_header
+            .and_then(canonical::find_canonical_header)
+            .or_else(|| canonical_link.map(str::to_string));
+        
+        href.and_then(|href| page_url.join(&href).ok())
+            .filter(|canonical_url| matches!(canonical_url.scheme(), "http" | "https"))
+            .filter(|canonical_url| canonical::same_site(&page_url, canonical_url))
+            .map(|canonical_url| canonical::canonicalize(canonical_url, &self.config.canonicalization))
+            .unwrap_or_else(|| url.to_string())
     }
     
     /// Extract the domain from a URL
@@ -171,46 +436,55 @@ impl Crawler {
             .ok_or_else(|| AppError::InvalidInput(format!("URL has no host: {}", url)).into())
     }
     
-    /// Extract the path from a URL
-    fn extract_path(&self, url: &str) -> Result<String> {
-        let parsed_url = Url::parse(url).map_err(|e| AppError::InvalidInput(format!("Invalid URL: {}, error: {}", url, e)))?;
-        
-        Ok(parsed_url.path().to_string())
-    }
-    
     /// Apply rate limiting for a domain
-    async fn apply_rate_limiting(&self, domain: &str) {
-        let delay_duration = Duration::from_millis(self.config.delay_between_requests_ms);
-        
-        let mut domain_delays = self.domain_delays.lock().await;
-        
-        if let Some(last_request_time) = domain_delays.get(domain) {
-            let elapsed = last_request_time.elapsed();
+    ///
+    /// Each caller reserves the next free slot for the domain while holding the
+    /// lock, so concurrent requests to the same domain are spaced out by the
+    /// configured delay instead of all waking up at once. A robots.txt
+    /// Crawl-delay longer than the configured delay takes its place, up to
+    /// `max_crawl_delay_ms`.
+    async fn apply_rate_limiting(&self, domain: &str, crawl_delay: Option<Duration>) {
+        let crawl_delay = crawl_delay.unwrap_or_default().min(Duration::from_millis(self.config.max_crawl_delay_ms));
+        let delay_duration = Duration::from_millis(self.config.delay_between_requests_ms).max(crawl_delay);
+        
+        let sleep_duration = {
+            let mut domain_delays = self.domain_delays.lock().await;
+            let now = Instant::now();
             
-            if elapsed < delay_duration {
-                let sleep_duration = delay_duration - elapsed;
-                // Release the lock before sleeping
-                drop(domain_delays);
-                
-                debug!("Rate limiting: sleeping for {}ms before requesting {}", sleep_duration.as_millis(), domain);
-                sleep(sleep_duration).await;
-                
-                // Reacquire the lock to update the last request time
-                domain_delays = self.domain_delays.lock().await;
-            }
-        }
+            let slot = match domain_delays.get(domain) {
+                Some(last_request_time) if *last_request_time + delay_duration > now => *last_request_time + delay_duration,
+                _ => now,
+            };
+            
+            // Record the reserved slot as the domain's last request time
+            domain_delays.insert(domain.to_string(), slot);
+            slot - now
+        };
         
-        // Update the last request time
-        domain_delays.insert(domain.to_string(), Instant::now());
+        if !sleep_duration.is_zero() {
+            debug!("Rate limiting: sleeping for {}ms before requesting {}", sleep_duration.as_millis(), domain);
+            sleep(sleep_duration).await;
+        }
     }
     
     /// Make an HTTP request with retries
-    async fn make_request_with_retries(&self, url: &str) -> Result<Response> {
+    async fn make_request_with_retries(&self, url: &str, validators: Option<&Validators>) -> Result<Response> {
         let mut retries = 0;
         let max_retries = self.config.max_retries;
         
         loop {
-            match self.http_client.get(url).send().await {
+            let mut request = self.http_client.get(url);
+            
+            if let Some(validators) = validators {
+                if let Some(etag) = &validators.etag {
+                    request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &validators.last_modified {
+                    request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            
+            match request.send().await {
                 Ok(response) => {
                     // Check if we got a server error (5xx)
                     if response.status().is_server_error() {
@@ -254,7 +528,8 @@ impl Crawler {
         url: &str,
         depth: i32,
         parent_url: Option<String>,
-    ) -> Result<(Page, Vec<String>)> {
+        conditional: bool,
+    ) -> Result<(Page, Vec<PageLink>)> {
         let status = response.status();
         let headers = response.headers().clone();
         
@@ -266,6 +541,36 @@ impl Crawler {
             }
         }
         
+        // The page is unchanged since the validators were issued, so there is no body to process
+        if status == StatusCode::NOT_MODIFIED && conditional {
+            let page = Page {
+                id: uuid::Uuid::new_v4(),
+                job_id: uuid::Uuid::default(), // This will be set by the worker
+                url: url.to_string(),
+                normalized_url: url.to_string(),
+                content_hash: String::new(),
+                http_status: status.as_u16() as i32,
+                http_headers: serde_json::Value::Object(headers_json),
+                crawled_at: chrono::Utc::now(),
+                html_storage_path: None,
+                markdown_storage_path: None,
+                title: None,
+                metadata: serde_json::json!({}),
+                error_message: None,
+                depth,
+                parent_url,
+                content_type: None,
+                document_storage_path: None,
+                html_content: None,
+                document_content: None,
+                markdown_content: None,
+                noindex: false,
+                citations: Vec::new(),
+            };
+            
+            return Ok((page, Vec::new()));
+        }
+        
         // Check if the response is successful
         if !status.is_success() {
             return Err(AppError::Scraper(format!("HTTP error: {}", status)).into());
@@ -277,212 +582,380 @@ impl Crawler {
             .and_then(|v| v.to_str().ok())
             .unwrap_or("text/html");
         
-        // Check if the content type is HTML
+        // Anything else may be a document such as a PDF
         if !content_type.contains("text/html") {
-            return Err(AppError::Scraper(format!("Unsupported content type: {}", content_type)).into());
+            let content_type = content_type.to_string();
+            return self.process_document(response, url, &content_type, headers_json, depth, parent_url).await;
         }
         
-        // Get the HTML content
-        let html_content = response.text().await
-            .map_err(|e| AppError::Scraper(format!("Failed to get response body: {}", e)))?;
-        
-        // Check if the content is too large
-        if html_content.len() > self.config.max_page_size_bytes {
-            return Err(AppError::Scraper(format!("Content too large: {} bytes", html_content.len())).into());
-        }
+        // Get the HTML content, stopping once it is larger than a page may be
+        let charset = Self::charset(content_type);
+        let body = Self::read_body(response, self.config.max_page_size_bytes).await?;
+        let html_content = Self::decode_html(&body, charset.as_deref());
         
         // Calculate a hash of the content
         let content_hash = format!("{:x}", md5::compute(html_content.as_bytes()));
         
-        // Extract the title
-        let title = self.extract_title(&html_content);
+        // Parse the page for its title, canonical URL, robots directives and links
+        let page_url = Url::parse(url).map_err(|e| AppError::InvalidInput(format!("Invalid URL: {}, error: {}", url, e)))?;
+        let (parsed, html_content) = self.parse_html(html_content, page_url, true).await?;
         
-        // Extract links
-        let links = self.extract_links(&html_content, url);
+        let x_robots_tag = headers.get("x-robots-tag").and_then(|v| v.to_str().ok());
+        let robots = self.robots_directives(x_robots_tag, parsed.robots);
+        let links = self.collect_links(parsed.links, robots.nofollow);
+        
+        // Record the page under the URL it declares as canonical
+        let link_header = headers.get(reqwest::header::LINK).and_then(|v| v.to_str().ok());
+        let canonical_link = parsed.canonical_url.as_ref().map(Url::as_str);
+        let normalized_url = self.canonical_url(url, link_header, canonical_link);
         
         // Create a page object
-        let page = Page {
+        let mut page = Page {
             id: uuid::Uuid::new_v4(),
             job_id: uuid::Uuid::default(), // This will be set by the worker
             url: url.to_string(),
-            normalized_url: url.to_string(),
+            normalized_url,
             content_hash,
             http_status: status.as_u16() as i32,
             http_headers: serde_json::Value::Object(headers_json),
             crawled_at: chrono::Utc::now(),
             html_storage_path: None,
             markdown_storage_path: None,
-            title,
+            title: parsed.title,
             metadata: serde_json::json!({}),
             error_message: None,
             depth,
             parent_url,
+            content_type: Some("text/html".to_string()),
+            document_storage_path: None,
             html_content: Some(html_content), // Store the HTML content temporarily
+            document_content: None,
+            markdown_content: None,
+            noindex: robots.noindex,
+            citations: citation::extract(&parsed.text),
         };
         
+        if let Some(fields) = parsed.fields {
+            Self::add_fields(&mut page, fields);
+        }
+        
         Ok((page, links))
     }
     
-    /// Extract the title from HTML content
-    fn extract_title(&self, html: &str) -> Option<String> {
-        let re = Regex::new(r"<title[^>]*>(.*?)</title>").ok()?;
-        re.captures(html)
-            .and_then(|caps| caps.get(1))
-            .map(|m| m.as_str().trim().to_string())
+    /// Download a document such as a PDF within the page size limit and extract its text as Markdown
+    async fn process_document(
+        &self,
+        response: Response,
+        url: &str,
+        content_type: &str,
+        headers_json: serde_json::Map<String, serde_json::Value>,
+        depth: i32,
+        parent_url: Option<String>,
+    ) -> Result<(Page, Vec<PageLink>)> {
+        let mime_type = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        let charset = Self::charset(content_type);
+        
+        // Generic binary types are downloaded and recognised by their first bytes
+        let kind = DocumentKind::from_mime_type(&mime_type);
+        if kind.is_none() && !DocumentKind::is_generic_mime_type(&mime_type) {
+            return Err(AppError::Scraper(format!("Unsupported content type: {}", content_type)).into());
+        }
+        
+        let status = response.status();
+        let body = Self::read_body(response, self.config.max_page_size_bytes).await?;
+        
+        let kind = kind
+            .or_else(|| DocumentKind::sniff(&body))
+            .ok_or_else(|| AppError::Scraper(format!("Unsupported content type: {}", content_type)))?;
+        
+        let content_hash = format!("{:x}", md5::compute(&body));
+        let (document, body) = self.extract_document(kind, body, charset).await?;
+        
+        let x_robots_tag = headers_json.get("x-robots-tag").and_then(|v| v.as_str());
+        let robots = self.robots_directives(x_robots_tag, RobotsDirectives::default());
+        let links = self.document_links(&document.links, url, robots.nofollow);
+        
+        let link_header = headers_json.get("link").and_then(|v| v.as_str());
+        let normalized_url = self.canonical_url(url, link_header, None);
+        
+        // Rules without a selector run on the extracted text
+        let fields = match Url::parse(url) {
+            Ok(document_url) if !self.extractor.is_empty() => Some(self.extractor.extract_text(&document.markdown, &document_url)),
+            _ => None,
+        };
+        let citations = citation::extract(&document.markdown);
+        
+        let mut page = Page {
+            id: uuid::Uuid::new_v4(),
+            job_id: uuid::Uuid::default(), // This will be set by the worker
+            url: url.to_string(),
+            normalized_url,
+            content_hash,
+            http_status: status.as_u16() as i32,
+            http_headers: serde_json::Value::Object(headers_json),
+            crawled_at: chrono::Utc::now(),
+            html_storage_path: None,
+            markdown_storage_path: None,
+            title: document.title,
+            metadata: serde_json::json!({}),
+            error_message: None,
+            depth,
+            parent_url,
+            content_type: Some(kind.mime_type().to_string()),
+            document_storage_path: None,
+            html_content: None,
+            document_content: Some(body), // Store the document and its Markdown temporarily
+            markdown_content: Some(document.markdown),
+            noindex: robots.noindex,
+            citations,
+        };
+        
+        if let Some(fields) = fields {
+            Self::add_fields(&mut page, fields);
+        }
+        
+        Ok((page, links))
     }
     
-    /// Extract links from HTML content
-    fn extract_links(&self, html: &str, base_url: &str) -> Vec<String> {
-        let base_url = Url::parse(base_url).unwrap_or_else(|_| {
-            // Fallback to a dummy URL if parsing fails
-            Url::parse("http://example.com").unwrap()
-        });
-        
-        let re = Regex::new(r#"<a[^>]+href=["']([^"']+)["']"#).unwrap_or_else(|_| Regex::new("").unwrap());
-        
-        let mut urls = HashSet::new();
-        
-        for cap in re.captures_iter(html) {
-            if let Some(href) = cap.get(1) {
-                let href = href.as_str();
-                
-                // Skip empty links, javascript, mailto, tel, etc.
-                if href.is_empty() || 
-                   href.starts_with("javascript:") || 
-                   href.starts_with("mailto:") || 
-                   href.starts_with("tel:") || 
-                   href.starts_with("#") {
-                    continue;
-                }
-                
-                // Resolve relative URLs
-                if let Ok(absolute_url) = base_url.join(href) {
-                    // Only keep http and https URLs
-                    if absolute_url.scheme() == "http" || absolute_url.scheme() == "https" {
-                        urls.insert(absolute_url.to_string());
-                    }
-                }
-            }
+    /// Write extracted fields into the metadata of a page, with values that failed to convert under `extraction_errors`
+    fn add_fields(page: &mut Page, fields: ExtractedFields) {
+        for (field, value) in fields.values {
+            page.add_metadata(&field, value);
         }
         
-        urls.into_iter().collect()
+        if !fields.errors.is_empty() {
+            page.add_metadata(EXTRACTION_ERRORS_KEY, serde_json::json!(fields.errors));
+        }
     }
     
-    /// Check if a URL is allowed by robots.txt
-    async fn is_allowed_by_robots_txt(&self, domain: &str, path: &str) -> Result<bool> {
-        let mut robots_cache = self.robots_txt_cache.lock().await;
-        
-        // Check if we have cached robots.txt for this domain
-        if !robots_cache.contains_key(domain) {
-            // Fetch robots.txt
-            let robots_url = format!("http://{}/robots.txt", domain);
-            
-            match self.http_client.get(&robots_url).send().await {
-                Ok(response) => {
-                    if response.status().is_success() {
-                        let content = response.text().await?;
-                        let robots = RobotsTxt::parse(&content, &self.config.user_agent);
-                        robots_cache.insert(domain.to_string(), robots);
-                    } else {
-                        // If robots.txt doesn't exist or can't be fetched, assume everything is allowed
-                        robots_cache.insert(domain.to_string(), RobotsTxt::allow_all());
-                    }
-                },
-                Err(_) => {
-                    // If robots.txt can't be fetched, assume everything is allowed
-                    robots_cache.insert(domain.to_string(), RobotsTxt::allow_all());
-                }
+    /// Read a response body, failing as soon as it exceeds `limit` bytes whatever its `Content-Length` says
+    async fn read_body(mut response: Response, limit: usize) -> Result<Vec<u8>> {
+        if response.content_length().is_some_and(|length| length as usize > limit) {
+            return Err(AppError::Scraper(format!("Content too large: {:?} bytes", response.content_length())).into());
+        }
+        
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await
+            .map_err(|e| AppError::Scraper(format!("Failed to get response body: {}", e)))? {
+            if body.len() + chunk.len() > limit {
+                return Err(AppError::Scraper(format!("Content too large: more than {} bytes", limit)).into());
             }
+            body.extend_from_slice(&chunk);
         }
         
-        // Check if the path is allowed
-        if let Some(robots) = robots_cache.get(domain) {
-            Ok(robots.is_allowed(path))
-        } else {
-            // This should never happen, but just in case
-            Ok(true)
+        Ok(body)
+    }
+    
+    /// Read the first `limit` bytes of a response body and drop the rest
+    async fn read_body_prefix(mut response: Response, limit: usize) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        while body.len() < limit {
+            let Some(chunk) = response.chunk().await
+                .map_err(|e| AppError::Scraper(format!("Failed to get response body: {}", e)))? else {
+                break;
+            };
+            body.extend_from_slice(&chunk[..chunk.len().min(limit - body.len())]);
         }
+        
+        Ok(body)
     }
-}
-
-/// Simple robots.txt parser
-#[derive(Debug, Clone)]
-struct RobotsTxt {
-    allow_rules: Vec<String>,
-    disallow_rules: Vec<String>,
-}
-
-impl RobotsTxt {
-    /// Parse robots.txt content
-    fn parse(content: &str, user_agent: &str) -> Self {
-        let mut current_agent = String::new();
-        let mut allow_rules = Vec::new();
-        let mut disallow_rules = Vec::new();
-        
-        // Split content into lines
-        for line in content.lines() {
-            let line = line.trim();
-            
-            // Skip empty lines and comments
-            if line.is_empty() || line.starts_with('#') {
-                continue;
+    
+    /// The charset parameter of a content type
+    fn charset(content_type: &str) -> Option<String> {
+        content_type
+            .split(';')
+            .skip(1)
+            .filter_map(|parameter| parameter.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+            .map(|(_, value)| value.trim().trim_matches('"').to_string())
+    }
+    
+    /// Decode an HTML body in its charset like `Response::text`, falling back to UTF-8 for unknown charsets
+    fn decode_html(body: &[u8], charset: Option<&str>) -> String {
+        let encoding = charset
+            .and_then(|charset| Encoding::for_label(charset.as_bytes()))
+            .unwrap_or(UTF_8);
+        
+        encoding.decode(body).0.into_owned()
+    }
+    
+    /// Extract the text of a document on a blocking thread.
+    ///
+    /// The body is handed back so it can still be stored.
+    pub async fn extract_document(
+        &self,
+        kind: DocumentKind,
+        body: Vec<u8>,
+        charset: Option<String>,
+    ) -> Result<(ExtractedDocument, Vec<u8>)> {
+        let (document, body) = tokio::task::spawn_blocking(move || {
+            let document = document::extract(kind, &body, charset.as_deref());
+            (document, body)
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Document extraction panicked: {}", e)))?;
+        
+        let document = document
+            .map_err(|e| AppError::Scraper(format!("Failed to extract {} document: {}", kind.extension(), e)))?;
+        
+        Ok((document, body))
+    }
+    
+    /// Parse an HTML page on a blocking thread, running the config's extraction rules if `extract_fields` is set.
+    ///
+    /// The HTML is handed back so it can still be stored.
+    async fn parse_html(&self, html_content: String, page_url: Url, extract_fields: bool) -> Result<(HtmlPage, String)> {
+        let extractor = extract_fields.then(|| Arc::clone(&self.extractor));
+        
+        let parsed = tokio::task::spawn_blocking(move || {
+            let parsed = html::parse(&html_content, &page_url, extractor.as_deref());
+            (parsed, html_content)
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("HTML parsing panicked: {}", e)))?;
+        
+        Ok(parsed)
+    }
+    
+    /// Extract the links of an HTML page, none of which are followed if the page asks for that
+    pub async fn extract_links(&self, html_content: String, url: &str) -> Vec<PageLink> {
+        let Ok(page_url) = Url::parse(url) else {
+            return Vec::new();
+        };
+        
+        let parsed = match self.parse_html(html_content, page_url, false).await {
+            Ok((parsed, _)) => parsed,
+            Err(e) => {
+                warn!("Error parsing the HTML of {}: {}", url, e);
+                return Vec::new();
             }
+        };
+        let robots = self.robots_directives(None, parsed.robots);
+        
+        self.collect_links(parsed.links, robots.nofollow)
+    }
+    
+    /// Resolve the link targets of a document against its URL
+    pub fn document_links(&self, hrefs: &[String], url: &str, nofollow: bool) -> Vec<PageLink> {
+        let Ok(document_url) = Url::parse(url) else {
+            return Vec::new();
+        };
+        
+        let links = hrefs
+            .iter()
+            .filter_map(|href| document_url.join(href.trim()).ok())
+            .filter(|link_url| matches!(link_url.scheme(), "http" | "https"))
+            .map(|link_url| HtmlLink {
+                url: link_url,
+                text: None,
+                element: "document",
+                nofollow: false,
+            });
+        
+        self.collect_links(links, nofollow)
+    }
+    
+    /// Canonicalise links and keep one per target, with the first anchor text found for it.
+    ///
+    /// A target is only nofollow if every link to it is.
+    fn collect_links(&self, links: impl IntoIterator<Item = HtmlLink>, nofollow: bool) -> Vec<PageLink> {
+        let mut collected: Vec<PageLink> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        
+        for link in links {
+            let url = canonical::canonicalize(link.url, &self.config.canonicalization);
             
-            // Parse the line
-            if let Some(pos) = line.find(':') {
-                let (key, value) = line.split_at(pos);
-                let key = key.trim().to_lowercase();
-                let value = value[1..].trim();
-                
-                match key.as_str() {
-                    "user-agent" => {
-                        current_agent = value.to_string();
-                    },
-                    "allow" => {
-                        if current_agent == "*" || current_agent == user_agent {
-                            allow_rules.push(value.to_string());
-                        }
-                    },
-                    "disallow" => {
-                        if current_agent == "*" || current_agent == user_agent {
-                            disallow_rules.push(value.to_string());
-                        }
-                    },
-                    _ => {}
+            match positions.get(&url) {
+                Some(&position) => {
+                    let existing = &mut collected[position];
+                    existing.anchor_text = existing.anchor_text.take().or(link.text);
+                    existing.nofollow &= link.nofollow;
+                },
+                None => {
+                    positions.insert(url.clone(), collected.len());
+                    collected.push(PageLink {
+                        url,
+                        anchor_text: link.text,
+                        element: link.element.to_string(),
+                        nofollow: link.nofollow,
+                    });
                 }
             }
         }
         
-        Self {
-            allow_rules,
-            disallow_rules,
+        if nofollow {
+            collected.iter_mut().for_each(|link| link.nofollow = true);
         }
+        
+        collected
     }
     
-    /// Create a robots.txt that allows all paths
-    fn allow_all() -> Self {
-        Self {
-            allow_rules: vec!["/".to_string()],
-            disallow_rules: Vec::new(),
+    /// Indexing directives of a page's `X-Robots-Tag` header and meta robots tags, ignored unless robots.txt is respected
+    fn robots_directives(&self, x_robots_tag: Option<&str>, meta: RobotsDirectives) -> RobotsDirectives {
+        if !self.config.respect_robots_txt {
+            return RobotsDirectives::default();
         }
+        
+        x_robots_tag.map(RobotsDirectives::parse).unwrap_or_default().merge(meta)
     }
     
-    /// Check if a path is allowed
-    fn is_allowed(&self, path: &str) -> bool {
-        // Check if the path matches any disallow rule
-        for rule in &self.disallow_rules {
-            if path.starts_with(rule) {
-                // Check if there's a more specific allow rule
-                for allow_rule in &self.allow_rules {
-                    if path.starts_with(allow_rule) && allow_rule.len() > rule.len() {
-                        return true;
-                    }
+    /// Get the robots.txt of a URL's origin, fetching it if it is not cached or has expired.
+    ///
+    /// robots.txt applies per scheme, host and port (RFC 9309), so the cache is
+    /// keyed by origin and HTTPS sites get their file over HTTPS.
+    async fn robots_txt(&self, url: &str) -> Result<Arc<RobotsTxt>> {
+        let parsed_url = Url::parse(url).map_err(|e| AppError::InvalidInput(format!("Invalid URL: {}, error: {}", url, e)))?;
+        let origin = parsed_url.origin().ascii_serialization();
+        
+        let entry = self.robots_txt_cache.lock().await.entry(origin.clone()).or_default().clone();
+        
+        // Hold the origin's lock while fetching so its robots.txt is only requested once
+        let mut cached = entry.lock().await;
+        
+        if let Some(robots) = cached.as_ref().filter(|robots| !robots.is_expired()) {
+            return Ok(robots.clone());
+        }
+        
+        let robots = Arc::new(self.fetch_robots_txt(&origin, cached.as_deref()).await);
+        *cached = Some(robots.clone());
+        
+        Ok(robots)
+    }
+    
+    /// Fetch and parse the robots.txt of an origin, applying the status code rules of RFC 9309
+    async fn fetch_robots_txt(&self, origin: &str, previous: Option<&RobotsTxt>) -> RobotsTxt {
+        let robots_url = format!("{}/robots.txt", origin);
+        let ttl = Duration::from_secs(self.config.robots_txt_cache_ttl_secs);
+        
+        // Redirects are followed by the HTTP client
+        let error = match self.http_client.get(&robots_url).send().await {
+            Ok(response) if response.status().is_success() => {
+                // Only the part that is parsed is read, however large the file is
+                match Self::read_body_prefix(response, MAX_ROBOTS_TXT_BYTES).await {
+                    Ok(content) => return RobotsTxt::parsed(&String::from_utf8_lossy(&content), ttl),
+                    Err(e) => format!("failed to read body: {}", e),
                 }
-                return false;
+            },
+            // A missing or forbidden robots.txt means there are no restrictions
+            Ok(response) if response.status().is_client_error() && response.status() != StatusCode::TOO_MANY_REQUESTS => {
+                debug!("robots.txt at {} is unavailable ({}), allowing all URLs", robots_url, response.status());
+                return RobotsTxt::unavailable(ttl);
+            },
+            Ok(response) => format!("HTTP {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+        
+        // An unreachable robots.txt disallows everything, unless we still have an earlier copy
+        let retry_ttl = ttl.min(Duration::from_secs(ROBOTS_TXT_RETRY_SECS));
+        match previous.filter(|robots| !robots.is_unreachable()) {
+            Some(robots) => {
+                warn!("robots.txt at {} is unreachable ({}), using the cached copy", robots_url, error);
+                robots.refreshed(retry_ttl)
+            },
+            None => {
+                warn!("robots.txt at {} is unreachable ({}), not crawling the host", robots_url, error);
+                RobotsTxt::unreachable(retry_ttl)
             }
         }
-        
-        // If no disallow rule matches, the path is allowed
-        true
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,278 @@
+use anyhow::Result;
+use std::collections::HashSet;
+
+use super::{read_u16, read_u32, MAX_DECOMPRESSED_BYTES};
+use crate::utils::error::AppError;
+
+/// Magic bytes of a compound file, the container of legacy Office documents
+pub const SIGNATURE: [u8; 8] = [0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1];
+
+const END_OF_CHAIN: u32 = 0xffff_fffe;
+const HEADER_DIFAT_ENTRIES: usize = 109;
+const DIRECTORY_ENTRY_SIZE: usize = 128;
+const STREAM_ENTRY: u8 = 2;
+const ROOT_ENTRY: u8 = 5;
+
+/// A compound file (OLE2) read from memory.
+///
+/// Streams are looked up by name regardless of the storage that holds them,
+/// which is enough for Word documents whose streams all live in the root.
+pub struct CompoundFile<'a> {
+    data: &'a [u8],
+    sector_size: usize,
+    fat: Vec<u32>,
+    mini_fat: Vec<u32>,
+    mini_stream: Vec<u8>,
+    mini_stream_cutoff: usize,
+    entries: Vec<DirectoryEntry>,
+}
+
+struct DirectoryEntry {
+    name: String,
+    kind: u8,
+    start_sector: u32,
+    size: usize,
+}
+
+impl<'a> CompoundFile<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        if !data.starts_with(&SIGNATURE) || data.len() < 512 {
+            return Err(invalid("missing signature"));
+        }
+
+        let sector_shift = read_u16(data, 0x1e).unwrap_or(0);
+        if sector_shift != 9 && sector_shift != 12 {
+            return Err(invalid("unsupported sector size"));
+        }
+
+        let mut file = Self {
+            data,
+            sector_size: 1 << sector_shift,
+            fat: Vec::new(),
+            mini_fat: Vec::new(),
+            mini_stream: Vec::new(),
+            mini_stream_cutoff: read_u32(data, 0x38).unwrap_or(4096) as usize,
+            entries: Vec::new(),
+        };
+
+        // The FAT sectors are listed in the header, continued in a chain of DIFAT sectors
+        let mut fat_sectors: Vec<u32> = (0..HEADER_DIFAT_ENTRIES)
+            .filter_map(|i| read_u32(data, 0x4c + i * 4))
+            .collect();
+
+        let mut difat_sector = read_u32(data, 0x44).unwrap_or(END_OF_CHAIN);
+        let mut seen = HashSet::new();
+        while difat_sector < END_OF_CHAIN && seen.insert(difat_sector) {
+            let sector = file.sector(difat_sector).ok_or_else(|| invalid("truncated DIFAT"))?;
+            let entries = file.sector_size / 4 - 1;
+
+            fat_sectors.extend((0..entries).filter_map(|i| read_u32(sector, i * 4)));
+            difat_sector = read_u32(sector, entries * 4).unwrap_or(END_OF_CHAIN);
+        }
+
+        let fat_sector_count = read_u32(data, 0x2c).unwrap_or(0) as usize;
+        for &sector_id in fat_sectors.iter().filter(|&&id| id < END_OF_CHAIN).take(fat_sector_count) {
+            let sector = file.sector(sector_id).ok_or_else(|| invalid("truncated FAT"))?;
+            file.fat.extend((0..file.sector_size / 4).filter_map(|i| read_u32(sector, i * 4)));
+        }
+
+        let directory = file.read_chain(read_u32(data, 0x30).unwrap_or(END_OF_CHAIN), usize::MAX)?;
+        file.entries = directory
+            .chunks_exact(DIRECTORY_ENTRY_SIZE)
+            .map(|entry| {
+                let name_length = (read_u16(entry, 0x40).unwrap_or(0) as usize).min(64).saturating_sub(2);
+                let name: Vec<u16> = (0..name_length / 2).filter_map(|i| read_u16(entry, i * 2)).collect();
+
+                DirectoryEntry {
+                    name: String::from_utf16_lossy(&name),
+                    kind: entry[0x42],
+                    start_sector: read_u32(entry, 0x74).unwrap_or(END_OF_CHAIN),
+                    // Version 3 files may leave garbage in the upper half of the size
+                    size: read_u32(entry, 0x78).unwrap_or(0) as usize,
+                }
+            })
+            .collect();
+
+        let mini_fat = file.read_chain(read_u32(data, 0x3c).unwrap_or(END_OF_CHAIN), usize::MAX)?;
+        file.mini_fat = (0..mini_fat.len() / 4).filter_map(|i| read_u32(&mini_fat, i * 4)).collect();
+
+        if let Some(root) = file.entries.iter().find(|entry| entry.kind == ROOT_ENTRY) {
+            file.mini_stream = file.read_chain(root.start_sector, root.size)?;
+        }
+
+        Ok(file)
+    }
+
+    /// Read a stream by name, or return `None` if the file has no such stream
+    pub fn read_stream(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let Some(entry) = self.entries.iter().find(|entry| entry.kind == STREAM_ENTRY && entry.name == name) else {
+            return Ok(None);
+        };
+
+        if entry.size < self.mini_stream_cutoff {
+            return self.read_mini_chain(entry.start_sector, entry.size).map(Some);
+        }
+
+        self.read_chain(entry.start_sector, entry.size).map(Some)
+    }
+
+    fn sector(&self, id: u32) -> Option<&'a [u8]> {
+        let offset = (id as usize + 1).checked_mul(self.sector_size)?;
+        self.data.get(offset..offset + self.sector_size)
+    }
+
+    /// Follow a chain of sectors, stopping at `size` bytes or at a loop in the chain
+    fn read_chain(&self, start: u32, size: usize) -> Result<Vec<u8>> {
+        let limit = size.min(MAX_DECOMPRESSED_BYTES);
+        let mut data = Vec::new();
+        let mut sector_id = start;
+        let mut seen = HashSet::new();
+
+        while sector_id < END_OF_CHAIN && data.len() < limit && seen.insert(sector_id) {
+            let sector = self.sector(sector_id).ok_or_else(|| invalid("sector out of bounds"))?;
+            data.extend_from_slice(sector);
+            sector_id = self.fat.get(sector_id as usize).copied().unwrap_or(END_OF_CHAIN);
+        }
+
+        data.truncate(limit);
+        Ok(data)
+    }
+
+    fn read_mini_chain(&self, start: u32, size: usize) -> Result<Vec<u8>> {
+        const MINI_SECTOR_SIZE: usize = 64;
+
+        let mut data = Vec::new();
+        let mut sector_id = start;
+        let mut seen = HashSet::new();
+
+        while sector_id < END_OF_CHAIN && data.len() < size && seen.insert(sector_id) {
+            let offset = sector_id as usize * MINI_SECTOR_SIZE;
+            let sector = self
+                .mini_stream
+                .get(offset..offset + MINI_SECTOR_SIZE)
+                .ok_or_else(|| invalid("mini sector out of bounds"))?;
+
+            data.extend_from_slice(sector);
+            sector_id = self.mini_fat.get(sector_id as usize).copied().unwrap_or(END_OF_CHAIN);
+        }
+
+        data.truncate(size);
+        Ok(data)
+    }
+}
+
+fn invalid(reason: &str) -> anyhow::Error {
+    AppError::Scraper(format!("Invalid compound file: {}", reason)).into()
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    const SECTOR_SIZE: usize = 512;
+    const FAT_SECTOR: u32 = 0xffff_fffd;
+    const FREE_SECTOR: u32 = 0xffff_ffff;
+
+    /// A version 3 compound file holding `streams` in its root, all in regular sectors
+    pub(in super::super) fn compound_file(streams: &[(&str, &[u8])]) -> Vec<u8> {
+        assert!(streams.len() <= 3, "the directory has a single sector");
+
+        let mut header = vec![0u8; SECTOR_SIZE];
+        header[..8].copy_from_slice(&SIGNATURE);
+        header[0x18..0x1a].copy_from_slice(&0x3eu16.to_le_bytes());
+        header[0x1a..0x1c].copy_from_slice(&3u16.to_le_bytes());
+        header[0x1c..0x1e].copy_from_slice(&0xfffeu16.to_le_bytes());
+        header[0x1e..0x20].copy_from_slice(&9u16.to_le_bytes());
+        header[0x20..0x22].copy_from_slice(&6u16.to_le_bytes());
+        header[0x2c..0x30].copy_from_slice(&1u32.to_le_bytes());
+        header[0x30..0x34].copy_from_slice(&1u32.to_le_bytes());
+        // No stream is small enough for the mini stream
+        header[0x38..0x3c].copy_from_slice(&0u32.to_le_bytes());
+        header[0x3c..0x40].copy_from_slice(&END_OF_CHAIN.to_le_bytes());
+        header[0x44..0x48].copy_from_slice(&END_OF_CHAIN.to_le_bytes());
+        for i in 0..HEADER_DIFAT_ENTRIES {
+            let entry = if i == 0 { 0 } else { FREE_SECTOR };
+            header[0x4c + i * 4..0x50 + i * 4].copy_from_slice(&entry.to_le_bytes());
+        }
+
+        // Sector 0 holds the FAT, sector 1 the directory and the streams follow
+        let mut fat = vec![FAT_SECTOR, END_OF_CHAIN];
+        let mut directory = vec![directory_entry("Root Entry", ROOT_ENTRY, END_OF_CHAIN, 0)];
+        let mut sectors = Vec::new();
+
+        for (name, data) in streams {
+            let start = fat.len() as u32;
+            let count = data.len().div_ceil(SECTOR_SIZE).max(1);
+            for i in 1..count {
+                fat.push(start + i as u32);
+            }
+            fat.push(END_OF_CHAIN);
+
+            directory.push(directory_entry(name, STREAM_ENTRY, start, data.len()));
+            let mut padded = data.to_vec();
+            padded.resize(count * SECTOR_SIZE, 0);
+            sectors.extend(padded);
+        }
+
+        let mut file = header;
+        let mut fat_sector: Vec<u8> = fat.iter().flat_map(|entry| entry.to_le_bytes()).collect();
+        fat_sector.resize(SECTOR_SIZE, 0xff);
+        file.extend(fat_sector);
+        let mut directory_sector = directory.concat();
+        directory_sector.resize(SECTOR_SIZE, 0);
+        file.extend(directory_sector);
+        file.extend(sectors);
+        file
+    }
+
+    fn directory_entry(name: &str, kind: u8, start_sector: u32, size: usize) -> Vec<u8> {
+        let mut entry = vec![0u8; DIRECTORY_ENTRY_SIZE];
+        let name: Vec<u8> = name.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect();
+        entry[..name.len()].copy_from_slice(&name);
+        entry[0x40..0x42].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        entry[0x42] = kind;
+        entry[0x44..0x50].copy_from_slice(&[0xff; 12]);
+        entry[0x74..0x78].copy_from_slice(&start_sector.to_le_bytes());
+        entry[0x78..0x7c].copy_from_slice(&(size as u32).to_le_bytes());
+        entry
+    }
+
+    #[test]
+    fn reads_streams_by_name() {
+        let long: Vec<u8> = (0..1300).map(|i| i as u8).collect();
+        let data = compound_file(&[("Short", b"hello"), ("Long", &long)]);
+        let file = CompoundFile::new(&data).unwrap();
+
+        assert_eq!(file.read_stream("Short").unwrap().as_deref(), Some(&b"hello"[..]));
+        assert_eq!(file.read_stream("Long").unwrap(), Some(long));
+        assert_eq!(file.read_stream("Missing").unwrap(), None);
+    }
+
+    #[test]
+    fn stops_at_loops_in_a_chain() {
+        let mut data = compound_file(&[("Stream", &[7u8; 1024])]);
+        // Point the stream's second sector back at its first
+        let fat = SECTOR_SIZE;
+        data[fat + 3 * 4..fat + 4 * 4].copy_from_slice(&2u32.to_le_bytes());
+
+        let file = CompoundFile::new(&data).unwrap();
+        assert_eq!(file.read_stream("Stream").unwrap().unwrap().len(), 1024);
+    }
+
+    #[test]
+    fn rejects_truncated_and_malformed_files() {
+        let data = compound_file(&[("Stream", &[1u8; 600])]);
+
+        assert!(CompoundFile::new(&data[..8]).is_err());
+        assert!(CompoundFile::new(&data[..SECTOR_SIZE]).is_err());
+        assert!(CompoundFile::new(&data[1..]).is_err());
+
+        let mut bad_sector_size = data.clone();
+        bad_sector_size[0x1e] = 7;
+        assert!(CompoundFile::new(&bad_sector_size).is_err());
+
+        // The stream's sectors are cut off
+        let file = CompoundFile::new(&data[..3 * SECTOR_SIZE + 100]).unwrap();
+        assert!(file.read_stream("Stream").is_err());
+    }
+}
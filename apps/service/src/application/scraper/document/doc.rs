@@ -0,0 +1,231 @@
+use anyhow::Result;
+
+use super::cfb::CompoundFile;
+use super::{decode_windows_1252, hyperlink_target, read_u16, read_u32, ExtractedDocument, MarkdownWriter, MAX_TEXT_BYTES};
+use crate::utils::error::AppError;
+
+/// `wIdent` of a Word 97 or later document
+const WORD_IDENT: u16 = 0xa5ec;
+const FLAG_ENCRYPTED: u16 = 0x0100;
+const FLAG_TABLE_1: u16 = 0x0200;
+
+/// Offsets into the FIB: the length of the main text and the location of the piece table
+const CCP_TEXT_OFFSET: usize = 0x4c;
+const FC_CLX_OFFSET: usize = 0x1a2;
+const LCB_CLX_OFFSET: usize = 0x1a6;
+
+/// Piece descriptors with this bit in their file offset hold 8-bit text
+const COMPRESSED_PIECE: u32 = 0x4000_0000;
+
+/// Extract a legacy Word (97 and later) document.
+///
+/// The main text is read through the piece table, which also covers documents
+/// that were saved with fast saves. Formatting isn't interpreted, so every
+/// paragraph becomes a plain paragraph; the targets of `HYPERLINK` fields are
+/// reported as links.
+pub fn extract(body: &[u8]) -> Result<ExtractedDocument> {
+    let file = CompoundFile::new(body)?;
+
+    let word_document = file
+        .read_stream("WordDocument")?
+        .ok_or_else(|| AppError::Scraper("Not a Word document: no WordDocument stream".to_string()))?;
+
+    if read_u16(&word_document, 0) != Some(WORD_IDENT) {
+        return Err(AppError::Scraper("Unsupported Word document version".to_string()).into());
+    }
+
+    let flags = read_u16(&word_document, 0x0a).unwrap_or(0);
+    if flags & FLAG_ENCRYPTED != 0 {
+        return Err(AppError::Scraper("Encrypted Word documents are not supported".to_string()).into());
+    }
+
+    let table_name = if flags & FLAG_TABLE_1 != 0 { "1Table" } else { "0Table" };
+    let table = file
+        .read_stream(table_name)?
+        .ok_or_else(|| AppError::Scraper(format!("Invalid Word document: no {} stream", table_name)))?;
+
+    let text_length = read_u32(&word_document, CCP_TEXT_OFFSET).unwrap_or(0) as usize;
+    let clx_offset = read_u32(&word_document, FC_CLX_OFFSET).unwrap_or(0) as usize;
+    let clx_length = read_u32(&word_document, LCB_CLX_OFFSET).unwrap_or(0) as usize;
+
+    let clx = table
+        .get(clx_offset..clx_offset.saturating_add(clx_length))
+        .ok_or_else(|| AppError::Scraper("Invalid Word document: piece table out of bounds".to_string()))?;
+
+    let text = read_pieces(&word_document, clx, text_length)?;
+    let (text, links) = strip_fields(&text);
+
+    let mut markdown = MarkdownWriter::default();
+    for paragraph in text.split('\r') {
+        markdown.paragraph(paragraph);
+    }
+
+    Ok(ExtractedDocument {
+        title: None,
+        markdown: markdown.finish(),
+        links,
+    })
+}
+
+/// Read the first `text_length` characters of the document through the piece table in `clx`
+fn read_pieces(word_document: &[u8], clx: &[u8], text_length: usize) -> Result<String> {
+    // Skip the property modifiers that precede the piece table
+    let mut offset = 0;
+    while clx.get(offset) == Some(&0x01) {
+        offset += 3 + read_u16(clx, offset + 1).unwrap_or(0) as usize;
+    }
+
+    if clx.get(offset) != Some(&0x02) {
+        return Err(AppError::Scraper("Invalid Word document: no piece table".to_string()).into());
+    }
+
+    let table_length = read_u32(clx, offset + 1).unwrap_or(0) as usize;
+    let pieces = clx
+        .get(offset + 5..offset + 5 + table_length)
+        .ok_or_else(|| AppError::Scraper("Invalid Word document: truncated piece table".to_string()))?;
+
+    // n + 1 character positions are followed by n piece descriptors of 8 bytes
+    let count = table_length.saturating_sub(4) / 12;
+    let mut text = String::new();
+
+    for i in 0..count {
+        let start = read_u32(pieces, i * 4).unwrap_or(0) as usize;
+        let end = (read_u32(pieces, (i + 1) * 4).unwrap_or(0) as usize).min(text_length);
+        if start >= end {
+            continue;
+        }
+
+        let file_offset = read_u32(pieces, (count + 1) * 4 + i * 8 + 2).unwrap_or(0);
+        let length = end - start;
+
+        if file_offset & COMPRESSED_PIECE != 0 {
+            let position = ((file_offset & !COMPRESSED_PIECE) / 2) as usize;
+            if let Some(bytes) = word_document.get(position..position + length) {
+                text.push_str(&decode_windows_1252(bytes));
+            }
+        } else {
+            let position = file_offset as usize;
+            if let Some(bytes) = word_document.get(position..position + length * 2) {
+                let units: Vec<u16> = bytes.chunks_exact(2).map(|unit| u16::from_le_bytes([unit[0], unit[1]])).collect();
+                text.push_str(&String::from_utf16_lossy(&units));
+            }
+        }
+
+        if text.len() >= MAX_TEXT_BYTES {
+            break;
+        }
+    }
+
+    Ok(text)
+}
+
+/// Drop field instructions and Word's control characters, keeping field results and collecting hyperlink targets
+fn strip_fields(text: &str) -> (String, Vec<String>) {
+    let mut result = String::with_capacity(text.len());
+    let mut links = Vec::new();
+
+    // For each open field, its instruction while it is still being read
+    let mut fields: Vec<Option<String>> = Vec::new();
+
+    for c in text.chars() {
+        match c {
+            '\u{13}' => fields.push(Some(String::new())),
+            '\u{14}' | '\u{15}' => {
+                // The separator ends the instruction, the end mark ends the field
+                if let Some(instruction) = fields.last_mut().and_then(Option::take) {
+                    links.extend(hyperlink_target(&instruction));
+                }
+                if c == '\u{15}' {
+                    fields.pop();
+                }
+            },
+            // The result of a field nested in an instruction is part of that instruction
+            _ if fields.iter().any(Option::is_some) => {
+                if let Some(instruction) = fields.iter_mut().rev().find_map(Option::as_mut) {
+                    instruction.push(c);
+                }
+            },
+            // Cell and row marks
+            '\u{07}' => result.push('\r'),
+            '\u{0b}' => result.push('\n'),
+            '\u{0c}' => result.push('\r'),
+            '\u{1e}' => result.push('-'),
+            '\u{a0}' => result.push(' '),
+            '\t' | '\r' => result.push(c),
+            _ if c.is_control() || c == '\u{1f}' => {},
+            _ => result.push(c),
+        }
+    }
+
+    (result, links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::cfb::tests::compound_file;
+    use super::*;
+
+    /// Where the text is stored in the WordDocument stream
+    const TEXT_OFFSET: usize = 0x400;
+
+    /// A Word document whose text is one 8-bit piece
+    fn word_document(text: &[u8], flags: u16) -> Vec<u8> {
+        let mut word_document = vec![0u8; TEXT_OFFSET];
+        word_document[0..2].copy_from_slice(&WORD_IDENT.to_le_bytes());
+        word_document[0x0a..0x0c].copy_from_slice(&(flags | FLAG_TABLE_1).to_le_bytes());
+        word_document[CCP_TEXT_OFFSET..CCP_TEXT_OFFSET + 4].copy_from_slice(&(text.len() as u32).to_le_bytes());
+        word_document[FC_CLX_OFFSET..FC_CLX_OFFSET + 4].copy_from_slice(&0u32.to_le_bytes());
+        word_document[LCB_CLX_OFFSET..LCB_CLX_OFFSET + 4].copy_from_slice(&21u32.to_le_bytes());
+        word_document.extend_from_slice(text);
+
+        // One piece: character positions 0 and n, then its descriptor
+        let mut clx = vec![0x02];
+        clx.extend_from_slice(&16u32.to_le_bytes());
+        clx.extend_from_slice(&0u32.to_le_bytes());
+        clx.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        clx.extend_from_slice(&0u16.to_le_bytes());
+        clx.extend_from_slice(&((TEXT_OFFSET as u32 * 2) | COMPRESSED_PIECE).to_le_bytes());
+        clx.extend_from_slice(&0u16.to_le_bytes());
+
+        compound_file(&[("WordDocument", &word_document), ("1Table", &clx)])
+    }
+
+    #[test]
+    fn extracts_paragraphs_and_hyperlinks() {
+        let text = b"Caf\xe9 menu\r\x13 HYPERLINK \"https://example.com/menu\" \\o \"Menu\" \x14See the menu\x15\rA\x0bB\x07\r";
+        let document = extract(&word_document(text, 0)).unwrap();
+
+        assert_eq!(document.markdown, "Caf\u{e9} menu\n\nSee the menu\n\nA\nB\n");
+        assert_eq!(document.links, vec!["https://example.com/menu"]);
+        assert_eq!(document.title, None);
+    }
+
+    #[test]
+    fn drops_instructions_of_nested_fields() {
+        let (text, links) = strip_fields("a\u{13}IF \u{13}PAGE\u{14}1\u{15} = 1\u{14}b\u{15}c");
+
+        assert_eq!(text, "abc");
+        assert!(links.is_empty());
+    }
+
+    #[test]
+    fn rejects_encrypted_and_malformed_documents() {
+        assert!(extract(&word_document(b"secret\r", FLAG_ENCRYPTED)).is_err());
+        assert!(extract(&compound_file(&[("1Table", b"")])).is_err());
+
+        let body = word_document(b"text\r", 0);
+        for length in [0, 100, body.len() / 2] {
+            assert!(extract(&body[..length]).is_err(), "length {}", length);
+        }
+
+        // A piece table that points past the end of the stream yields no text rather than failing
+        let mut clx = vec![0x02];
+        clx.extend_from_slice(&16u32.to_le_bytes());
+        clx.extend_from_slice(&0u32.to_le_bytes());
+        clx.extend_from_slice(&100u32.to_le_bytes());
+        clx.extend_from_slice(&[0, 0, 0xff, 0xff, 0xff, 0x3f, 0, 0]);
+        assert_eq!(read_pieces(&[0u8; 64], &clx, 100).unwrap(), "");
+        assert!(read_pieces(&[0u8; 64], &clx[..10], 100).is_err());
+        assert!(read_pieces(&[0u8; 64], &[0x01, 0xff, 0xff], 100).is_err());
+    }
+}
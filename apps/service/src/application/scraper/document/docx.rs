@@ -0,0 +1,355 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::Cursor;
+use xmlparser::{ElementEnd, Token, Tokenizer};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use super::{DecompressionBudget, ExtractedDocument, MarkdownWriter, MAX_DOCUMENT_DECOMPRESSED_BYTES};
+use crate::utils::error::AppError;
+use crate::utils::xml::unescape;
+
+const DOCUMENT_PART: &str = "word/document.xml";
+const DOCUMENT_RELATIONSHIPS_PART: &str = "word/_rels/document.xml.rels";
+const CORE_PROPERTIES_PART: &str = "docProps/core.xml";
+
+/// Extract a Word (Office Open XML) document.
+///
+/// Paragraphs with a heading or title style become headings, numbered
+/// paragraphs list items, and top-level tables Markdown tables. Hyperlinks are
+/// kept as Markdown links and reported as links of the document.
+pub fn extract(body: &[u8]) -> Result<ExtractedDocument> {
+    let mut archive = ZipArchive::new(Cursor::new(body)).map_err(|e| AppError::Scraper(format!("Invalid zip archive: {}", e)))?;
+    let budget = DecompressionBudget::new(MAX_DOCUMENT_DECOMPRESSED_BYTES);
+
+    let document = read_part(&mut archive, DOCUMENT_PART, &budget)?
+        .ok_or_else(|| AppError::Scraper("Not a Word document: the archive has no word/document.xml".to_string()))?;
+
+    let hyperlinks = match read_part(&mut archive, DOCUMENT_RELATIONSHIPS_PART, &budget)? {
+        Some(relationships) => parse_hyperlinks(&String::from_utf8_lossy(&relationships))?,
+        None => HashMap::new(),
+    };
+
+    let (markdown, first_heading) = render(&String::from_utf8_lossy(&document), &hyperlinks)?;
+
+    let title = match read_part(&mut archive, CORE_PROPERTIES_PART, &budget)? {
+        Some(properties) => parse_title(&String::from_utf8_lossy(&properties))?,
+        None => None,
+    };
+
+    let mut links: Vec<String> = hyperlinks.into_values().collect();
+    links.sort();
+    links.dedup();
+
+    Ok(ExtractedDocument {
+        title: title.or(first_heading),
+        markdown,
+        links,
+    })
+}
+
+/// Decompress a part of the archive, or return `None` if it has no part of that name
+fn read_part(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str, budget: &DecompressionBudget) -> Result<Option<Vec<u8>>> {
+    let part = match archive.by_name(name) {
+        Ok(part) => part,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(AppError::Scraper(format!("Failed to open {}: {}", name, e)).into()),
+    };
+
+    let mut data = Vec::new();
+    budget.read(part, &mut data)?;
+    Ok(Some(data))
+}
+
+/// Targets of the external hyperlink relationships, by relationship ID
+fn parse_hyperlinks(xml: &str) -> Result<HashMap<String, String>> {
+    let mut hyperlinks = HashMap::new();
+    let mut attributes: HashMap<String, String> = HashMap::new();
+    let mut in_relationship = false;
+
+    for token in Tokenizer::from(xml) {
+        match token.map_err(invalid_xml)? {
+            Token::ElementStart { local, .. } => {
+                in_relationship = local.as_str() == "Relationship";
+                attributes.clear();
+            },
+            Token::Attribute { local, value, .. } if in_relationship => {
+                attributes.insert(local.to_string(), unescape(value.as_str()));
+            },
+            Token::ElementEnd { .. } if in_relationship => {
+                in_relationship = false;
+
+                let is_hyperlink = attributes.get("Type").is_some_and(|kind| kind.ends_with("/hyperlink"));
+                let is_external = attributes.get("TargetMode").is_some_and(|mode| mode == "External");
+
+                if is_hyperlink && is_external {
+                    if let (Some(id), Some(target)) = (attributes.remove("Id"), attributes.remove("Target")) {
+                        hyperlinks.insert(id, target);
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    Ok(hyperlinks)
+}
+
+/// The `dc:title` of the core properties
+fn parse_title(xml: &str) -> Result<Option<String>> {
+    let mut in_title = false;
+    let mut title = String::new();
+
+    for token in Tokenizer::from(xml) {
+        match token.map_err(invalid_xml)? {
+            Token::ElementStart { local, .. } => in_title = local.as_str() == "title",
+            Token::Text { text } if in_title => title.push_str(&unescape(text.as_str())),
+            Token::ElementEnd { end: ElementEnd::Close(..) | ElementEnd::Empty, .. } => in_title = false,
+            _ => {}
+        }
+    }
+
+    let title = title.trim();
+    Ok((!title.is_empty()).then(|| title.to_string()))
+}
+
+/// Where the text of a paragraph goes once it ends
+enum ParagraphKind {
+    Body,
+    Heading(usize),
+    ListItem,
+}
+
+/// Render the main document part as Markdown and return it with the text of its first heading
+fn render(xml: &str, hyperlinks: &HashMap<String, String>) -> Result<(String, Option<String>)> {
+    let mut markdown = MarkdownWriter::default();
+    let mut first_heading = None;
+
+    let mut element = String::new();
+    let mut paragraph = String::new();
+    let mut kind = ParagraphKind::Body;
+    let mut in_text = false;
+    let mut link_target: Option<String> = None;
+
+    // Only the outermost table is rendered as a table, nested ones are flattened into its cells
+    let mut table_depth = 0;
+    let mut rows: Vec<Vec<String>> = Vec::new();
+
+    for token in Tokenizer::from(xml) {
+        match token.map_err(invalid_xml)? {
+            Token::ElementStart { prefix, local, .. } if prefix.as_str() == "w" => {
+                element = local.to_string();
+
+                match local.as_str() {
+                    "p" => {
+                        paragraph.clear();
+                        kind = ParagraphKind::Body;
+                    },
+                    "t" => in_text = true,
+                    "tab" => paragraph.push('\t'),
+                    "br" | "cr" => paragraph.push('\n'),
+                    // Numbered headings stay headings
+                    "numPr" if !matches!(kind, ParagraphKind::Heading(_)) => kind = ParagraphKind::ListItem,
+                    "hyperlink" => link_target = None,
+                    "tbl" => {
+                        table_depth += 1;
+                        if table_depth == 1 {
+                            rows.clear();
+                        }
+                    },
+                    "tr" if table_depth == 1 => rows.push(Vec::new()),
+                    "tc" if table_depth == 1 => {
+                        if let Some(row) = rows.last_mut() {
+                            row.push(String::new());
+                        }
+                    },
+                    _ => {}
+                }
+            },
+            Token::ElementStart { local, .. } => element = local.to_string(),
+            Token::Attribute { local, value, .. } => {
+                let value = unescape(value.as_str());
+
+                match (element.as_str(), local.as_str()) {
+                    ("pStyle", "val") => {
+                        if let Some(level) = heading_level(&value) {
+                            kind = ParagraphKind::Heading(level);
+                        } else if value.starts_with("List") && !matches!(kind, ParagraphKind::Heading(_)) {
+                            kind = ParagraphKind::ListItem;
+                        }
+                    },
+                    ("hyperlink", "id") => {
+                        if let Some(target) = hyperlinks.get(&value) {
+                            link_target = Some(target.clone());
+                            paragraph.push('[');
+                        }
+                    },
+                    _ => {}
+                }
+            },
+            Token::Text { text } | Token::Cdata { text, .. } if in_text => {
+                paragraph.push_str(&unescape(text.as_str()));
+            },
+            Token::ElementEnd { end: ElementEnd::Empty, .. } if element == "t" => in_text = false,
+            Token::ElementEnd { end: ElementEnd::Close(prefix, local), .. } if prefix.as_str() == "w" => {
+                match local.as_str() {
+                    "t" => in_text = false,
+                    "hyperlink" => {
+                        if let Some(target) = link_target.take() {
+                            paragraph.push_str(&format!("]({})", target));
+                        }
+                    },
+                    "p" => {
+                        let text = std::mem::take(&mut paragraph);
+
+                        if table_depth > 0 {
+                            if let Some(cell) = rows.last_mut().and_then(|row| row.last_mut()) {
+                                cell.push(' ');
+                                cell.push_str(&text);
+                            }
+                        } else {
+                            match kind {
+                                ParagraphKind::Heading(level) => {
+                                    if first_heading.is_none() && !text.trim().is_empty() {
+                                        first_heading = Some(text.split_whitespace().collect::<Vec<_>>().join(" "));
+                                    }
+                                    markdown.heading(level, &text);
+                                },
+                                ParagraphKind::ListItem => markdown.list_item(&text),
+                                ParagraphKind::Body => markdown.paragraph(&text),
+                            }
+                        }
+                    },
+                    "tbl" => {
+                        table_depth -= 1;
+                        if table_depth == 0 {
+                            markdown.table(&rows);
+                        }
+                    },
+                    _ => {}
+                }
+            },
+            _ => {}
+        }
+    }
+
+    Ok((markdown.finish(), first_heading))
+}
+
+/// Level of a built-in heading style such as `Heading2`, with the title as level 1
+fn heading_level(style: &str) -> Option<usize> {
+    if style == "Title" {
+        return Some(1);
+    }
+
+    let level = style.strip_prefix("Heading")?.trim_start().parse::<usize>().ok()?;
+    (1..=6).contains(&level).then_some(level)
+}
+
+fn invalid_xml(e: xmlparser::Error) -> AppError {
+    AppError::Scraper(format!("Invalid Word document XML: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    const DOCUMENT: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">
+<w:body>
+<w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Release Notes</w:t></w:r></w:p>
+<w:p><w:r><w:t xml:space="preserve">Read the </w:t></w:r><w:hyperlink r:id="rId5"><w:r><w:t>changelog</w:t></w:r></w:hyperlink><w:r><w:t> &amp; upgrade.</w:t></w:r></w:p>
+<w:p><w:pPr><w:numPr><w:ilvl w:val="0"/></w:numPr></w:pPr><w:r><w:t>First item</w:t></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="ListParagraph"/></w:pPr><w:r><w:t>Second item</w:t></w:r></w:p>
+<w:tbl>
+<w:tr><w:tc><w:p><w:r><w:t>Name</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>Value</w:t></w:r></w:p></w:tc></w:tr>
+<w:tr><w:tc><w:p><w:r><w:t>a|b</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>1</w:t></w:r></w:p></w:tc></w:tr>
+</w:tbl>
+</w:body>
+</w:document>"#;
+
+    const RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>
+<Relationship Id="rId5" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com/changelog?a=1&amp;b=2" TargetMode="External"/>
+</Relationships>"#;
+
+    const CORE_PROPERTIES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:title>Release 2.0</dc:title><dc:creator>Someone</dc:creator>
+</cp:coreProperties>"#;
+
+    fn archive(parts: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in parts {
+            writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn extracts_headings_links_lists_and_tables() {
+        let body = archive(&[(DOCUMENT_PART, DOCUMENT), (DOCUMENT_RELATIONSHIPS_PART, RELATIONSHIPS), (CORE_PROPERTIES_PART, CORE_PROPERTIES)]);
+        let document = extract(&body).unwrap();
+
+        assert_eq!(
+            document.markdown,
+            "# Release Notes\n\n\
+             Read the [changelog](https://example.com/changelog?a=1&b=2) & upgrade.\n\n\
+             - First item\n- Second item\n\n\
+             | Name | Value |\n| --- | --- |\n| a\\|b | 1 |\n"
+        );
+        assert_eq!(document.title.as_deref(), Some("Release 2.0"));
+        assert_eq!(document.links, vec!["https://example.com/changelog?a=1&b=2"]);
+    }
+
+    #[test]
+    fn falls_back_to_the_first_heading_for_the_title() {
+        let document = extract(&archive(&[(DOCUMENT_PART, DOCUMENT)])).unwrap();
+
+        assert_eq!(document.title.as_deref(), Some("Release Notes"));
+        assert!(document.links.is_empty());
+        assert!(document.markdown.contains("Read the changelog & upgrade."));
+    }
+
+    #[test]
+    fn rejects_archives_without_a_document() {
+        let error = extract(&archive(&[("xl/workbook.xml", "<workbook/>")])).unwrap_err();
+
+        assert!(error.to_string().contains("word/document.xml"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_truncated_and_malformed_archives() {
+        let body = archive(&[(DOCUMENT_PART, DOCUMENT)]);
+
+        for length in [0, 4, 30, body.len() / 2, body.len() - 1] {
+            assert!(extract(&body[..length]).is_err(), "length {}", length);
+        }
+
+        let mut corrupt = body.clone();
+        let data_start = 30 + DOCUMENT_PART.len();
+        for byte in &mut corrupt[data_start..data_start + 16] {
+            *byte ^= 0xff;
+        }
+        assert!(extract(&corrupt).is_err());
+
+        let broken_xml = archive(&[(DOCUMENT_PART, "<w:document><w:p w:rsidR=></w:p></w:document>")]);
+        assert!(extract(&broken_xml).is_err());
+    }
+
+    #[test]
+    fn counts_every_part_against_the_budget() {
+        let body = archive(&[(DOCUMENT_PART, DOCUMENT), (DOCUMENT_RELATIONSHIPS_PART, RELATIONSHIPS)]);
+        let mut archive = ZipArchive::new(Cursor::new(body.as_slice())).unwrap();
+        let budget = DecompressionBudget::new(DOCUMENT.len() + 10);
+
+        assert!(read_part(&mut archive, DOCUMENT_PART, &budget).unwrap().is_some());
+        assert!(read_part(&mut archive, DOCUMENT_RELATIONSHIPS_PART, &budget).is_err());
+        assert!(read_part(&mut archive, "missing.xml", &budget).unwrap().is_none());
+    }
+}
@@ -0,0 +1,405 @@
+use anyhow::Result;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use std::cell::Cell;
+use std::io::Read;
+
+use crate::utils::error::AppError;
+
+mod cfb;
+mod doc;
+mod docx;
+mod pdf;
+mod rtf;
+
+/// Largest amount of data decompressed from a single part of a document, to guard against zip bombs
+pub const MAX_DECOMPRESSED_BYTES: usize = 64 * 1024 * 1024;
+
+/// Largest amount of data decompressed from all the parts of a document together
+pub const MAX_DOCUMENT_DECOMPRESSED_BYTES: usize = 256 * 1024 * 1024;
+
+/// Largest amount of text extracted from a document
+pub const MAX_TEXT_BYTES: usize = 16 * 1024 * 1024;
+
+/// A non-HTML format whose text can be extracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Pdf,
+    Docx,
+    Doc,
+    Rtf,
+    Text,
+}
+
+impl DocumentKind {
+    /// The kind of a MIME type without parameters, e.g. `application/pdf`
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type {
+            "application/pdf" | "application/x-pdf" => Some(Self::Pdf),
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => Some(Self::Docx),
+            "application/msword" => Some(Self::Doc),
+            "application/rtf" | "text/rtf" | "application/x-rtf" => Some(Self::Rtf),
+            "text/plain" => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    /// Whether a response of this MIME type is generic and has to be sniffed to find its kind
+    pub fn is_generic_mime_type(mime_type: &str) -> bool {
+        matches!(
+            mime_type,
+            "application/octet-stream" | "binary/octet-stream" | "application/x-download" | "application/download" | "application/force-download"
+        )
+    }
+
+    /// Recognise a document from its first bytes
+    pub fn sniff(body: &[u8]) -> Option<Self> {
+        if body.starts_with(b"%PDF-") {
+            Some(Self::Pdf)
+        } else if body.starts_with(b"PK\x03\x04") {
+            // Other zip based formats fail extraction when they lack a Word document
+            Some(Self::Docx)
+        } else if body.starts_with(&cfb::SIGNATURE) {
+            Some(Self::Doc)
+        } else if body.starts_with(b"{\\rtf") {
+            Some(Self::Rtf)
+        } else {
+            None
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Pdf => "application/pdf",
+            Self::Docx => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            Self::Doc => "application/msword",
+            Self::Rtf => "application/rtf",
+            Self::Text => "text/plain",
+        }
+    }
+
+    /// File extension used when the document is stored
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Pdf => "pdf",
+            Self::Docx => "docx",
+            Self::Doc => "doc",
+            Self::Rtf => "rtf",
+            Self::Text => "txt",
+        }
+    }
+}
+
+/// Text extracted from a document, rendered as Markdown
+#[derive(Debug, Default)]
+pub struct ExtractedDocument {
+    pub title: Option<String>,
+    pub markdown: String,
+    /// Link targets found in the document, possibly relative
+    pub links: Vec<String>,
+}
+
+/// Extract the text, title and links of a document.
+///
+/// `charset` is the charset parameter of the response, which only applies to
+/// plain text. Extraction is CPU bound, so callers on the runtime should run it
+/// on a blocking thread.
+pub fn extract(kind: DocumentKind, body: &[u8], charset: Option<&str>) -> Result<ExtractedDocument> {
+    match kind {
+        DocumentKind::Pdf => pdf::extract(body),
+        DocumentKind::Docx => docx::extract(body),
+        DocumentKind::Doc => doc::extract(body),
+        DocumentKind::Rtf => rtf::extract(body),
+        DocumentKind::Text => Ok(extract_text(body, charset)),
+    }
+}
+
+fn extract_text(body: &[u8], charset: Option<&str>) -> ExtractedDocument {
+    let encoding = charset
+        .and_then(|charset| Encoding::for_label(charset.as_bytes()))
+        .unwrap_or(UTF_8);
+
+    // A byte order mark overrides the declared charset
+    let (text, _, _) = encoding.decode(truncate(body, MAX_TEXT_BYTES));
+    let text = text.replace("\r\n", "\n");
+
+    let mut markdown = MarkdownWriter::default();
+    for paragraph in text.split("\n\n") {
+        markdown.paragraph(paragraph);
+    }
+
+    ExtractedDocument {
+        title: None,
+        markdown: markdown.finish(),
+        links: Vec::new(),
+    }
+}
+
+/// What is left of a document's allowance of decompressed data.
+///
+/// Every part decompressed from a document draws on the same budget, so that
+/// many parts which are each within [`MAX_DECOMPRESSED_BYTES`] can't add up to
+/// a zip bomb.
+struct DecompressionBudget {
+    remaining: Cell<usize>,
+}
+
+impl DecompressionBudget {
+    fn new(limit: usize) -> Self {
+        Self { remaining: Cell::new(limit) }
+    }
+
+    /// Decompress a part to its end, appending it to `output`.
+    ///
+    /// Fails once the part goes past the limit of a part or what is left of the
+    /// document's budget; what was decompressed before a failure stays in
+    /// `output`.
+    fn read(&self, reader: impl Read, output: &mut Vec<u8>) -> Result<()> {
+        let remaining = self.remaining.get();
+        let limit = MAX_DECOMPRESSED_BYTES.min(remaining);
+        let start = output.len();
+
+        let result = reader.take(limit as u64 + 1).read_to_end(output);
+
+        let read = output.len() - start;
+        self.remaining.set(remaining - read.min(limit));
+
+        if read > limit {
+            output.truncate(start + limit);
+            return Err(AppError::Scraper(format!("Document decompresses to more than {} bytes", limit)).into());
+        }
+
+        result.map_err(|e| AppError::Scraper(format!("Failed to decompress document: {}", e)))?;
+        Ok(())
+    }
+}
+
+fn truncate(body: &[u8], limit: usize) -> &[u8] {
+    &body[..body.len().min(limit)]
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset.checked_add(2)?).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset.checked_add(4)?).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Decode single byte text as Windows-1252, which legacy formats use when no other code page is given
+fn decode_windows_1252(bytes: &[u8]) -> String {
+    WINDOWS_1252.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// The target of a `HYPERLINK` field instruction as used by Word and RTF, e.g. `HYPERLINK "https://example.com" \o "tip"`
+fn hyperlink_target(instruction: &str) -> Option<String> {
+    let rest = instruction.trim_start().strip_prefix("HYPERLINK")?.trim_start();
+
+    // Switches such as `\l` (a bookmark) come before or after the target
+    let mut rest = rest;
+    while let Some(switch) = rest.strip_prefix('\\') {
+        let end = switch.find(char::is_whitespace).unwrap_or(switch.len());
+        rest = switch[end..].trim_start();
+    }
+
+    let target = match rest.strip_prefix('"') {
+        Some(quoted) => &quoted[..quoted.find('"').unwrap_or(quoted.len())],
+        None => &rest[..rest.find(char::is_whitespace).unwrap_or(rest.len())],
+    };
+
+    let target = target.trim();
+    (!target.is_empty()).then(|| target.to_string())
+}
+
+/// Builds the Markdown rendition of a document block by block
+#[derive(Default)]
+struct MarkdownWriter {
+    out: String,
+}
+
+impl MarkdownWriter {
+    fn heading(&mut self, level: usize, text: &str) {
+        let text = collapse_whitespace(text);
+        if !text.is_empty() {
+            self.block(&format!("{} {}", "#".repeat(level.clamp(1, 6)), text));
+        }
+    }
+
+    /// A paragraph whose line breaks are kept
+    fn paragraph(&mut self, text: &str) {
+        let lines: Vec<String> = text
+            .lines()
+            .map(|line| escape_line(line.trim_end()))
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+
+        if !lines.is_empty() {
+            self.block(&lines.join("\n"));
+        }
+    }
+
+    fn list_item(&mut self, text: &str) {
+        let text = collapse_whitespace(text);
+        if text.is_empty() {
+            return;
+        }
+
+        // Consecutive items form one list
+        if self.out.ends_with("\n\n") && self.out.trim_end().lines().last().is_some_and(|line| line.starts_with("- ")) {
+            self.out.pop();
+        }
+
+        self.block(&format!("- {}", text));
+    }
+
+    fn table(&mut self, rows: &[Vec<String>]) {
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+
+        let mut table = String::new();
+        for (i, row) in rows.iter().enumerate() {
+            let cells: Vec<String> = (0..columns)
+                .map(|column| {
+                    let cell = row.get(column).map(String::as_str).unwrap_or("");
+                    collapse_whitespace(cell).replace('|', "\\|")
+                })
+                .collect();
+
+            table.push_str(&format!("| {} |\n", cells.join(" | ")));
+
+            if i == 0 {
+                table.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+            }
+        }
+
+        self.block(table.trim_end());
+    }
+
+    fn block(&mut self, block: &str) {
+        if self.out.len() >= MAX_TEXT_BYTES {
+            return;
+        }
+
+        self.out.push_str(block);
+        self.out.push_str("\n\n");
+    }
+
+    fn finish(self) -> String {
+        let mut out = self.out;
+        out.truncate(out.trim_end().len());
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Escape what would make a line of plain text start a Markdown block.
+///
+/// Leading whitespace is dropped, since indentation would otherwise turn the
+/// line into a code block.
+fn escape_line(line: &str) -> String {
+    let line = line.trim_start();
+
+    let starts_block = line.starts_with('#')
+        || line.starts_with('>')
+        || line.starts_with('|')
+        || ["-", "+", "*"].iter().any(|marker| line == *marker || line.starts_with(&format!("{} ", marker)));
+
+    if starts_block {
+        return format!("\\{}", line);
+    }
+
+    // An ordered list marker such as `1.` or `2)`
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0 && digits < 10 {
+        let rest = &line[digits..];
+        if rest == "." || rest == ")" || rest.starts_with(". ") || rest.starts_with(") ") {
+            return format!("{}\\{}", &line[..digits], rest);
+        }
+    }
+
+    line.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_documents_from_their_first_bytes() {
+        assert_eq!(DocumentKind::sniff(b"%PDF-1.7\n"), Some(DocumentKind::Pdf));
+        assert_eq!(DocumentKind::sniff(b"PK\x03\x04rest"), Some(DocumentKind::Docx));
+        assert_eq!(DocumentKind::sniff(&cfb::SIGNATURE), Some(DocumentKind::Doc));
+        assert_eq!(DocumentKind::sniff(b"{\\rtf1"), Some(DocumentKind::Rtf));
+        assert_eq!(DocumentKind::sniff(b"<!doctype html>"), None);
+        assert_eq!(DocumentKind::sniff(b""), None);
+    }
+
+    #[test]
+    fn decodes_text_in_its_charset() {
+        let document = extract(DocumentKind::Text, b"Caf\xe9\r\n# not a heading\r\n\r\n1. not a list", Some("windows-1252")).unwrap();
+        assert_eq!(document.markdown, "Caf\u{e9}\n\\# not a heading\n\n1\\. not a list\n");
+
+        // A byte order mark wins over the declared charset
+        let document = extract(DocumentKind::Text, b"\xef\xbb\xbfna\xc3\xafve", Some("iso-8859-1")).unwrap();
+        assert_eq!(document.markdown, "na\u{ef}ve\n");
+    }
+
+    #[test]
+    fn escapes_lines_that_would_start_markdown_blocks() {
+        assert_eq!(escape_line("  - item"), "\\- item");
+        assert_eq!(escape_line("> quote"), "\\> quote");
+        assert_eq!(escape_line("*"), "\\*");
+        assert_eq!(escape_line("2) second"), "2\\) second");
+        assert_eq!(escape_line("2024 was a year"), "2024 was a year");
+        assert_eq!(escape_line("-5 degrees"), "-5 degrees");
+    }
+
+    #[test]
+    fn finds_hyperlink_targets() {
+        assert_eq!(hyperlink_target(r#" HYPERLINK "https://example.com/a b" \o "tip""#).as_deref(), Some("https://example.com/a b"));
+        assert_eq!(hyperlink_target(r#"HYPERLINK \l "anchor""#).as_deref(), Some("anchor"));
+        assert_eq!(hyperlink_target("HYPERLINK https://example.com/ \\t _blank").as_deref(), Some("https://example.com/"));
+        assert_eq!(hyperlink_target(r#"HYPERLINK """#), None);
+        assert_eq!(hyperlink_target("PAGE"), None);
+    }
+
+    #[test]
+    fn writes_lists_and_tables() {
+        let mut markdown = MarkdownWriter::default();
+        markdown.heading(9, "  Deep   heading ");
+        markdown.list_item("one");
+        markdown.list_item("two");
+        markdown.paragraph("after");
+        markdown.table(&[vec!["a".to_string(), "b".to_string()], vec!["c|d".to_string()]]);
+        markdown.table(&[]);
+
+        assert_eq!(
+            markdown.finish(),
+            "###### Deep heading\n\n- one\n- two\n\nafter\n\n| a | b |\n| --- | --- |\n| c\\|d |  |\n"
+        );
+    }
+
+    #[test]
+    fn shares_the_decompression_budget_between_parts() {
+        let budget = DecompressionBudget::new(10);
+
+        let mut first = Vec::new();
+        budget.read(&b"123456"[..], &mut first).unwrap();
+        assert_eq!(first, b"123456");
+
+        let mut second = Vec::new();
+        assert!(budget.read(&b"abcdef"[..], &mut second).is_err());
+        assert_eq!(second, b"abcd");
+
+        let mut empty = Vec::new();
+        budget.read(&b""[..], &mut empty).unwrap();
+        assert!(budget.read(&b"x"[..], &mut empty).is_err());
+    }
+}
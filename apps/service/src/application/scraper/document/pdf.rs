@@ -0,0 +1,1186 @@
+use anyhow::Result;
+use flate2::read::ZlibDecoder;
+use regex::bytes::Regex;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use super::{decode_windows_1252, DecompressionBudget, ExtractedDocument, MarkdownWriter, MAX_DOCUMENT_DECOMPRESSED_BYTES, MAX_TEXT_BYTES};
+use crate::utils::error::AppError;
+
+/// How deeply form XObjects drawn by a page are followed
+const MAX_XOBJECT_DEPTH: usize = 4;
+
+/// How deeply nested arrays and dictionaries are parsed
+const MAX_NESTING: usize = 64;
+
+/// Extract a PDF document.
+///
+/// Objects are found by scanning the file rather than through the cross
+/// reference table, which also copes with damaged files and object streams.
+/// Pages are read in order and their text is decoded through each font's
+/// `ToUnicode` map or simple encoding. The title comes from the document
+/// information and link annotations are reported as links. Encrypted files and
+/// text drawn as images (scans) yield no text.
+pub fn extract(body: &[u8]) -> Result<ExtractedDocument> {
+    let document = PdfDocument::parse(body)?;
+
+    if document.trailer.contains_key("Encrypt") {
+        return Err(AppError::Scraper("Encrypted PDF documents are not supported".to_string()).into());
+    }
+
+    let mut markdown = MarkdownWriter::default();
+    let mut length = 0;
+
+    for page in document.pages() {
+        let text = document.page_text(&page);
+
+        for paragraph in text.split("\n\n") {
+            markdown.paragraph(paragraph);
+        }
+
+        length += text.len();
+        if length >= MAX_TEXT_BYTES {
+            break;
+        }
+    }
+
+    Ok(ExtractedDocument {
+        title: document.title(),
+        markdown: markdown.finish(),
+        links: document.links(),
+    })
+}
+
+#[derive(Debug, Clone)]
+enum Object {
+    Null,
+    Number(f64),
+    Name(String),
+    String(Vec<u8>),
+    Array(Vec<Object>),
+    Dictionary(Dictionary),
+    Stream(Dictionary, Vec<u8>),
+    Reference(u32),
+    /// A bare keyword such as `true`, which in a content stream is an operator
+    Keyword(String),
+}
+
+type Dictionary = HashMap<String, Object>;
+
+impl Object {
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Object::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    fn as_name(&self) -> Option<&str> {
+        match self {
+            Object::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn as_dictionary(&self) -> Option<&Dictionary> {
+        match self {
+            Object::Dictionary(dictionary) | Object::Stream(dictionary, _) => Some(dictionary),
+            _ => None,
+        }
+    }
+}
+
+/// Reads PDF objects from bytes
+struct Lexer<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a [u8], position: usize) -> Self {
+        Self { data, position }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            if is_whitespace(byte) {
+                self.position += 1;
+            } else if byte == b'%' {
+                while self.peek().is_some_and(|byte| byte != b'\r' && byte != b'\n') {
+                    self.position += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.data[self.position.min(self.data.len())..].starts_with(prefix)
+    }
+
+    /// Read the next object, or `None` at the end of the data or at a closing delimiter
+    fn next_object(&mut self, depth: usize) -> Option<Object> {
+        self.skip_whitespace();
+        let byte = self.peek()?;
+
+        if depth > MAX_NESTING {
+            self.position = self.data.len();
+            return None;
+        }
+
+        match byte {
+            b'<' if self.starts_with(b"<<") => {
+                self.position += 2;
+                let mut dictionary = Dictionary::new();
+
+                loop {
+                    self.skip_whitespace();
+                    if self.starts_with(b">>") {
+                        self.position += 2;
+                        break;
+                    }
+
+                    match self.next_object(depth + 1) {
+                        Some(Object::Name(key)) => {
+                            let value = self.next_object(depth + 1).unwrap_or(Object::Null);
+                            dictionary.insert(key, value);
+                        },
+                        Some(_) => {},
+                        None => {
+                            // Skip a stray delimiter, or stop at the end of the data
+                            if self.peek().is_none() {
+                                break;
+                            }
+                            self.position += 1;
+                        }
+                    }
+                }
+
+                Some(Object::Dictionary(dictionary))
+            },
+            b'<' => {
+                self.position += 1;
+                let start = self.position;
+                while self.peek().is_some_and(|byte| byte != b'>') {
+                    self.position += 1;
+                }
+                let hex = &self.data[start..self.position];
+                self.position += 1;
+                Some(Object::String(decode_hex(hex)))
+            },
+            b'(' => {
+                self.position += 1;
+                Some(Object::String(self.literal_string()))
+            },
+            b'/' => {
+                self.position += 1;
+                Some(Object::Name(self.name()))
+            },
+            b'[' => {
+                self.position += 1;
+                let mut array = Vec::new();
+
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b']') => {
+                            self.position += 1;
+                            break;
+                        },
+                        None => break,
+                        _ => {}
+                    }
+
+                    match self.next_object(depth + 1) {
+                        Some(object) => array.push(object),
+                        None => self.position += 1,
+                    }
+                }
+
+                Some(Object::Array(array))
+            },
+            b']' | b'>' | b')' | b'{' | b'}' => None,
+            b'0'..=b'9' | b'+' | b'-' | b'.' => {
+                let number = self.number();
+
+                // An integer followed by a generation and `R` is a reference
+                if number.fract() == 0.0 && number >= 0.0 {
+                    let saved = self.position;
+                    self.skip_whitespace();
+
+                    if self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+                        self.number();
+                        self.skip_whitespace();
+
+                        if self.peek() == Some(b'R') && self.data.get(self.position + 1).is_none_or(|&byte| is_delimiter(byte)) {
+                            self.position += 1;
+                            return Some(Object::Reference(number as u32));
+                        }
+                    }
+
+                    self.position = saved;
+                }
+
+                Some(Object::Number(number))
+            },
+            _ => {
+                let start = self.position;
+                while self.peek().is_some_and(|byte| !is_delimiter(byte)) {
+                    self.position += 1;
+                }
+
+                let keyword = String::from_utf8_lossy(&self.data[start..self.position]).into_owned();
+                Some(match keyword.as_str() {
+                    "null" => Object::Null,
+                    _ => Object::Keyword(keyword),
+                })
+            }
+        }
+    }
+
+    fn number(&mut self) -> f64 {
+        let start = self.position;
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit() || matches!(byte, b'+' | b'-' | b'.')) {
+            self.position += 1;
+        }
+
+        std::str::from_utf8(&self.data[start..self.position])
+            .ok()
+            .and_then(|number| number.parse().ok())
+            .unwrap_or(0.0)
+    }
+
+    fn name(&mut self) -> String {
+        let mut name = Vec::new();
+
+        while let Some(byte) = self.peek().filter(|&byte| !is_delimiter(byte)) {
+            self.position += 1;
+
+            // `#xx` escapes a byte
+            let escaped = (byte == b'#')
+                .then(|| self.data.get(self.position..self.position + 2))
+                .flatten()
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+            match escaped {
+                Some(escaped) => {
+                    name.push(escaped);
+                    self.position += 2;
+                },
+                None => name.push(byte),
+            }
+        }
+
+        String::from_utf8_lossy(&name).into_owned()
+    }
+
+    fn literal_string(&mut self) -> Vec<u8> {
+        let mut string = Vec::new();
+        let mut depth = 0;
+
+        while let Some(byte) = self.peek() {
+            self.position += 1;
+
+            match byte {
+                b'(' => {
+                    depth += 1;
+                    string.push(byte);
+                },
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    string.push(byte);
+                },
+                b'\\' => {
+                    let Some(escaped) = self.peek() else {
+                        break;
+                    };
+                    self.position += 1;
+
+                    match escaped {
+                        b'n' => string.push(b'\n'),
+                        b'r' => string.push(b'\r'),
+                        b't' => string.push(b'\t'),
+                        b'b' => string.push(0x08),
+                        b'f' => string.push(0x0c),
+                        b'\r' => {
+                            // A line continuation
+                            if self.peek() == Some(b'\n') {
+                                self.position += 1;
+                            }
+                        },
+                        b'\n' => {},
+                        b'0'..=b'7' => {
+                            let mut value = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        value = value * 8 + (digit - b'0') as u32;
+                                        self.position += 1;
+                                    },
+                                    _ => break,
+                                }
+                            }
+                            string.push(value as u8);
+                        },
+                        _ => string.push(escaped),
+                    }
+                },
+                _ => string.push(byte),
+            }
+        }
+
+        string
+    }
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n' | 0x0c | 0x00)
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    is_whitespace(byte) || matches!(byte, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+fn decode_hex(hex: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = hex
+        .iter()
+        .filter_map(|&byte| (byte as char).to_digit(16).map(|digit| digit as u8))
+        .collect();
+
+    // An odd final digit is followed by an implicit zero
+    digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+/// A decoded font: how the bytes of a shown string map to text
+struct Font {
+    /// Bytes per character code, 2 for composite fonts
+    code_length: usize,
+    to_unicode: HashMap<u32, String>,
+    differences: HashMap<u32, String>,
+}
+
+impl Font {
+    fn decode(&self, bytes: &[u8]) -> String {
+        let mut text = String::new();
+
+        for code in bytes.chunks(self.code_length) {
+            let code = code.iter().fold(0u32, |code, &byte| (code << 8) | byte as u32);
+
+            if let Some(mapped) = self.to_unicode.get(&code).or_else(|| self.differences.get(&code)) {
+                text.push_str(mapped);
+            } else if self.code_length == 1 {
+                text.push_str(&decode_windows_1252(&[code as u8]));
+            }
+            // Composite fonts without a ToUnicode map use glyph IDs, which can't be decoded
+        }
+
+        text
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self {
+            code_length: 1,
+            to_unicode: HashMap::new(),
+            differences: HashMap::new(),
+        }
+    }
+}
+
+struct PdfDocument {
+    objects: HashMap<u32, Object>,
+    trailer: Dictionary,
+    /// Shared by every stream, including content streams decoded again for each page that draws them
+    budget: DecompressionBudget,
+}
+
+impl PdfDocument {
+    fn parse(data: &[u8]) -> Result<Self> {
+        static OBJECT_HEADER: OnceLock<Regex> = OnceLock::new();
+        let object_header = OBJECT_HEADER.get_or_init(|| Regex::new(r"(\d+)\s+\d+\s+obj\b").unwrap());
+
+        if !data.starts_with(b"%PDF-") {
+            return Err(AppError::Scraper("Not a PDF document".to_string()).into());
+        }
+
+        let mut document = Self {
+            objects: HashMap::new(),
+            trailer: Dictionary::new(),
+            budget: DecompressionBudget::new(MAX_DOCUMENT_DECOMPRESSED_BYTES),
+        };
+
+        // Later definitions win, as incremental updates append new versions of objects
+        for captures in object_header.captures_iter(data) {
+            let Some(number) = std::str::from_utf8(&captures[1]).ok().and_then(|number| number.parse::<u32>().ok()) else {
+                continue;
+            };
+
+            let mut lexer = Lexer::new(data, captures.get(0).map_or(0, |header| header.end()));
+            let Some(object) = lexer.next_object(0) else {
+                continue;
+            };
+
+            let object = match object {
+                Object::Dictionary(dictionary) => {
+                    lexer.skip_whitespace();
+                    if lexer.starts_with(b"stream") {
+                        let stream = read_stream_data(data, lexer.position + b"stream".len(), &dictionary);
+                        Object::Stream(dictionary, stream.to_vec())
+                    } else {
+                        Object::Dictionary(dictionary)
+                    }
+                },
+                object => object,
+            };
+
+            // Cross reference streams double as the trailer
+            if let Object::Stream(dictionary, _) = &object {
+                if dictionary.get("Type").and_then(Object::as_name) == Some("XRef") {
+                    document.trailer.extend(dictionary.clone());
+                }
+            }
+
+            document.objects.insert(number, object);
+        }
+
+        let mut search = 0;
+        while let Some(offset) = find(&data[search..], b"trailer") {
+            let mut lexer = Lexer::new(data, search + offset + b"trailer".len());
+            if let Some(Object::Dictionary(dictionary)) = lexer.next_object(0) {
+                document.trailer.extend(dictionary);
+            }
+            search += offset + b"trailer".len();
+        }
+
+        document.expand_object_streams();
+        Ok(document)
+    }
+
+    /// Add the objects compressed into object streams, unless they are also defined directly
+    fn expand_object_streams(&mut self) {
+        let streams: Vec<Object> = self
+            .objects
+            .values()
+            .filter(|object| matches!(object, Object::Stream(dictionary, _) if dictionary.get("Type").and_then(Object::as_name) == Some("ObjStm")))
+            .cloned()
+            .collect();
+
+        for stream in streams {
+            let Object::Stream(dictionary, _) = &stream else {
+                continue;
+            };
+            let Some(data) = self.decode_stream(&stream) else {
+                continue;
+            };
+
+            let count = dictionary.get("N").and_then(Object::as_number).unwrap_or(0.0) as usize;
+            let first = dictionary.get("First").and_then(Object::as_number).unwrap_or(0.0) as usize;
+
+            let mut header = Lexer::new(&data, 0);
+            for _ in 0..count {
+                let (Some(Object::Number(number)), Some(Object::Number(offset))) = (header.next_object(0), header.next_object(0)) else {
+                    break;
+                };
+
+                let mut lexer = Lexer::new(&data, first + offset as usize);
+                if let Some(object) = lexer.next_object(0) {
+                    self.objects.entry(number as u32).or_insert(object);
+                }
+            }
+        }
+    }
+
+    fn resolve<'a>(&'a self, mut object: &'a Object) -> &'a Object {
+        // Bound the number of hops in case references form a loop
+        for _ in 0..32 {
+            match object {
+                Object::Reference(number) => object = self.objects.get(number).unwrap_or(&Object::Null),
+                _ => return object,
+            }
+        }
+
+        &Object::Null
+    }
+
+    fn get<'a>(&'a self, dictionary: &'a Dictionary, key: &str) -> Option<&'a Object> {
+        dictionary.get(key).map(|object| self.resolve(object))
+    }
+
+    fn get_dictionary<'a>(&'a self, dictionary: &'a Dictionary, key: &str) -> Option<&'a Dictionary> {
+        self.get(dictionary, key).and_then(Object::as_dictionary)
+    }
+
+    /// Decode a stream's data, or return `None` if it uses a filter that isn't supported
+    fn decode_stream(&self, stream: &Object) -> Option<Vec<u8>> {
+        let Object::Stream(dictionary, data) = stream else {
+            return None;
+        };
+
+        let filters = match self.get(dictionary, "Filter") {
+            Some(Object::Name(name)) => vec![name.clone()],
+            Some(Object::Array(filters)) => filters.iter().filter_map(|filter| self.resolve(filter).as_name().map(str::to_string)).collect(),
+            _ => Vec::new(),
+        };
+
+        let mut data = data.clone();
+        for filter in filters {
+            data = match filter.as_str() {
+                "FlateDecode" | "Fl" => inflate(&data, &self.budget),
+                _ => return None,
+            };
+        }
+
+        Some(data)
+    }
+
+    /// The pages in order, as dictionaries with their inherited resources filled in
+    fn pages(&self) -> Vec<Dictionary> {
+        let root = self
+            .get_dictionary(&self.trailer, "Root")
+            .or_else(|| {
+                self.objects
+                    .values()
+                    .filter_map(Object::as_dictionary)
+                    .find(|dictionary| dictionary.get("Type").and_then(Object::as_name) == Some("Catalog"))
+            });
+
+        let mut pages = Vec::new();
+        if let Some(tree) = root.and_then(|root| root.get("Pages")) {
+            let mut visited = HashSet::new();
+            self.collect_pages(tree, None, &mut pages, &mut visited);
+        }
+
+        pages
+    }
+
+    fn collect_pages(&self, node: &Object, resources: Option<&Object>, pages: &mut Vec<Dictionary>, visited: &mut HashSet<u32>) {
+        if let Object::Reference(number) = node {
+            if !visited.insert(*number) {
+                return;
+            }
+        }
+
+        let Some(dictionary) = self.resolve(node).as_dictionary() else {
+            return;
+        };
+        let resources = dictionary.get("Resources").or(resources);
+
+        match self.get(dictionary, "Kids") {
+            Some(Object::Array(kids)) => {
+                for kid in kids {
+                    self.collect_pages(kid, resources, pages, visited);
+                }
+            },
+            _ => {
+                let mut page = dictionary.clone();
+                if let Some(resources) = resources {
+                    page.insert("Resources".to_string(), resources.clone());
+                }
+                pages.push(page);
+            }
+        }
+    }
+
+    fn page_text(&self, page: &Dictionary) -> String {
+        let mut content = Vec::new();
+
+        match self.get(page, "Contents") {
+            Some(Object::Array(streams)) => {
+                for stream in streams {
+                    if let Some(data) = self.decode_stream(self.resolve(stream)) {
+                        content.extend_from_slice(&data);
+                        content.push(b'\n');
+                    }
+                }
+            },
+            Some(stream) => content.extend(self.decode_stream(stream).unwrap_or_default()),
+            None => {},
+        }
+
+        let empty = Dictionary::new();
+        let resources = self.get_dictionary(page, "Resources").unwrap_or(&empty);
+
+        let mut text = TextWriter::default();
+        self.run_content(&content, resources, &mut text, 0);
+        text.finish()
+    }
+
+    /// Interpret a content stream, writing the text it shows
+    fn run_content(&self, content: &[u8], resources: &Dictionary, text: &mut TextWriter, depth: usize) {
+        let fonts = self.fonts(resources);
+        let mut font: Option<&Font> = None;
+        let mut operands: Vec<Object> = Vec::new();
+        let mut lexer = Lexer::new(content, 0);
+
+        while lexer.position < content.len() {
+            let Some(object) = lexer.next_object(0) else {
+                lexer.position += 1;
+                continue;
+            };
+
+            let Object::Keyword(operator) = object else {
+                operands.push(object);
+                continue;
+            };
+
+            let number = |index: usize| operands.get(index).and_then(Object::as_number).unwrap_or(0.0);
+
+            match operator.as_str() {
+                "BT" => text.begin_text(),
+                "Tf" => {
+                    font = operands.first().and_then(Object::as_name).and_then(|name| fonts.get(name));
+                    text.font_size = number(1).abs();
+                },
+                "TL" => text.leading = number(0),
+                "Td" => text.move_to(number(0), number(1)),
+                "TD" => {
+                    text.leading = -number(1);
+                    text.move_to(number(0), number(1));
+                },
+                "Tm" => text.set_matrix(number(3), number(5)),
+                "T*" => text.next_line(),
+                "Tj" | "'" | "\"" => {
+                    if operator != "Tj" {
+                        text.next_line();
+                    }
+                    if let (Some(Object::String(bytes)), Some(font)) = (operands.last(), font) {
+                        text.show(&font.decode(bytes));
+                    }
+                },
+                "TJ" => {
+                    if let (Some(Object::Array(items)), Some(font)) = (operands.last(), font) {
+                        for item in items {
+                            match item {
+                                Object::String(bytes) => text.show(&font.decode(bytes)),
+                                // A large negative adjustment moves right by about a space
+                                Object::Number(adjustment) if *adjustment < -200.0 => text.space(),
+                                _ => {}
+                            }
+                        }
+                    }
+                },
+                "Do" if depth < MAX_XOBJECT_DEPTH => {
+                    let xobject = operands
+                        .first()
+                        .and_then(Object::as_name)
+                        .and_then(|name| self.get_dictionary(resources, "XObject").and_then(|xobjects| self.get(xobjects, name)));
+
+                    if let Some(stream @ Object::Stream(dictionary, _)) = xobject {
+                        if dictionary.get("Subtype").and_then(Object::as_name) == Some("Form") {
+                            if let Some(data) = self.decode_stream(stream) {
+                                let form_resources = self.get_dictionary(dictionary, "Resources").unwrap_or(resources);
+                                self.run_content(&data, form_resources, text, depth + 1);
+                            }
+                        }
+                    }
+                },
+                "ID" => {
+                    // Skip the data of an inline image, which ends at `EI`
+                    let data = &content[lexer.position..];
+                    let end = (0..data.len().saturating_sub(2))
+                        .find(|&i| is_whitespace(data[i]) && data[i + 1..].starts_with(b"EI") && data.get(i + 3).is_none_or(|&byte| is_delimiter(byte)))
+                        .map_or(data.len(), |i| i + 3);
+                    lexer.position += end;
+                },
+                _ => {}
+            }
+
+            operands.clear();
+
+            if text.len() >= MAX_TEXT_BYTES {
+                break;
+            }
+        }
+    }
+
+    /// The fonts of a resource dictionary by resource name
+    fn fonts(&self, resources: &Dictionary) -> HashMap<String, Font> {
+        let Some(fonts) = self.get_dictionary(resources, "Font") else {
+            return HashMap::new();
+        };
+
+        fonts
+            .iter()
+            .filter_map(|(name, font)| Some((name.clone(), self.font(self.resolve(font).as_dictionary()?))))
+            .collect()
+    }
+
+    fn font(&self, dictionary: &Dictionary) -> Font {
+        let mut font = Font::default();
+
+        if dictionary.get("Subtype").and_then(Object::as_name) == Some("Type0") {
+            font.code_length = 2;
+        }
+
+        if let Some(Object::Dictionary(encoding)) = self.get(dictionary, "Encoding") {
+            if let Some(Object::Array(differences)) = self.get(encoding, "Differences") {
+                let mut code = 0u32;
+                for item in differences {
+                    match self.resolve(item) {
+                        Object::Number(number) => code = *number as u32,
+                        Object::Name(glyph) => {
+                            if let Some(text) = glyph_text(glyph) {
+                                font.differences.insert(code, text);
+                            }
+                            code += 1;
+                        },
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if let Some(cmap) = self.get(dictionary, "ToUnicode").and_then(|stream| self.decode_stream(stream)) {
+            parse_cmap(&cmap, &mut font);
+        }
+
+        font
+    }
+
+    /// The title from the document information dictionary
+    fn title(&self) -> Option<String> {
+        let info = self.get_dictionary(&self.trailer, "Info")?;
+        let Some(Object::String(title)) = self.get(info, "Title") else {
+            return None;
+        };
+
+        let title = decode_text_string(title);
+        let title = title.trim();
+        (!title.is_empty()).then(|| title.to_string())
+    }
+
+    /// Targets of URI actions, as used by link annotations
+    fn links(&self) -> Vec<String> {
+        let mut links: Vec<String> = self
+            .objects
+            .values()
+            .filter_map(Object::as_dictionary)
+            .flat_map(|dictionary| {
+                // The action is usually inline in the annotation, or an object of its own
+                let action = self.get_dictionary(dictionary, "A").unwrap_or(dictionary);
+                match (action.get("S").and_then(Object::as_name), self.get(action, "URI")) {
+                    (Some("URI"), Some(Object::String(uri))) => Some(String::from_utf8_lossy(uri).trim().to_string()),
+                    _ => None,
+                }
+            })
+            .filter(|uri| !uri.is_empty())
+            .collect();
+
+        links.sort();
+        links.dedup();
+        links
+    }
+}
+
+/// The data of a stream whose `stream` keyword ends at `start`
+fn read_stream_data<'a>(data: &'a [u8], mut start: usize, dictionary: &Dictionary) -> &'a [u8] {
+    // The keyword is followed by CRLF or LF
+    if data.get(start) == Some(&b'\r') {
+        start += 1;
+    }
+    if data.get(start) == Some(&b'\n') {
+        start += 1;
+    }
+    let start = start.min(data.len());
+
+    // Trust a direct length only if `endstream` follows it
+    if let Some(length) = dictionary.get("Length").and_then(Object::as_number) {
+        let end = start.saturating_add(length as usize);
+        if end <= data.len() {
+            let mut lexer = Lexer::new(data, end);
+            lexer.skip_whitespace();
+            if lexer.starts_with(b"endstream") {
+                return &data[start..end];
+            }
+        }
+    }
+
+    let end = find(&data[start..], b"endstream").map_or(data.len(), |offset| start + offset);
+    let mut stream = &data[start..end];
+    if stream.ends_with(b"\n") {
+        stream = &stream[..stream.len() - 1];
+    }
+    if stream.ends_with(b"\r") {
+        stream = &stream[..stream.len() - 1];
+    }
+    stream
+}
+
+/// Inflate zlib data, keeping what was decoded before any error since truncated streams are common.
+///
+/// Data past the document's decompression budget is dropped the same way.
+fn inflate(data: &[u8], budget: &DecompressionBudget) -> Vec<u8> {
+    let mut output = Vec::new();
+    let _ = budget.read(ZlibDecoder::new(data), &mut output);
+    output
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Decode a text string, which is UTF-16BE with a byte order mark or else single byte
+fn decode_text_string(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xfe, 0xff]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16.chunks_exact(2).map(|unit| u16::from_be_bytes([unit[0], unit[1]])).collect();
+            String::from_utf16_lossy(&units)
+        },
+        None => match bytes.strip_prefix(&[0xef, 0xbb, 0xbf]) {
+            Some(utf8) => String::from_utf8_lossy(utf8).into_owned(),
+            None => decode_windows_1252(bytes),
+        },
+    }
+}
+
+/// Read the `bfchar` and `bfrange` mappings and the code length of a ToUnicode CMap
+fn parse_cmap(cmap: &[u8], font: &mut Font) {
+    // Ranges wider than this are malformed
+    const MAX_RANGE: u32 = 0xffff;
+
+    let mut lexer = Lexer::new(cmap, 0);
+    let mut operands: Vec<Object> = Vec::new();
+
+    while lexer.position < cmap.len() {
+        let Some(object) = lexer.next_object(0) else {
+            lexer.position += 1;
+            continue;
+        };
+
+        let Object::Keyword(operator) = object else {
+            operands.push(object);
+            continue;
+        };
+
+        let code = |bytes: &[u8]| bytes.iter().fold(0u32, |code, &byte| (code << 8) | byte as u32);
+
+        match operator.as_str() {
+            "endcodespacerange" => {
+                if let Some(Object::String(low)) = operands.first() {
+                    font.code_length = low.len().clamp(1, 4);
+                }
+            },
+            "endbfchar" => {
+                for pair in operands.chunks(2) {
+                    if let [Object::String(source), Object::String(target)] = pair {
+                        font.to_unicode.insert(code(source), decode_utf16(target));
+                    }
+                }
+            },
+            "endbfrange" => {
+                for range in operands.chunks(3) {
+                    let [Object::String(low), Object::String(high), target] = range else {
+                        continue;
+                    };
+
+                    let (low, high) = (code(low), code(high));
+                    if high < low || high - low > MAX_RANGE {
+                        continue;
+                    }
+
+                    match target {
+                        Object::String(first) => {
+                            // The last unit of the target is incremented through the range
+                            let mut units: Vec<u16> = first.chunks(2).map(|unit| code(unit) as u16).collect();
+                            for source in low..=high {
+                                font.to_unicode.insert(source, String::from_utf16_lossy(&units));
+                                if let Some(last) = units.last_mut() {
+                                    *last = last.wrapping_add(1);
+                                }
+                            }
+                        },
+                        Object::Array(targets) => {
+                            for (source, target) in (low..=high).zip(targets) {
+                                if let Object::String(target) = target {
+                                    font.to_unicode.insert(source, decode_utf16(target));
+                                }
+                            }
+                        },
+                        _ => {}
+                    }
+                }
+            },
+            _ => {}
+        }
+
+        operands.clear();
+    }
+}
+
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|unit| ((unit[0] as u16) << 8) | unit.get(1).copied().unwrap_or(0) as u16)
+        .collect();
+
+    String::from_utf16_lossy(&units)
+}
+
+/// Text of a glyph name used in an encoding's `Differences`
+fn glyph_text(glyph: &str) -> Option<String> {
+    if glyph.chars().count() == 1 {
+        return Some(glyph.to_string());
+    }
+
+    if let Some(hex) = glyph.strip_prefix("uni").filter(|hex| hex.len() == 4) {
+        return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32).map(String::from);
+    }
+
+    let text = match glyph {
+        "space" | "nbspace" => " ",
+        "exclam" => "!",
+        "quotedbl" => "\"",
+        "numbersign" => "#",
+        "dollar" => "$",
+        "percent" => "%",
+        "ampersand" => "&",
+        "quotesingle" => "'",
+        "parenleft" => "(",
+        "parenright" => ")",
+        "asterisk" => "*",
+        "plus" => "+",
+        "comma" => ",",
+        "hyphen" | "minus" => "-",
+        "period" => ".",
+        "slash" => "/",
+        "zero" => "0",
+        "one" => "1",
+        "two" => "2",
+        "three" => "3",
+        "four" => "4",
+        "five" => "5",
+        "six" => "6",
+        "seven" => "7",
+        "eight" => "8",
+        "nine" => "9",
+        "colon" => ":",
+        "semicolon" => ";",
+        "less" => "<",
+        "equal" => "=",
+        "greater" => ">",
+        "question" => "?",
+        "at" => "@",
+        "bracketleft" => "[",
+        "backslash" => "\\",
+        "bracketright" => "]",
+        "underscore" => "_",
+        "quoteleft" => "\u{2018}",
+        "quoteright" => "\u{2019}",
+        "quotedblleft" => "\u{201c}",
+        "quotedblright" => "\u{201d}",
+        "endash" => "\u{2013}",
+        "emdash" => "\u{2014}",
+        "bullet" => "\u{2022}",
+        "ellipsis" => "\u{2026}",
+        "section" => "\u{a7}",
+        "paragraph" => "\u{b6}",
+        "copyright" => "\u{a9}",
+        "registered" => "\u{ae}",
+        "degree" => "\u{b0}",
+        "fi" => "fi",
+        "fl" => "fl",
+        "ff" => "ff",
+        "ffi" => "ffi",
+        "ffl" => "ffl",
+        _ => return None,
+    };
+
+    Some(text.to_string())
+}
+
+/// Lays out shown text into lines and paragraphs from the text positions
+#[derive(Default)]
+struct TextWriter {
+    out: String,
+    font_size: f64,
+    leading: f64,
+    /// Vertical scale of the text matrix
+    scale: f64,
+    y: f64,
+    /// Baseline of the last text shown
+    last_y: Option<f64>,
+    /// Whether the position moved along the line since text was last shown
+    moved: bool,
+}
+
+impl TextWriter {
+    fn begin_text(&mut self) {
+        self.scale = 1.0;
+        self.y = 0.0;
+    }
+
+    fn move_to(&mut self, x: f64, y: f64) {
+        self.y += y * self.scale();
+        if x != 0.0 {
+            self.moved = true;
+        }
+    }
+
+    fn set_matrix(&mut self, scale: f64, y: f64) {
+        self.scale = scale.abs();
+        self.y = y;
+        self.moved = true;
+    }
+
+    fn next_line(&mut self) {
+        let leading = if self.leading != 0.0 { self.leading } else { self.font_size };
+        self.y -= leading * self.scale();
+    }
+
+    fn scale(&self) -> f64 {
+        if self.scale == 0.0 { 1.0 } else { self.scale }
+    }
+
+    fn show(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        let line_height = (self.font_size * self.scale()).max(1.0);
+
+        if let Some(last_y) = self.last_y {
+            let distance = (last_y - self.y).abs();
+
+            if distance > line_height * 1.8 {
+                self.break_line("\n\n");
+            } else if distance > line_height * 0.5 {
+                self.break_line("\n");
+            } else if self.moved {
+                self.space();
+            }
+        }
+
+        self.out.push_str(text);
+        self.last_y = Some(self.y);
+        self.moved = false;
+    }
+
+    fn space(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with(char::is_whitespace) {
+            self.out.push(' ');
+        }
+    }
+
+    fn break_line(&mut self, separator: &str) {
+        self.out.truncate(self.out.trim_end_matches([' ', '\t']).len());
+
+        if !self.out.is_empty() && !self.out.ends_with(separator) {
+            if separator == "\n\n" && self.out.ends_with('\n') {
+                self.out.push('\n');
+            } else {
+                self.out.push_str(separator);
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.out.len()
+    }
+
+    fn finish(self) -> String {
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// A one page PDF drawing `content` with a Type 1 font, titled and with a link annotation
+    fn pdf(content: &[u8], compress: bool) -> Vec<u8> {
+        let (filter, stream) = if compress {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(content).unwrap();
+            (" /Filter /FlateDecode", encoder.finish().unwrap())
+        } else {
+            ("", content.to_vec())
+        };
+
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        pdf.extend_from_slice(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+        pdf.extend_from_slice(b"2 0 obj\n<< /Type /Pages /Kids [3 0 R] /Count 1 >>\nendobj\n");
+        pdf.extend_from_slice(b"3 0 obj\n<< /Type /Page /Parent 2 0 R /Resources << /Font << /F1 4 0 R >> >> /Contents 5 0 R /Annots [6 0 R] >>\nendobj\n");
+        pdf.extend_from_slice(b"4 0 obj\n<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>\nendobj\n");
+        pdf.extend_from_slice(format!("5 0 obj\n<< /Length {}{} >>\nstream\n", stream.len(), filter).as_bytes());
+        pdf.extend_from_slice(&stream);
+        pdf.extend_from_slice(b"\nendstream\nendobj\n");
+        pdf.extend_from_slice(b"6 0 obj\n<< /Type /Annot /Subtype /Link /A << /S /URI /URI (https://example.com/report) >> >>\nendobj\n");
+        pdf.extend_from_slice(b"7 0 obj\n<< /Title (Annual Report) >>\nendobj\n");
+        pdf.extend_from_slice(b"trailer\n<< /Root 1 0 R /Info 7 0 R >>\n%%EOF\n");
+        pdf
+    }
+
+    const CONTENT: &[u8] = b"BT /F1 12 Tf 72 720 Td (Hello world) Tj 0 -14 Td (Second line) Tj ET";
+
+    #[test]
+    fn extracts_text_title_and_links() {
+        let document = extract(&pdf(CONTENT, false)).unwrap();
+
+        assert!(document.markdown.contains("Hello world"), "{}", document.markdown);
+        assert!(document.markdown.contains("Second line"), "{}", document.markdown);
+        assert_eq!(document.title.as_deref(), Some("Annual Report"));
+        assert_eq!(document.links, vec!["https://example.com/report"]);
+    }
+
+    #[test]
+    fn inflates_compressed_streams() {
+        let plain = extract(&pdf(CONTENT, false)).unwrap();
+        let compressed = extract(&pdf(CONTENT, true)).unwrap();
+
+        assert_eq!(compressed.markdown, plain.markdown);
+    }
+
+    #[test]
+    fn keeps_what_was_inflated_before_a_truncated_stream_ends() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(CONTENT).unwrap();
+        let data = encoder.finish().unwrap();
+
+        let inflated = inflate(&data[..data.len() - 6], &DecompressionBudget::new(MAX_DOCUMENT_DECOMPRESSED_BYTES));
+        assert!(!inflated.is_empty());
+        assert!(CONTENT.starts_with(&inflated));
+    }
+
+    #[test]
+    fn stops_inflating_at_the_document_budget() {
+        let budget = DecompressionBudget::new(20);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(CONTENT).unwrap();
+        let data = encoder.finish().unwrap();
+
+        assert_eq!(inflate(&data, &budget), &CONTENT[..20]);
+        assert!(inflate(&data, &budget).is_empty());
+    }
+
+    #[test]
+    fn survives_truncated_documents() {
+        let body = pdf(CONTENT, true);
+
+        for length in [9, 60, body.len() / 2, body.len() - 30] {
+            let _ = extract(&body[..length]);
+        }
+    }
+
+    #[test]
+    fn rejects_other_and_encrypted_documents() {
+        assert!(extract(b"<html></html>").is_err());
+
+        let mut encrypted = pdf(CONTENT, false);
+        encrypted.extend_from_slice(b"trailer\n<< /Encrypt 8 0 R >>\n");
+        assert!(extract(&encrypted).is_err());
+    }
+}
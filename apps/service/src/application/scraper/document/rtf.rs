@@ -0,0 +1,342 @@
+use anyhow::Result;
+use encoding_rs::{Encoding, WINDOWS_1252};
+
+use super::{hyperlink_target, ExtractedDocument, MarkdownWriter, MAX_TEXT_BYTES};
+use crate::utils::error::AppError;
+
+/// Destinations whose content is not part of the text
+const IGNORED_DESTINATIONS: &[&str] = &[
+    "fonttbl", "colortbl", "stylesheet", "listtable", "listoverridetable", "revtbl", "rsidtbl", "filetbl",
+    "generator", "pict", "object", "objdata", "header", "headerl", "headerr", "headerf", "footer", "footerl",
+    "footerr", "footerf", "footnote", "annotation", "xmlnstbl", "themedata", "colorschememapping", "datastore",
+    "latentstyles", "pgdsctbl", "mmathPr", "author", "operator", "company", "subject", "keywords", "doccomm",
+    "comment", "bkmkstart", "bkmkend",
+];
+
+/// Where the text of the current group goes
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Destination {
+    Text,
+    Title,
+    FieldInstruction,
+    Ignored,
+}
+
+#[derive(Clone, Copy)]
+struct GroupState {
+    destination: Destination,
+    /// Number of fallback characters that follow a `\u` character
+    unicode_skip: usize,
+    /// Whether the group started with `\*`, marking an optional destination
+    optional: bool,
+    /// Whether no control word or text has been seen in the group yet
+    at_start: bool,
+}
+
+/// Extract a Rich Text Format document.
+///
+/// The title comes from the `\info` group and the targets of `HYPERLINK`
+/// fields are reported as links. Formatting isn't interpreted, so paragraphs
+/// become plain paragraphs.
+pub fn extract(body: &[u8]) -> Result<ExtractedDocument> {
+    if !body.starts_with(b"{\\rtf") {
+        return Err(AppError::Scraper("Not an RTF document".to_string()).into());
+    }
+
+    let mut parser = RtfParser::default();
+    parser.parse(body);
+
+    let mut markdown = MarkdownWriter::default();
+    for paragraph in parser.text.split("\n\n") {
+        markdown.paragraph(paragraph);
+    }
+
+    let title = parser.title.trim();
+
+    Ok(ExtractedDocument {
+        title: (!title.is_empty()).then(|| title.to_string()),
+        markdown: markdown.finish(),
+        links: parser.links,
+    })
+}
+
+struct RtfParser {
+    state: GroupState,
+    stack: Vec<GroupState>,
+    encoding: &'static Encoding,
+    /// Bytes of `\'hh` escapes, decoded together so multi-byte code pages work
+    pending_bytes: Vec<u8>,
+    /// Fallback characters still to be skipped after a `\u` character
+    skip: usize,
+    text: String,
+    title: String,
+    instruction: String,
+    links: Vec<String>,
+}
+
+impl Default for RtfParser {
+    fn default() -> Self {
+        Self {
+            state: GroupState {
+                destination: Destination::Text,
+                unicode_skip: 1,
+                optional: false,
+                at_start: true,
+            },
+            stack: Vec::new(),
+            encoding: WINDOWS_1252,
+            pending_bytes: Vec::new(),
+            skip: 0,
+            text: String::new(),
+            title: String::new(),
+            instruction: String::new(),
+            links: Vec::new(),
+        }
+    }
+}
+
+impl RtfParser {
+    fn parse(&mut self, body: &[u8]) {
+        let mut i = 0;
+
+        while i < body.len() && self.text.len() < MAX_TEXT_BYTES {
+            match body[i] {
+                b'{' => {
+                    self.flush_bytes();
+                    self.stack.push(self.state);
+                    self.state.optional = false;
+                    self.state.at_start = true;
+                    i += 1;
+                },
+                b'}' => {
+                    self.flush_bytes();
+                    if self.state.destination == Destination::FieldInstruction {
+                        let instruction = std::mem::take(&mut self.instruction);
+                        self.links.extend(hyperlink_target(&instruction));
+                    }
+                    if let Some(state) = self.stack.pop() {
+                        self.state = state;
+                    }
+                    self.skip = 0;
+                    i += 1;
+                },
+                b'\\' => i = self.control(body, i + 1),
+                b'\r' | b'\n' => i += 1,
+                byte => {
+                    self.state.at_start = false;
+                    if self.skip > 0 {
+                        self.skip -= 1;
+                    } else {
+                        self.pending_bytes.push(byte);
+                    }
+                    i += 1;
+                }
+            }
+        }
+
+        self.flush_bytes();
+    }
+
+    /// Handle the control word or symbol starting at `i`, just after its backslash, and return where it ends
+    fn control(&mut self, body: &[u8], mut i: usize) -> usize {
+        let Some(&first) = body.get(i) else {
+            return i;
+        };
+
+        if !first.is_ascii_alphabetic() {
+            let at_start = std::mem::replace(&mut self.state.at_start, false);
+
+            match first {
+                b'\'' => {
+                    let byte = body.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok()).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                    if let Some(byte) = byte {
+                        if self.skip > 0 {
+                            self.skip -= 1;
+                        } else {
+                            self.pending_bytes.push(byte);
+                        }
+                    }
+                    return i + 3;
+                },
+                b'*' => self.state.optional = at_start,
+                b'~' => self.push_str(" "),
+                b'_' => self.push_str("-"),
+                b'\r' | b'\n' => self.push_str("\n\n"),
+                b'\\' | b'{' | b'}' => self.push_str(&(first as char).to_string()),
+                _ => {}
+            }
+
+            return i + 1;
+        }
+
+        let start = i;
+        while body.get(i).is_some_and(u8::is_ascii_alphabetic) {
+            i += 1;
+        }
+        let word = std::str::from_utf8(&body[start..i]).unwrap_or_default();
+
+        let parameter_start = i;
+        if body.get(i) == Some(&b'-') {
+            i += 1;
+        }
+        while body.get(i).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+        let parameter: Option<i32> = std::str::from_utf8(&body[parameter_start..i]).ok().and_then(|p| p.parse().ok());
+
+        // A space delimits the control word and is not part of the text
+        if body.get(i) == Some(&b' ') {
+            i += 1;
+        }
+
+        let at_start = std::mem::replace(&mut self.state.at_start, false);
+        self.control_word(word, parameter, at_start);
+
+        if word == "bin" {
+            i = i.saturating_add(parameter.unwrap_or(0).max(0) as usize);
+        }
+
+        i
+    }
+
+    fn control_word(&mut self, word: &str, parameter: Option<i32>, at_start: bool) {
+        // A destination has to be the first control word of its group
+        if at_start || self.state.optional {
+            let destination = match word {
+                "fldinst" => Some(Destination::FieldInstruction),
+                "title" => Some(Destination::Title),
+                "info" => Some(Destination::Ignored),
+                word if IGNORED_DESTINATIONS.contains(&word) => Some(Destination::Ignored),
+                _ if self.state.optional => Some(Destination::Ignored),
+                _ => None,
+            };
+
+            self.state.optional = false;
+
+            if let Some(destination) = destination {
+                self.flush_bytes();
+                // Nothing nested in an ignored group is shown, except the title of the info group
+                if self.state.destination != Destination::Ignored || destination == Destination::Title {
+                    self.state.destination = destination;
+                }
+                return;
+            }
+        }
+
+        match word {
+            "par" | "sect" | "page" | "row" => self.push_str("\n\n"),
+            "line" | "cell" => self.push_str("\n"),
+            "tab" => self.push_str("\t"),
+            "emdash" => self.push_str("\u{2014}"),
+            "endash" => self.push_str("\u{2013}"),
+            "lquote" => self.push_str("\u{2018}"),
+            "rquote" => self.push_str("\u{2019}"),
+            "ldblquote" => self.push_str("\u{201c}"),
+            "rdblquote" => self.push_str("\u{201d}"),
+            "bullet" => self.push_str("\u{2022}"),
+            "uc" => self.state.unicode_skip = parameter.unwrap_or(1).max(0) as usize,
+            "u" => {
+                if let Some(code) = parameter {
+                    // Code points above 32767 are written as negative numbers
+                    let code = if code < 0 { code + 65536 } else { code } as u32;
+                    self.push_str(&char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER).to_string());
+                    self.skip = self.state.unicode_skip;
+                }
+            },
+            "ansicpg" => {
+                if let Some(encoding) = parameter.and_then(code_page_encoding) {
+                    self.encoding = encoding;
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn push_str(&mut self, text: &str) {
+        self.flush_bytes();
+
+        match self.state.destination {
+            Destination::Text => self.text.push_str(text),
+            Destination::Title => self.title.push_str(text),
+            Destination::FieldInstruction => self.instruction.push_str(text),
+            Destination::Ignored => {},
+        }
+    }
+
+    fn flush_bytes(&mut self) {
+        if self.pending_bytes.is_empty() {
+            return;
+        }
+
+        let bytes = std::mem::take(&mut self.pending_bytes);
+        let (text, _) = self.encoding.decode_without_bom_handling(&bytes);
+        let text = text.into_owned();
+        self.push_str(&text);
+    }
+}
+
+/// The encoding of a Windows code page number as used by `\ansicpg`
+fn code_page_encoding(code_page: i32) -> Option<&'static Encoding> {
+    let label = match code_page {
+        1250..=1258 => format!("windows-{}", code_page),
+        874 => "windows-874".to_string(),
+        932 => "shift_jis".to_string(),
+        936 => "gbk".to_string(),
+        949 => "euc-kr".to_string(),
+        950 => "big5".to_string(),
+        10000 => "macintosh".to_string(),
+        65001 => "utf-8".to_string(),
+        _ => return None,
+    };
+
+    Encoding::for_label(label.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_paragraphs_and_title() {
+        let body = br"{\rtf1\ansi\deff0{\fonttbl{\f0 Times;}}{\info{\title Quarterly Notes}{\author Someone}}
+\pard First paragraph\par
+Second \b bold\b0  paragraph\par}";
+        let document = extract(body).unwrap();
+
+        assert_eq!(document.title.as_deref(), Some("Quarterly Notes"));
+        assert_eq!(document.markdown, "First paragraph\n\nSecond bold paragraph\n");
+        assert!(document.links.is_empty());
+    }
+
+    #[test]
+    fn decodes_escapes_and_unicode() {
+        let body = br"{\rtf1\ansi\ansicpg1252 caf\'e9 \u8364? \{braces\}\par}";
+        let document = extract(body).unwrap();
+
+        assert_eq!(document.markdown, "caf\u{e9} \u{20ac} {braces}\n");
+    }
+
+    #[test]
+    fn reports_hyperlink_fields() {
+        let body = br#"{\rtf1 See {\field{\*\fldinst HYPERLINK "https://example.com/"}{\fldrslt the site}}.\par}"#;
+        let document = extract(body).unwrap();
+
+        assert_eq!(document.markdown, "See the site.\n");
+        assert_eq!(document.links, vec!["https://example.com/"]);
+    }
+
+    #[test]
+    fn skips_ignored_destinations() {
+        let body = br"{\rtf1{\*\generator Writer;}{\header Page header}{\*\unknown hidden}Body\par}";
+
+        assert_eq!(extract(body).unwrap().markdown, "Body\n");
+    }
+
+    #[test]
+    fn survives_truncated_and_malformed_documents() {
+        assert_eq!(extract(br"{\rtf1 Unclosed {group\par").unwrap().markdown, "Unclosed group\n");
+        assert_eq!(extract(br"{\rtf1 Stray}}} text\'z").unwrap().markdown, "Stray text\n");
+        assert!(extract(br"{\rtf1 \u").is_ok());
+        assert!(extract(b"plain text").is_err());
+    }
+}
@@ -0,0 +1,317 @@
+use chrono::{DateTime, NaiveDate};
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use url::Url;
+
+use crate::domain::scraper_config::{ExtractionRule, FieldType};
+use crate::utils::error::AppError;
+
+/// Metadata key listing the values that could not be converted to their field's type
+pub const EXTRACTION_ERRORS_KEY: &str = "extraction_errors";
+
+/// Formats tried in order when converting a value to a date
+const DATE_FORMATS: [&str; 10] = [
+    "%Y-%m-%d", "%d %B %Y", "%B %d, %Y", "%B %d %Y", "%d %b %Y", "%b %d, %Y", "%d/%m/%Y", "%d.%m.%Y", "%Y/%m/%d", "%Y%m%d",
+];
+
+/// Values extracted from a page, with one entry per rule
+#[derive(Debug, Default)]
+pub struct ExtractedFields {
+    /// Each field's value, or null (an empty array for `multiple` rules) if nothing matched
+    pub values: Map<String, Value>,
+    /// Values that matched but could not be converted to their field's type
+    pub errors: Vec<String>,
+}
+
+struct CompiledRule {
+    field: String,
+    selector: Option<Selector>,
+    attribute: Option<String>,
+    regex: Option<Regex>,
+    field_type: FieldType,
+    multiple: bool,
+}
+
+/// The extraction rules of a config, compiled once per crawl
+#[derive(Default)]
+pub struct FieldExtractor {
+    rules: Vec<CompiledRule>,
+}
+
+impl FieldExtractor {
+    /// Compile extraction rules, failing on the first invalid selector, regex or field name
+    pub fn new(rules: &[ExtractionRule]) -> Result<Self, AppError> {
+        let mut fields = HashSet::new();
+        let mut compiled = Vec::with_capacity(rules.len());
+
+        for rule in rules {
+            let field = rule.field.trim();
+            if field.is_empty() || field == EXTRACTION_ERRORS_KEY {
+                return Err(AppError::InvalidInput(format!("Invalid extraction field name '{}'", rule.field)));
+            }
+            if !fields.insert(field.to_string()) {
+                return Err(AppError::InvalidInput(format!("Extraction field '{}' is defined twice", field)));
+            }
+
+            let selector = rule
+                .selector
+                .as_deref()
+                .map(|selector| {
+                    Selector::parse(selector)
+                        .map_err(|e| AppError::InvalidInput(format!("Invalid selector for field '{}': {}", field, e)))
+                })
+                .transpose()?;
+
+            let regex = rule
+                .regex
+                .as_deref()
+                .map(|regex| {
+                    Regex::new(regex).map_err(|e| AppError::InvalidInput(format!("Invalid regex for field '{}': {}", field, e)))
+                })
+                .transpose()?;
+
+            if selector.is_none() && regex.is_none() {
+                return Err(AppError::InvalidInput(format!("Extraction field '{}' needs a selector or a regex", field)));
+            }
+
+            compiled.push(CompiledRule {
+                field: field.to_string(),
+                selector,
+                attribute: rule.attribute.clone().filter(|attribute| !attribute.trim().is_empty()),
+                regex,
+                field_type: rule.field_type,
+                multiple: rule.multiple,
+            });
+        }
+
+        Ok(Self { rules: compiled })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Run the rules on a parsed HTML page, whose text is used by rules without a selector
+    pub fn extract_html(&self, document: &Html, base_url: &Url) -> ExtractedFields {
+        let mut text = None;
+
+        self.extract(base_url, |rule| match &rule.selector {
+            Some(selector) => document
+                .select(selector)
+                .filter_map(|element| match &rule.attribute {
+                    Some(attribute) => element.attr(attribute).map(|value| value.trim().to_string()),
+                    None => Some(collapse_whitespace(&element.text().collect::<String>())),
+                })
+                .collect(),
+            None => vec![text
+                .get_or_insert_with(|| collapse_whitespace(&document.root_element().text().collect::<String>()))
+                .clone()],
+        })
+    }
+
+    /// Run the rules on the text of a document such as a PDF. Rules with a selector find nothing in it.
+    pub fn extract_text(&self, text: &str, base_url: &Url) -> ExtractedFields {
+        self.extract(base_url, |rule| match rule.selector {
+            Some(_) => Vec::new(),
+            None => vec![text.to_string()],
+        })
+    }
+
+    fn extract(&self, base_url: &Url, mut candidates: impl FnMut(&CompiledRule) -> Vec<String>) -> ExtractedFields {
+        let mut fields = ExtractedFields::default();
+
+        for rule in &self.rules {
+            let mut values = Vec::new();
+
+            for candidate in candidates(rule) {
+                let matches: Vec<String> = match &rule.regex {
+                    Some(regex) => regex
+                        .captures_iter(&candidate)
+                        .map(|captures| captures.get(1).or(captures.get(0)).map_or("", |m| m.as_str()).trim().to_string())
+                        .collect(),
+                    None => vec![candidate],
+                };
+
+                for value in matches.into_iter().filter(|value| !value.is_empty()) {
+                    match convert(&value, rule.field_type, base_url) {
+                        Some(converted) => values.push(converted),
+                        None => fields.errors.push(format!(
+                            "{}: cannot read '{}' as {}",
+                            rule.field,
+                            value,
+                            type_name(rule.field_type)
+                        )),
+                    }
+                }
+
+                if !rule.multiple && !values.is_empty() {
+                    break;
+                }
+            }
+
+            let value = if rule.multiple {
+                Value::Array(values)
+            } else {
+                values.into_iter().next().unwrap_or(Value::Null)
+            };
+
+            fields.values.insert(rule.field.clone(), value);
+        }
+
+        fields
+    }
+}
+
+fn convert(value: &str, field_type: FieldType, base_url: &Url) -> Option<Value> {
+    match field_type {
+        FieldType::Text => Some(Value::String(value.to_string())),
+        FieldType::Integer => {
+            let digits: String = value.chars().filter(|c| !matches!(c, ',' | ' ' | '_')).collect();
+            digits.parse::<i64>().ok().map(Value::from)
+        },
+        FieldType::Number => {
+            let digits: String = value.chars().filter(|c| !matches!(c, ',' | ' ' | '_')).collect();
+            digits.parse::<f64>().ok().filter(|number| number.is_finite()).map(Value::from)
+        },
+        FieldType::Date => parse_date(value).map(|date| Value::String(date.format("%Y-%m-%d").to_string())),
+        FieldType::Url => base_url.join(value).ok().map(|url| Value::String(url.to_string())),
+    }
+}
+
+/// Parse a date written in one of `DATE_FORMATS`, with ordinals such as `1st` allowed, or an RFC 3339 timestamp
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    static ORDINAL: OnceLock<Regex> = OnceLock::new();
+    let ordinal = ORDINAL.get_or_init(|| Regex::new(r"(?i)\b(\d{1,2})(st|nd|rd|th)\b").unwrap());
+
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.date_naive());
+    }
+
+    let value = ordinal.replace_all(value, "$1");
+    let value = collapse_whitespace(&value);
+
+    DATE_FORMATS.iter().find_map(|format| NaiveDate::parse_from_str(&value, format).ok())
+}
+
+fn type_name(field_type: FieldType) -> &'static str {
+    match field_type {
+        FieldType::Text => "text",
+        FieldType::Integer => "an integer",
+        FieldType::Number => "a number",
+        FieldType::Date => "a date",
+        FieldType::Url => "a URL",
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(field: &str, selector: Option<&str>, regex: Option<&str>, field_type: FieldType) -> ExtractionRule {
+        ExtractionRule {
+            field: field.to_string(),
+            selector: selector.map(str::to_string),
+            attribute: None,
+            regex: regex.map(str::to_string),
+            field_type,
+            multiple: false,
+        }
+    }
+
+    fn base_url() -> Url {
+        Url::parse("https://example.com/cases/").unwrap()
+    }
+
+    #[test]
+    fn converts_values_to_their_types() {
+        let url = base_url();
+
+        assert_eq!(convert("1,234", FieldType::Integer, &url), Some(json!(1234)));
+        assert_eq!(convert("12.5", FieldType::Integer, &url), None);
+        assert_eq!(convert("1 234.5", FieldType::Number, &url), Some(json!(1234.5)));
+        assert_eq!(convert("inf", FieldType::Number, &url), None);
+        assert_eq!(convert("../about", FieldType::Url, &url), Some(json!("https://example.com/about")));
+        assert_eq!(convert("  kept as is ", FieldType::Text, &url), Some(json!("  kept as is ")));
+    }
+
+    #[test]
+    fn parses_dates_in_common_formats() {
+        for value in ["2024-03-01", "1 March 2024", "March 1st, 2024", "1st  Mar 2024", "01/03/2024", "01.03.2024", "20240301", "2024-03-01T23:30:00+02:00"] {
+            assert_eq!(parse_date(value), NaiveDate::from_ymd_opt(2024, 3, 1), "{}", value);
+        }
+
+        assert_eq!(parse_date("31/02/2024"), None);
+        assert_eq!(parse_date("soon"), None);
+    }
+
+    #[test]
+    fn extracts_from_selectors_attributes_and_text() {
+        let mut published = rule("published", Some("meta[name=date]"), None, FieldType::Date);
+        published.attribute = Some("content".to_string());
+        let mut judges = rule("judges", Some(".judge"), None, FieldType::Text);
+        judges.multiple = true;
+
+        let extractor = FieldExtractor::new(&[
+            rule("docket", None, Some(r"Docket No\. (\d+-\d+)"), FieldType::Text),
+            published,
+            judges,
+            rule("pages", Some(".pages"), Some(r"(\d+) pages"), FieldType::Integer),
+            rule("missing", Some(".nothing"), None, FieldType::Text),
+        ])
+        .unwrap();
+
+        let document = Html::parse_document(
+            r#"<html><head><meta name="date" content=" 2024-05-06 "></head><body>
+            <p>Docket No. 23-719</p><span class="judge">Roberts</span><span class="judge"> Kagan </span>
+            <p class="pages">many pages</p></body></html>"#,
+        );
+        let fields = extractor.extract_html(&document, &base_url());
+
+        assert_eq!(fields.values["docket"], json!("23-719"));
+        assert_eq!(fields.values["published"], json!("2024-05-06"));
+        assert_eq!(fields.values["judges"], json!(["Roberts", "Kagan"]));
+        assert_eq!(fields.values["pages"], json!(null));
+        assert_eq!(fields.values["missing"], json!(null));
+        assert!(fields.errors.is_empty());
+    }
+
+    #[test]
+    fn reports_values_that_cannot_be_converted() {
+        let extractor = FieldExtractor::new(&[rule("count", None, Some(r"Count: (\S+)"), FieldType::Integer)]).unwrap();
+        let fields = extractor.extract_text("Count: many", &base_url());
+
+        assert_eq!(fields.values["count"], json!(null));
+        assert_eq!(fields.errors, vec!["count: cannot read 'many' as an integer"]);
+    }
+
+    #[test]
+    fn finds_nothing_with_selectors_in_documents() {
+        let extractor = FieldExtractor::new(&[rule("title", Some("h1"), None, FieldType::Text)]).unwrap();
+
+        assert_eq!(extractor.extract_text("# Title", &base_url()).values["title"], json!(null));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let invalid = [
+            vec![rule("", None, Some("x"), FieldType::Text)],
+            vec![rule(EXTRACTION_ERRORS_KEY, None, Some("x"), FieldType::Text)],
+            vec![rule("a", None, Some("x"), FieldType::Text), rule(" a ", None, Some("y"), FieldType::Text)],
+            vec![rule("a", Some("p[["), None, FieldType::Text)],
+            vec![rule("a", None, Some("("), FieldType::Text)],
+            vec![rule("a", None, None, FieldType::Text)],
+        ];
+
+        for rules in invalid {
+            assert!(matches!(FieldExtractor::new(&rules), Err(AppError::InvalidInput(_))), "{:?}", rules);
+        }
+    }
+}
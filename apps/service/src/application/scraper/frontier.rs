@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+/// A URL waiting to be crawled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrontierEntry {
+    pub url: String,
+    pub depth: i32,
+    pub parent_url: Option<String>,
+}
+
+/// Crawl frontier for a single job.
+///
+/// URLs are handed out breadth-first: every URL at depth `n` is popped before
+/// any URL at depth `n + 1`. Each URL is accepted at most once, so links that
+/// are discovered again while queued or after being crawled are ignored.
+///
+/// The frontier serializes to JSON so a paused job can be checkpointed and
+/// resumed later, possibly on another worker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlFrontier {
+    levels: BTreeMap<i32, VecDeque<FrontierEntry>>,
+    seen: HashSet<String>,
+    max_depth: i32,
+}
+
+impl CrawlFrontier {
+    pub fn new(max_depth: i32) -> Self {
+        Self {
+            levels: BTreeMap::new(),
+            seen: HashSet::new(),
+            max_depth,
+        }
+    }
+
+    /// Queue a URL, returning false if it was already seen or is beyond the max depth
+    pub fn push(&mut self, url: String, depth: i32, parent_url: Option<String>) -> bool {
+        if depth >= self.max_depth || self.seen.contains(&url) {
+            return false;
+        }
+
+        self.seen.insert(url.clone());
+        self.levels
+            .entry(depth)
+            .or_default()
+            .push_back(FrontierEntry { url, depth, parent_url });
+        true
+    }
+
+    /// Mark a URL as seen without queueing it, returning false if it was already seen
+    pub fn mark_seen(&mut self, url: String) -> bool {
+        self.seen.insert(url)
+    }
+
+    /// Put back a URL that was taken but not crawled, ahead of the other URLs at its depth
+    pub fn requeue(&mut self, entry: FrontierEntry) {
+        self.seen.insert(entry.url.clone());
+        self.levels.entry(entry.depth).or_default().push_front(entry);
+    }
+
+    /// Take the next URL from the shallowest non-empty depth
+    pub fn pop(&mut self) -> Option<FrontierEntry> {
+        let mut level = self.levels.first_entry()?;
+        let entry = level.get_mut().pop_front();
+
+        if level.get().is_empty() {
+            level.remove();
+        }
+
+        entry
+    }
+
+    /// Number of URLs waiting to be crawled
+    pub fn len(&self) -> usize {
+        self.levels.values().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+}
@@ -0,0 +1,300 @@
+use scraper::{ElementRef, Html, Selector};
+use std::sync::OnceLock;
+use url::Url;
+
+use crate::application::scraper::canonical;
+use crate::application::scraper::extraction::{ExtractedFields, FieldExtractor};
+
+/// Longest anchor text kept for a link, in characters
+const MAX_ANCHOR_TEXT_CHARS: usize = 500;
+
+/// What the crawler reads from an HTML page
+#[derive(Debug, Default)]
+pub struct HtmlPage {
+    pub title: Option<String>,
+    /// Target of the `<link rel="canonical">` in the head
+    pub canonical_url: Option<Url>,
+    /// Links in document order, resolved against the page's base URL
+    pub links: Vec<HtmlLink>,
+    /// Directives of the page's `<meta name="robots">` tags
+    pub robots: RobotsDirectives,
+    /// Text of the page outside scripts and styles, with whitespace collapsed
+    pub text: String,
+    /// Values of the config's extraction rules, if any were given
+    pub fields: Option<ExtractedFields>,
+}
+
+#[derive(Debug)]
+pub struct HtmlLink {
+    pub url: Url,
+    pub text: Option<String>,
+    /// Name of the element the link came from, e.g. `a` or `iframe`
+    pub element: &'static str,
+    /// Whether the link is marked `rel="nofollow"`
+    pub nofollow: bool,
+}
+
+/// Indexing directives of `<meta name="robots">` or an `X-Robots-Tag` header
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RobotsDirectives {
+    pub noindex: bool,
+    pub nofollow: bool,
+}
+
+impl RobotsDirectives {
+    /// Parse a comma separated list such as `noindex, nofollow`.
+    ///
+    /// Directives addressed to a specific crawler (`googlebot: noindex`) are ignored.
+    pub fn parse(value: &str) -> Self {
+        const DIRECTIVES_WITH_VALUES: [&str; 4] = ["unavailable_after", "max-snippet", "max-image-preview", "max-video-preview"];
+
+        let mut directives = Self::default();
+        let mut addressed_to_crawler = false;
+
+        for mut directive in value.split(',').map(str::trim) {
+            // A crawler name applies to the directives after it
+            if let Some((name, rest)) = directive.split_once(':') {
+                if !DIRECTIVES_WITH_VALUES.iter().any(|known| name.trim().eq_ignore_ascii_case(known)) {
+                    addressed_to_crawler = true;
+                    directive = rest.trim();
+                }
+            }
+
+            if addressed_to_crawler {
+                continue;
+            }
+
+            match directive.to_ascii_lowercase().as_str() {
+                "noindex" => directives.noindex = true,
+                "nofollow" => directives.nofollow = true,
+                "none" => {
+                    directives.noindex = true;
+                    directives.nofollow = true;
+                },
+                _ => {}
+            }
+        }
+
+        directives
+    }
+
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            noindex: self.noindex || other.noindex,
+            nofollow: self.nofollow || other.nofollow,
+        }
+    }
+}
+
+/// Parse an HTML page fetched from `page_url`.
+///
+/// Relative URLs are resolved against the first `<base href>`. Links come
+/// from `<a>`, `<area>`, `<iframe>` and `<frame>` elements and from
+/// `<link rel="next">` and `<link rel="prev">`; only http and https links are
+/// kept. The rules of `extractor` run on the same parsed document.
+pub fn parse(html: &str, page_url: &Url, extractor: Option<&FieldExtractor>) -> HtmlPage {
+    static BASE: OnceLock<Selector> = OnceLock::new();
+    static TITLE: OnceLock<Selector> = OnceLock::new();
+    static META: OnceLock<Selector> = OnceLock::new();
+    static LINKS: OnceLock<Selector> = OnceLock::new();
+
+    let base = BASE.get_or_init(|| Selector::parse("base[href]").unwrap());
+    let title = TITLE.get_or_init(|| Selector::parse("title").unwrap());
+    let meta = META.get_or_init(|| Selector::parse("meta[name][content]").unwrap());
+    let links = LINKS.get_or_init(|| {
+        Selector::parse("a[href], area[href], link[rel][href], iframe[src], frame[src]").unwrap()
+    });
+
+    let document = Html::parse_document(html);
+
+    let base_url = document
+        .select(base)
+        .next()
+        .and_then(|element| element.attr("href"))
+        .and_then(|href| page_url.join(href.trim()).ok())
+        .unwrap_or_else(|| page_url.clone());
+
+    let title = document
+        .select(title)
+        .next()
+        .map(|element| collapse_whitespace(element.text()))
+        .filter(|title| !title.is_empty());
+
+    let canonical_url = canonical::find_canonical_link(&document).and_then(|href| resolve(&base_url, &href));
+
+    let robots = document
+        .select(meta)
+        .filter(|element| element.attr("name").is_some_and(|name| name.trim().eq_ignore_ascii_case("robots")))
+        .filter_map(|element| element.attr("content"))
+        .map(RobotsDirectives::parse)
+        .fold(RobotsDirectives::default(), RobotsDirectives::merge);
+
+    let links = document
+        .select(links)
+        .filter_map(|element| {
+            let (element_name, href) = match element.value().name() {
+                "a" => ("a", element.attr("href")?),
+                "area" => ("area", element.attr("href")?),
+                "link" if has_rel(&element, "next") || has_rel(&element, "prev") => ("link", element.attr("href")?),
+                "iframe" => ("iframe", element.attr("src")?),
+                "frame" => ("frame", element.attr("src")?),
+                _ => return None,
+            };
+
+            Some(HtmlLink {
+                url: resolve(&base_url, href)?,
+                text: anchor_text(&element),
+                element: element_name,
+                nofollow: has_rel(&element, "nofollow"),
+            })
+        })
+        .collect();
+
+    let text = visible_text(&document);
+
+    let fields = extractor
+        .filter(|extractor| !extractor.is_empty())
+        .map(|extractor| extractor.extract_html(&document, &base_url));
+
+    HtmlPage {
+        title,
+        canonical_url,
+        links,
+        robots,
+        text,
+        fields,
+    }
+}
+
+/// Resolve a link target, skipping fragments on the same page and anything that isn't http or https
+fn resolve(base_url: &Url, href: &str) -> Option<Url> {
+    let href = href.trim();
+    if href.is_empty() || href.starts_with('#') {
+        return None;
+    }
+
+    base_url.join(href).ok().filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// The text nodes of a document that aren't inside a script, style or template, separated by spaces
+fn visible_text(document: &Html) -> String {
+    const HIDDEN_ELEMENTS: [&str; 4] = ["script", "style", "noscript", "template"];
+
+    let text: Vec<&str> = document
+        .root_element()
+        .descendants()
+        .filter_map(|node| {
+            let text = node.value().as_text()?;
+            let hidden = node
+                .ancestors()
+                .any(|ancestor| ancestor.value().as_element().is_some_and(|element| HIDDEN_ELEMENTS.contains(&element.name())));
+
+            (!hidden).then_some(&**text)
+        })
+        .collect();
+
+    collapse_whitespace(text.iter().flat_map(|text| [*text, " "]))
+}
+
+fn has_rel(element: &ElementRef, value: &str) -> bool {
+    element
+        .attr("rel")
+        .is_some_and(|rel| rel.split_ascii_whitespace().any(|token| token.eq_ignore_ascii_case(value)))
+}
+
+/// Text describing a link: the text of an anchor or the `alt` of its image, or the `alt` or `title` attribute
+fn anchor_text(element: &ElementRef) -> Option<String> {
+    static IMAGE: OnceLock<Selector> = OnceLock::new();
+    let image = IMAGE.get_or_init(|| Selector::parse("img[alt]").unwrap());
+
+    let text = match element.value().name() {
+        "a" => Some(collapse_whitespace(element.text()))
+            .filter(|text| !text.is_empty())
+            .or_else(|| element.select(image).find_map(|image| image.attr("alt").map(|alt| collapse_whitespace([alt])))),
+        "area" => element.attr("alt").map(|alt| collapse_whitespace([alt])),
+        _ => None,
+    };
+
+    text.or_else(|| element.attr("title").map(|title| collapse_whitespace([title])))
+        .filter(|text| !text.is_empty())
+        .map(|text| text.chars().take(MAX_ANCHOR_TEXT_CHARS).collect())
+}
+
+fn collapse_whitespace<'a>(text: impl IntoIterator<Item = &'a str>) -> String {
+    text.into_iter().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r##"<!doctype html>
+<html><head>
+  <title>  Release
+    notes </title>
+  <base href="https://example.com/docs/">
+  <link rel="canonical" href="/docs/releases">
+  <link rel="next" href="page-2">
+  <link rel="stylesheet" href="/style.css">
+  <meta name="ROBOTS" content="noarchive, nofollow">
+  <script>var hidden = "script text";</script>
+</head><body>
+  <h1>Notes</h1>
+  <p>See <a href="v2.html">version   2</a> and <a href="/old" rel="external nofollow" title="Old">old</a>.</p>
+  <a href="guide.pdf"><img src="cover.png" alt="Guide"></a>
+  <a href="#top">Top</a> <a href="mailto:team@example.com">Mail</a> <a href=" ">Blank</a>
+  <map><area href="https://other.example.org/map" alt="Map"></map>
+  <iframe src="embed.html"></iframe>
+  <style>p { color: red }</style>
+</body></html>"##;
+
+    #[test]
+    fn reads_title_canonical_url_and_robots() {
+        let page = parse(PAGE, &Url::parse("https://example.com/docs/index.html").unwrap(), None);
+
+        assert_eq!(page.title.as_deref(), Some("Release notes"));
+        assert_eq!(page.canonical_url.unwrap().as_str(), "https://example.com/docs/releases");
+        assert_eq!(page.robots, RobotsDirectives { noindex: false, nofollow: true });
+        assert!(page.fields.is_none());
+    }
+
+    #[test]
+    fn resolves_links_against_the_base_url() {
+        let page = parse(PAGE, &Url::parse("https://example.com/other/page").unwrap(), None);
+
+        let links: Vec<(&str, &str, Option<&str>, bool)> = page
+            .links
+            .iter()
+            .map(|link| (link.element, link.url.as_str(), link.text.as_deref(), link.nofollow))
+            .collect();
+
+        assert_eq!(
+            links,
+            vec![
+                ("link", "https://example.com/docs/page-2", None, false),
+                ("a", "https://example.com/docs/v2.html", Some("version 2"), false),
+                ("a", "https://example.com/old", Some("old"), true),
+                ("a", "https://example.com/docs/guide.pdf", Some("Guide"), false),
+                ("area", "https://other.example.org/map", Some("Map"), false),
+                ("iframe", "https://example.com/docs/embed.html", None, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_only_visible_text() {
+        let page = parse(PAGE, &Url::parse("https://example.com/").unwrap(), None);
+
+        assert!(page.text.starts_with("Release notes Notes See version 2 and old ."), "{}", page.text);
+        assert!(!page.text.contains("script text"));
+        assert!(!page.text.contains("color"));
+    }
+
+    #[test]
+    fn parses_robots_directives() {
+        assert_eq!(RobotsDirectives::parse("NONE"), RobotsDirectives { noindex: true, nofollow: true });
+        assert_eq!(RobotsDirectives::parse("max-snippet: 20, noindex"), RobotsDirectives { noindex: true, nofollow: false });
+        assert_eq!(RobotsDirectives::parse("googlebot: noindex, nofollow"), RobotsDirectives::default());
+        assert_eq!(RobotsDirectives::parse("nofollow, otherbot: noindex"), RobotsDirectives { noindex: false, nofollow: true });
+    }
+}
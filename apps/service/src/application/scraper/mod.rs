@@ -1,3 +1,12 @@
 pub mod service;
 pub mod worker;
-pub mod crawler; 
\ No newline at end of file
+pub mod canonical;
+pub mod citation;
+pub mod crawler;
+pub mod document;
+pub mod extraction;
+pub mod frontier;
+pub mod html;
+pub mod reaper;
+pub mod robots;
+pub mod sitemap;
\ No newline at end of file
@@ -0,0 +1,237 @@
+use anyhow::Result;
+use chrono::Utc;
+use sqlx::PgPool;
+use sqlx::Row;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::application::webhook::service::WebhookService;
+use crate::config::settings::Recovery as RecoveryConfig;
+use crate::domain::job::{Job, JobPriority, JobStatus};
+use crate::domain::webhook::WebhookEventType;
+use crate::infrastructure::queue::redis_queue::{JobQueue, RedisJobQueue};
+
+/// Recovers jobs whose worker stopped sending heartbeats.
+///
+/// A dequeued job stays in `processing:{queue}` with a `job:` key whose TTL
+/// is the visibility timeout; workers keep extending it while they crawl. When
+/// the key expires the worker is presumed dead, so the entry is removed and the
+/// job is queued again or, once it has used up its attempts, failed.
+pub struct JobReaper {
+    db_pool: PgPool,
+    job_queue: Arc<RedisJobQueue>,
+    webhook_service: Arc<WebhookService>,
+    config: RecoveryConfig,
+    // Queue and entry of those seen without a visibility key on the previous
+    // pass. An entry is only reclaimed when it is seen twice, because dequeue
+    // moves a job into the processing list just before it sets the key.
+    suspects: HashSet<(String, String)>,
+    running: bool,
+}
+
+impl JobReaper {
+    pub fn new(
+        db_pool: PgPool,
+        job_queue: Arc<RedisJobQueue>,
+        webhook_service: Arc<WebhookService>,
+        config: RecoveryConfig,
+    ) -> Self {
+        Self {
+            db_pool,
+            job_queue,
+            webhook_service,
+            config,
+            suspects: HashSet::new(),
+            running: false,
+        }
+    }
+
+    pub async fn start(&mut self) -> Result<()> {
+        if !self.config.enabled {
+            info!("Job reaper is disabled");
+            return Ok(());
+        }
+
+        if self.running {
+            return Ok(());
+        }
+
+        self.running = true;
+        info!("Starting job reaper");
+
+        while self.running {
+            match self.reap().await {
+                Ok(0) => {}
+                Ok(count) => info!("Recovered {} stale jobs", count),
+                Err(e) => error!("Error recovering stale jobs: {}", e),
+            }
+
+            sleep(Duration::from_secs(self.config.check_interval_seconds)).await;
+        }
+
+        info!("Job reaper stopped");
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        info!("Stopping job reaper");
+        self.running = false;
+    }
+
+    /// Reclaim processing entries whose visibility expired and return how many jobs were recovered
+    async fn reap(&mut self) -> Result<usize> {
+        let mut suspects = HashSet::new();
+        let mut recovered = 0;
+
+        for queue in self.job_queue.known_queues().await? {
+            for (job_id, job_data) in self.job_queue.find_expired(&queue).await? {
+                let entry = (queue.clone(), job_data);
+
+                if !self.suspects.contains(&entry) {
+                    suspects.insert(entry);
+                    continue;
+                }
+
+                if !self.job_queue.reclaim(&queue, &job_id, &entry.1).await? {
+                    continue;
+                }
+
+                match Uuid::parse_str(&job_id) {
+                    Ok(job_id) => {
+                        if let Err(e) = self.recover_job(&queue, job_id).await {
+                            error!("Error recovering job {}: {}", job_id, e);
+                        } else {
+                            recovered += 1;
+                        }
+                    },
+                    Err(e) => warn!("Dropped processing entry with invalid job ID {}: {}", job_id, e),
+                }
+            }
+        }
+
+        self.suspects = suspects;
+        Ok(recovered)
+    }
+
+    /// Queue a job of a dead worker again, or fail it once it has used up its attempts
+    async fn recover_job(&self, queue: &str, job_id: Uuid) -> Result<()> {
+        let row = sqlx::query("SELECT status, attempts, worker_id, priority FROM jobs WHERE id = $1")
+            .bind(job_id)
+            .fetch_optional(&self.db_pool)
+            .await?;
+
+        let Some(row) = row else {
+            debug!("Dropped processing entry for deleted job {}", job_id);
+            return Ok(());
+        };
+
+        let status: String = row.get("status");
+        let attempts: i32 = row.get("attempts");
+        let worker_id: Option<String> = row.get("worker_id");
+        let priority: JobPriority = row.get::<String, _>("priority").parse().unwrap_or_default();
+        let now = Utc::now();
+
+        match status.as_str() {
+            "pending" | "running" if attempts >= self.config.max_attempts => {
+                let error_message = format!(
+                    "Worker {} stopped responding, giving up after {} attempts",
+                    worker_id.as_deref().unwrap_or("unknown"),
+                    attempts
+                );
+
+                let result = sqlx::query(
+                    r#"
+                    UPDATE jobs
+                    SET status = 'failed', error_message = $1, completed_at = $2, updated_at = $2, worker_id = NULL
+                    WHERE id = $3 AND status = $4
+                    "#
+                )
+                .bind(&error_message)
+                .bind(now)
+                .bind(job_id)
+                .bind(&status)
+                .execute(&self.db_pool)
+                .await?;
+
+                if result.rows_affected() > 0 {
+                    warn!("Job {} failed: {}", job_id, error_message);
+                    self.job_queue.fail(queue, &job_id.to_string(), &error_message, attempts).await?;
+
+                    if let Some(job) = self.get_job(job_id).await? {
+                        self.webhook_service.notify_job(WebhookEventType::JobFailed, &job).await;
+                    }
+                }
+            },
+            "pending" | "running" => {
+                // Clearing the worker revokes its lease, so a worker that is only slow stops writing progress
+                let result = sqlx::query(
+                    r#"
+                    UPDATE jobs
+                    SET status = 'pending', worker_id = NULL, updated_at = $1
+                    WHERE id = $2 AND status = $3
+                    "#
+                )
+                .bind(now)
+                .bind(job_id)
+                .bind(&status)
+                .execute(&self.db_pool)
+                .await?;
+
+                if result.rows_affected() > 0 {
+                    self.job_queue.enqueue_with_priority(queue, &job_id, priority).await?;
+                    info!(
+                        "Re-enqueued job {} after worker {} stopped responding (attempt {} of {})",
+                        job_id,
+                        worker_id.as_deref().unwrap_or("unknown"),
+                        attempts,
+                        self.config.max_attempts
+                    );
+                }
+            },
+            "paused" => {
+                // The worker died while pausing, release the job so it can still be resumed
+                sqlx::query("UPDATE jobs SET worker_id = NULL, updated_at = $1 WHERE id = $2 AND status = 'paused'")
+                    .bind(now)
+                    .bind(job_id)
+                    .execute(&self.db_pool)
+                    .await?;
+
+                info!("Released paused job {} of worker {}", job_id, worker_id.as_deref().unwrap_or("unknown"));
+            },
+            _ => {
+                // The job finished but its entry was never removed from the processing list
+                debug!("Dropped processing entry for job {} in status {}", job_id, status);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_job(&self, job_id: Uuid) -> Result<Option<Job>> {
+        let job = sqlx::query_as!(
+            Job,
+            r#"
+            SELECT
+                id, config_id,
+                status as "status: JobStatus",
+                created_at, updated_at, started_at, completed_at,
+                error_message, pages_crawled, pages_failed, pages_skipped,
+                next_run_at, worker_id,
+                metadata as "metadata: serde_json::Value",
+                attempts, heartbeat_at,
+                priority as "priority: JobPriority", queue_name
+            FROM jobs
+            WHERE id = $1
+            "#,
+            job_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(job)
+    }
+}
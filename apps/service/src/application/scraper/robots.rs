@@ -0,0 +1,386 @@
+use robotstxt::matcher::{LongestMatchRobotsMatchStrategy, RobotsMatchStrategy};
+use robotstxt::RobotsParseHandler;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Largest part of a robots.txt file that is parsed, RFC 9309 asks crawlers to parse at least 500 KiB
+pub const MAX_ROBOTS_TXT_BYTES: usize = 500 * 1024;
+
+/// The robots.txt of one host, following RFC 9309.
+///
+/// The file is parsed once into its groups rather than into the rules of one
+/// user agent, so a cached entry can serve jobs that crawl the same host with
+/// different user agents. Parsing and pattern matching are delegated to the
+/// `robotstxt` crate, which implements wildcards and `$` anchors; the group
+/// that applies and the longest matching rule are picked the way its matcher
+/// picks them.
+#[derive(Debug, Clone)]
+pub struct RobotsTxt {
+    rules: RobotsRules,
+    fetched_at: Instant,
+    ttl: Duration,
+}
+
+#[derive(Debug, Clone)]
+enum RobotsRules {
+    /// The file was fetched and is matched against
+    Parsed(Arc<RobotsFile>),
+    /// The file is unavailable (4xx), so every URL may be crawled
+    AllowAll,
+    /// The file is unreachable (5xx or network error), so no URL may be crawled
+    DisallowAll,
+}
+
+impl RobotsTxt {
+    /// Rules from a fetched robots.txt body, truncated to `MAX_ROBOTS_TXT_BYTES`
+    pub fn parsed(body: &str, ttl: Duration) -> Self {
+        let mut end = body.len().min(MAX_ROBOTS_TXT_BYTES);
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let mut collector = GroupCollector::default();
+        robotstxt::parse_robotstxt(&body[..end], &mut collector);
+
+        Self::with_rules(RobotsRules::Parsed(Arc::new(collector.file)), ttl)
+    }
+
+    /// Rules for a robots.txt that doesn't exist or may not be accessed (4xx)
+    pub fn unavailable(ttl: Duration) -> Self {
+        Self::with_rules(RobotsRules::AllowAll, ttl)
+    }
+
+    /// Rules for a robots.txt that could not be fetched (5xx or network error)
+    pub fn unreachable(ttl: Duration) -> Self {
+        Self::with_rules(RobotsRules::DisallowAll, ttl)
+    }
+
+    fn with_rules(rules: RobotsRules, ttl: Duration) -> Self {
+        Self {
+            rules,
+            fetched_at: Instant::now(),
+            ttl,
+        }
+    }
+
+    /// The same rules, cached again for `ttl`
+    pub fn refreshed(&self, ttl: Duration) -> Self {
+        Self::with_rules(self.rules.clone(), ttl)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.fetched_at.elapsed() >= self.ttl
+    }
+
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self.rules, RobotsRules::DisallowAll)
+    }
+
+    /// Check whether `url` may be crawled by `user_agent`; the URL must be percent-encoded
+    pub fn is_allowed(&self, user_agent: &str, url: &str) -> bool {
+        match &self.rules {
+            RobotsRules::Parsed(file) => file.is_allowed(product_token(user_agent), &robotstxt::get_path_params_query(url)),
+            RobotsRules::AllowAll => true,
+            RobotsRules::DisallowAll => false,
+        }
+    }
+
+    /// Sitemaps listed with `Sitemap:` lines, which apply to every user agent
+    pub fn sitemaps(&self) -> Vec<String> {
+        match &self.rules {
+            RobotsRules::Parsed(file) => file.sitemaps.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Crawl-delay of the group that applies to `user_agent`, if it sets one
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<Duration> {
+        let RobotsRules::Parsed(file) = &self.rules else {
+            return None;
+        };
+
+        file.crawl_delay(product_token(user_agent))
+            .and_then(|delay| Duration::try_from_secs_f64(delay).ok())
+    }
+}
+
+/// A parsed robots.txt file
+#[derive(Debug, Default)]
+struct RobotsFile {
+    groups: Vec<RobotsGroup>,
+    sitemaps: Vec<String>,
+}
+
+/// The rules following a run of `User-agent` lines
+#[derive(Debug, Default)]
+struct RobotsGroup {
+    /// Whether the group applies to every crawler through `User-agent: *`
+    global: bool,
+    /// Product tokens of the crawlers the group names
+    agents: Vec<String>,
+    rules: Vec<RobotsRule>,
+    /// The first `Crawl-delay` of the group, in seconds
+    crawl_delay: Option<f64>,
+}
+
+#[derive(Debug)]
+struct RobotsRule {
+    allow: bool,
+    pattern: String,
+}
+
+/// Length of the longest matching `Allow` and `Disallow` rule, -1 if none matches
+#[derive(Clone, Copy)]
+struct Matches {
+    allow: i32,
+    disallow: i32,
+}
+
+impl Matches {
+    const NONE: Self = Self { allow: -1, disallow: -1 };
+
+    /// Whether the rules decide on the path; an empty pattern matches with length 0 and decides nothing
+    fn decide(self) -> Option<bool> {
+        (self.allow > 0 || self.disallow > 0).then_some(self.disallow <= self.allow)
+    }
+}
+
+impl RobotsGroup {
+    fn names(&self, product_token: &str) -> bool {
+        !product_token.is_empty() && self.agents.iter().any(|agent| agent.eq_ignore_ascii_case(product_token))
+    }
+
+    fn add_matches(&self, path: &str, matches: &mut Matches) {
+        for rule in &self.rules {
+            if !LongestMatchRobotsMatchStrategy::matches(path, &rule.pattern) {
+                continue;
+            }
+
+            let length = rule.pattern.len() as i32;
+            if rule.allow {
+                matches.allow = matches.allow.max(length);
+            } else {
+                matches.disallow = matches.disallow.max(length);
+            }
+        }
+    }
+}
+
+impl RobotsFile {
+    /// Groups naming the crawler replace the `*` groups, and the longest matching rule wins with `Allow` winning ties
+    fn is_allowed(&self, product_token: &str, path: &str) -> bool {
+        let mut specific = Matches::NONE;
+        let mut global = Matches::NONE;
+        let mut named = false;
+
+        for group in &self.groups {
+            if group.names(product_token) {
+                named = true;
+                group.add_matches(path, &mut specific);
+            } else if group.global {
+                group.add_matches(path, &mut global);
+            }
+        }
+
+        match specific.decide() {
+            Some(allowed) => allowed,
+            None if named => true,
+            None => global.decide().unwrap_or(true),
+        }
+    }
+
+    /// A group naming the crawler replaces the `*` group, even if it sets no delay
+    fn crawl_delay(&self, product_token: &str) -> Option<f64> {
+        let mut named = self.groups.iter().filter(|group| group.names(product_token)).peekable();
+
+        if named.peek().is_some() {
+            return named.find_map(|group| group.crawl_delay);
+        }
+
+        self.groups.iter().filter(|group| group.global).find_map(|group| group.crawl_delay)
+    }
+}
+
+/// Product token of a user agent as matched against `User-agent` lines, e.g. `FortaiBot` for `FortaiBot/1.0`
+pub fn product_token(user_agent: &str) -> &str {
+    let end = user_agent
+        .find(|c: char| !(c.is_ascii_alphabetic() || c == '-' || c == '_'))
+        .unwrap_or(user_agent.len());
+
+    &user_agent[..end]
+}
+
+/// Collects the groups and sitemaps of a robots.txt file.
+///
+/// Groups are delimited the same way the matcher delimits them: a run of
+/// `User-agent` lines starts a new group once any other line has been seen.
+/// Rules before the first `User-agent` line belong to no group.
+#[derive(Default)]
+struct GroupCollector {
+    file: RobotsFile,
+    seen_separator: bool,
+}
+
+impl GroupCollector {
+    fn add_rule(&mut self, allow: bool, pattern: &str) {
+        self.seen_separator = true;
+
+        let Some(group) = self.file.groups.last_mut() else {
+            return;
+        };
+
+        // Like Google, `Allow: /dir/index.html` also allows `/dir/`
+        if allow {
+            if let Some(slash) = pattern.rfind('/').filter(|&slash| pattern[slash..].starts_with("/index.htm")) {
+                group.rules.push(RobotsRule { allow, pattern: format!("{}$", &pattern[..=slash]) });
+            }
+        }
+
+        group.rules.push(RobotsRule { allow, pattern: pattern.to_string() });
+    }
+}
+
+impl RobotsParseHandler for GroupCollector {
+    fn handle_robots_start(&mut self) {}
+
+    fn handle_robots_end(&mut self) {}
+
+    fn handle_user_agent(&mut self, _line_num: u32, user_agent: &str) {
+        if self.seen_separator || self.file.groups.is_empty() {
+            self.file.groups.push(RobotsGroup::default());
+            self.seen_separator = false;
+        }
+
+        let group = self.file.groups.last_mut().expect("a group was just added");
+
+        if user_agent.starts_with('*') && (user_agent.len() == 1 || user_agent[1..].starts_with(char::is_whitespace)) {
+            group.global = true;
+        } else {
+            group.agents.push(product_token(user_agent).to_string());
+        }
+    }
+
+    fn handle_allow(&mut self, _line_num: u32, value: &str) {
+        self.add_rule(true, value);
+    }
+
+    fn handle_disallow(&mut self, _line_num: u32, value: &str) {
+        self.add_rule(false, value);
+    }
+
+    fn handle_sitemap(&mut self, _line_num: u32, value: &str) {
+        self.seen_separator = true;
+
+        if !value.is_empty() {
+            self.file.sitemaps.push(value.to_string());
+        }
+    }
+
+    fn handle_unknown_action(&mut self, _line_num: u32, action: &str, value: &str) {
+        self.seen_separator = true;
+
+        if !action.eq_ignore_ascii_case("crawl-delay") {
+            return;
+        }
+
+        let Some(delay) = value.trim().parse::<f64>().ok().filter(|delay| delay.is_finite() && *delay >= 0.0) else {
+            return;
+        };
+
+        // The first delay of a group wins
+        if let Some(group) = self.file.groups.last_mut() {
+            group.crawl_delay.get_or_insert(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use robotstxt::DefaultMatcher;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    const ROBOTS: &str = "\
+User-agent: *
+Disallow: /private/
+Allow: /private/public.html
+Crawl-delay: 2
+
+User-agent: FortaiBot
+User-agent: OtherBot
+Disallow: /*.pdf$
+Disallow: /search
+Allow: /search/about
+Allow: /docs/index.html
+Crawl-delay: 5
+Crawl-delay: 9
+
+User-agent: IgnoredBot
+Disallow: /
+
+Sitemap: https://example.com/sitemap.xml
+";
+
+    #[test]
+    fn matches_like_the_robotstxt_matcher() {
+        let robots = RobotsTxt::parsed(ROBOTS, TTL);
+        let paths = [
+            "/", "/private/", "/private/a", "/private/public.html", "/file.pdf", "/file.pdf?x=1", "/search",
+            "/search/about", "/search/other", "/docs/", "/docs/index.html", "/other",
+        ];
+
+        for agent in ["FortaiBot/1.0", "otherbot", "SomeBot", "IgnoredBot", ""] {
+            for path in paths {
+                let url = format!("https://example.com{}", path);
+                let expected = DefaultMatcher::default().one_agent_allowed_by_robots(ROBOTS, product_token(agent), &url);
+                assert_eq!(robots.is_allowed(agent, &url), expected, "{} {}", agent, path);
+            }
+        }
+    }
+
+    #[test]
+    fn named_group_replaces_global_group() {
+        let robots = RobotsTxt::parsed(ROBOTS, TTL);
+
+        assert!(robots.is_allowed("FortaiBot", "https://example.com/private/a"));
+        assert!(!robots.is_allowed("SomeBot", "https://example.com/private/a"));
+        assert!(robots.is_allowed("SomeBot", "https://example.com/private/public.html"));
+    }
+
+    #[test]
+    fn crawl_delay_comes_from_the_first_delay_of_the_matching_group() {
+        let robots = RobotsTxt::parsed(ROBOTS, TTL);
+
+        assert_eq!(robots.crawl_delay("FortaiBot/2.0"), Some(Duration::from_secs(5)));
+        assert_eq!(robots.crawl_delay("SomeBot"), Some(Duration::from_secs(2)));
+        assert_eq!(robots.crawl_delay("IgnoredBot"), None);
+    }
+
+    #[test]
+    fn collects_sitemaps() {
+        let robots = RobotsTxt::parsed(ROBOTS, TTL);
+
+        assert_eq!(robots.sitemaps(), vec!["https://example.com/sitemap.xml".to_string()]);
+    }
+
+    #[test]
+    fn rules_before_any_user_agent_are_ignored() {
+        let robots = RobotsTxt::parsed("Disallow: /\n", TTL);
+
+        assert!(robots.is_allowed("FortaiBot", "https://example.com/page"));
+    }
+
+    #[test]
+    fn unavailable_allows_and_unreachable_disallows() {
+        assert!(RobotsTxt::unavailable(TTL).is_allowed("FortaiBot", "https://example.com/"));
+        assert!(!RobotsTxt::unreachable(TTL).is_allowed("FortaiBot", "https://example.com/"));
+        assert!(RobotsTxt::unreachable(TTL).is_unreachable());
+    }
+
+    #[test]
+    fn product_token_stops_at_the_version() {
+        assert_eq!(product_token("FortaiBot/1.0 (+https://example.com)"), "FortaiBot");
+        assert_eq!(product_token("Fortai-Bot_2"), "Fortai-Bot_");
+    }
+}
@@ -1,23 +1,28 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
 use sqlx::PgPool;
 use std::sync::Arc;
 use uuid::Uuid;
 use tracing::{info, error, debug, instrument};
 
-use crate::domain::job::{Job, JobStatus};
-use crate::domain::scraper_config::ScraperConfig;
-use crate::infrastructure::queue::redis_queue::{JobQueue, RedisJobQueue};
+use crate::domain::job::{Job, JobPriority, JobStatus};
+use crate::domain::scraper_config::{CanonicalizationRules, CrawlMode, ExtractionRule, RetentionPolicy, ScraperConfig};
+use crate::domain::webhook::WebhookEventType;
+use crate::application::webhook::service::WebhookService;
+use crate::infrastructure::queue::redis_queue::RedisJobQueue;
 use crate::utils::error::AppError;
 
 pub struct ScraperService {
     db_pool: PgPool,
     job_queue: Arc<RedisJobQueue>,
+    webhook_service: Arc<WebhookService>,
 }
 
 impl ScraperService {
-    pub fn new(db_pool: PgPool, job_queue: Arc<RedisJobQueue>) -> Self {
+    pub fn new(db_pool: PgPool, job_queue: Arc<RedisJobQueue>, webhook_service: Arc<WebhookService>) -> Self {
         info!("Initializing ScraperService");
-        Self { db_pool, job_queue }
+        Self { db_pool, job_queue, webhook_service }
     }
     
     #[instrument(skip(self), err)]
@@ -32,7 +37,11 @@ impl ScraperService {
                 max_pages_per_job, respect_robots_txt, user_agent, 
                 request_delay_ms, max_concurrent_requests, schedule, 
                 headers as "headers: serde_json::Value", 
-                created_at, updated_at, active
+                created_at, updated_at, active, queue_name,
+                crawl_mode as "crawl_mode: CrawlMode",
+                canonicalization as "canonicalization: Json<CanonicalizationRules>",
+                extraction_rules as "extraction_rules: Json<Vec<ExtractionRule>>",
+                retention as "retention: Json<RetentionPolicy>"
             FROM scraper_configs
             WHERE id = $1
             "#,
@@ -49,8 +58,11 @@ impl ScraperService {
         config.ok_or_else(|| AppError::NotFound(format!("Config not found: {}", config_id)).into())
     }
     
+    /// Create a job that runs at `run_at`, or right away if no time in the future is given.
+    ///
+    /// The job goes to the queue of its config, or the default queue if the config has none.
     #[instrument(skip(self), err)]
-    pub async fn create_job(&self, config_id: Uuid) -> Result<Job> {
+    pub async fn create_job(&self, config_id: Uuid, run_at: Option<DateTime<Utc>>, priority: JobPriority) -> Result<Job> {
         info!("Creating new job for config: {}", config_id);
         
         // Verify config exists
@@ -62,7 +74,10 @@ impl ScraperService {
         }
         
         // Create job
-        let job = Job::new(config_id);
+        let queue_name = config.queue_name.clone().unwrap_or_else(|| self.job_queue.default_queue().to_string());
+        let mut job = Job::new(config_id, queue_name);
+        job.priority = priority;
+        job.next_run_at = run_at.filter(|run_at| *run_at > job.created_at);
         debug!("Created job with id: {}", job.id);
         
         // Save to database
@@ -73,9 +88,9 @@ impl ScraperService {
                 id, config_id, status, created_at, updated_at, 
                 started_at, completed_at, error_message, 
                 pages_crawled, pages_failed, pages_skipped, 
-                next_run_at, worker_id, metadata
+                next_run_at, worker_id, metadata, priority, queue_name
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             "#,
             job.id,
             job.config_id,
@@ -90,18 +105,33 @@ impl ScraperService {
             job.pages_skipped,
             job.next_run_at,
             job.worker_id,
-            job.metadata
+            job.metadata,
+            job.priority.to_string(),
+            job.queue_name
         )
         .execute(&self.db_pool)
         .await?;
         
-        // Enqueue job
-        debug!("Enqueueing job {} to job queue", job.id);
-        match self.job_queue.enqueue("scraper_jobs", &job.id).await {
-            Ok(_) => info!("Successfully enqueued job: {}", job.id),
-            Err(e) => error!("Failed to enqueue job {}: {:?}", job.id, e),
+        // Enqueue job, or schedule it so the promoter moves it into the queue when it is due
+        if let Some(run_at) = job.next_run_at {
+            let delay_ms = (run_at - Utc::now()).num_milliseconds().max(0) as u64;
+            let delay_seconds = delay_ms.div_ceil(1000);
+            
+            debug!("Scheduling job {} to run at {}", job.id, run_at);
+            match self.job_queue.schedule_with_priority(&job.queue_name, &job.id, delay_seconds, job.priority).await {
+                Ok(_) => info!("Successfully scheduled job {} for {}", job.id, run_at),
+                Err(e) => error!("Failed to schedule job {}: {:?}", job.id, e),
+            }
+        } else {
+            debug!("Enqueueing job {} to job queue", job.id);
+            match self.job_queue.enqueue_with_priority(&job.queue_name, &job.id, job.priority).await {
+                Ok(_) => info!("Successfully enqueued job {} to queue {}", job.id, job.queue_name),
+                Err(e) => error!("Failed to enqueue job {}: {:?}", job.id, e),
+            }
         }
         
+        self.webhook_service.notify_job(WebhookEventType::JobCreated, &job).await;
+        
         Ok(job)
     }
     
@@ -117,7 +147,9 @@ impl ScraperService {
                 created_at, updated_at, started_at, completed_at, 
                 error_message, pages_crawled, pages_failed, pages_skipped, 
                 next_run_at, worker_id, 
-                metadata as "metadata: serde_json::Value"
+                metadata as "metadata: serde_json::Value",
+                attempts, heartbeat_at,
+                priority as "priority: JobPriority", queue_name
             FROM jobs
             WHERE id = $1
             "#,
@@ -142,22 +174,23 @@ impl ScraperService {
         let mut job = self.get_job(job_id).await?;
         
         // Check if job can be cancelled
-        if job.status != JobStatus::Pending && job.status != JobStatus::Running {
+        if job.status != JobStatus::Pending && job.status != JobStatus::Running && job.status != JobStatus::Paused {
             error!("Cannot cancel job {}: current status is {:?}", job_id, job.status);
             return Err(AppError::InvalidInput(format!("Job cannot be cancelled: {}", job_id)).into());
         }
         
         // Update job status
         debug!("Cancelling job {}: changing status from {:?} to Cancelled", job_id, job.status);
+        let was_running = job.status == JobStatus::Running;
         job.cancel();
         
         // Update database
         debug!("Updating job {} status in database", job_id);
-        sqlx::query!(
+        let result = sqlx::query!(
             r#"
             UPDATE jobs
             SET status = $1, updated_at = $2, completed_at = $3
-            WHERE id = $4
+            WHERE id = $4 AND status IN ('pending', 'running', 'paused')
             "#,
             job.status.to_string(),
             job.updated_at,
@@ -167,7 +200,164 @@ impl ScraperService {
         .execute(&self.db_pool)
         .await?;
         
-        info!("Successfully cancelled job: {}", job_id);
+        // The job reached a terminal state between reading and updating it
+        if result.rows_affected() == 0 {
+            error!("Cannot cancel job {}: it finished before it could be cancelled", job_id);
+            return Err(AppError::InvalidInput(format!("Job cannot be cancelled: {}", job_id)).into());
+        }
+        
+        // A running job is stopped by its worker, which emits JobCancelled once in-flight pages are flushed
+        if was_running {
+            info!("Cancellation of running job {} will be picked up by its worker", job_id);
+        } else {
+            info!("Successfully cancelled job: {}", job_id);
+            self.webhook_service.notify_job(WebhookEventType::JobCancelled, &job).await;
+        }
+        
+        // A cancelled job is never resumed, so its checkpoint can go
+        sqlx::query!("DELETE FROM job_checkpoints WHERE job_id = $1", job.id)
+            .execute(&self.db_pool)
+            .await?;
+        
+        Ok(job)
+    }
+    
+    #[instrument(skip(self), err)]
+    pub async fn pause_job(&self, job_id: Uuid) -> Result<Job> {
+        info!("Attempting to pause job: {}", job_id);
+        
+        // Get job
+        let mut job = self.get_job(job_id).await?;
+        
+        // Check if job can be paused
+        if job.status != JobStatus::Pending && job.status != JobStatus::Running {
+            error!("Cannot pause job {}: current status is {:?}", job_id, job.status);
+            return Err(AppError::InvalidInput(format!("Job cannot be paused: {}", job_id)).into());
+        }
+        
+        debug!("Pausing job {}: changing status from {:?} to Paused", job_id, job.status);
+        let was_running = job.status == JobStatus::Running;
+        job.pause();
+        
+        let result = sqlx::query!(
+            r#"
+            UPDATE jobs
+            SET status = $1, updated_at = $2
+            WHERE id = $3 AND status IN ('pending', 'running')
+            "#,
+            job.status.to_string(),
+            job.updated_at,
+            job.id
+        )
+        .execute(&self.db_pool)
+        .await?;
+        
+        if result.rows_affected() == 0 {
+            error!("Cannot pause job {}: its status changed before it could be paused", job_id);
+            return Err(AppError::InvalidInput(format!("Job cannot be paused: {}", job_id)).into());
+        }
+        
+        // A running job is stopped by its worker, which saves the frontier and then releases the job
+        if was_running {
+            info!("Pausing of running job {} will be picked up by its worker", job_id);
+        } else {
+            info!("Successfully paused job: {}", job_id);
+        }
+        Ok(job)
+    }
+    
+    #[instrument(skip(self), err)]
+    pub async fn resume_job(&self, job_id: Uuid) -> Result<Job> {
+        info!("Attempting to resume job: {}", job_id);
+        
+        // Get job
+        let mut job = self.get_job(job_id).await?;
+        
+        if job.status != JobStatus::Paused {
+            error!("Cannot resume job {}: current status is {:?}", job_id, job.status);
+            return Err(AppError::InvalidInput(format!("Job is not paused: {}", job_id)).into());
+        }
+        
+        debug!("Resuming job {}: changing status from Paused to Pending", job_id);
+        job.resume();
+        
+        // Attempts are counted each time a worker claims the job, so a resumed job starts with
+        // a fresh set rather than running out of retries after being paused a few times
+        job.attempts = 0;
+        
+        // The worker clears worker_id once the checkpoint of a paused running job is saved
+        let result = sqlx::query!(
+            r#"
+            UPDATE jobs
+            SET status = $1, attempts = 0, updated_at = $2
+            WHERE id = $3 AND status = 'paused' AND worker_id IS NULL
+            "#,
+            job.status.to_string(),
+            job.updated_at,
+            job.id
+        )
+        .execute(&self.db_pool)
+        .await?;
+        
+        if result.rows_affected() == 0 {
+            error!("Cannot resume job {}: its worker has not finished pausing it", job_id);
+            return Err(AppError::InvalidInput(format!("Job is still being paused, try again shortly: {}", job_id)).into());
+        }
+        
+        // Enqueue job, the worker that picks it up continues from the checkpoint
+        debug!("Enqueueing job {} to job queue", job.id);
+        match self.job_queue.enqueue_with_priority(&job.queue_name, &job.id, job.priority).await {
+            Ok(_) => info!("Successfully resumed job: {}", job.id),
+            Err(e) => error!("Failed to enqueue job {}: {:?}", job.id, e),
+        }
+        
+        Ok(job)
+    }
+    
+    /// Queue a job from the dead-letter list again with a fresh set of attempts
+    #[instrument(skip(self), err)]
+    pub async fn retry_dead_letter(&self, job_id: Uuid) -> Result<Job> {
+        info!("Retrying dead-lettered job: {}", job_id);
+        
+        let mut job = self.get_job(job_id).await?;
+        
+        if self.job_queue.get_dead_letter(&job.queue_name, &job_id.to_string()).await?.is_none() {
+            return Err(AppError::NotFound(format!("Job not in dead-letter queue: {}", job_id)).into());
+        }
+        
+        if job.status != JobStatus::Failed {
+            error!("Cannot retry job {}: current status is {:?}", job_id, job.status);
+            return Err(AppError::InvalidInput(format!("Job is not failed: {}", job_id)).into());
+        }
+        
+        job.status = JobStatus::Pending;
+        job.error_message = None;
+        job.completed_at = None;
+        job.worker_id = None;
+        job.attempts = 0;
+        job.updated_at = Utc::now();
+        
+        let result = sqlx::query!(
+            r#"
+            UPDATE jobs
+            SET status = $1, error_message = NULL, completed_at = NULL, worker_id = NULL, attempts = 0, updated_at = $2
+            WHERE id = $3 AND status = 'failed'
+            "#,
+            job.status.to_string(),
+            job.updated_at,
+            job.id
+        )
+        .execute(&self.db_pool)
+        .await?;
+        
+        if result.rows_affected() == 0 {
+            return Err(AppError::InvalidInput(format!("Job is not failed: {}", job_id)).into());
+        }
+        
+        self.job_queue.remove_dead_letter(&job.queue_name, &job_id.to_string()).await?;
+        self.job_queue.enqueue_with_priority(&job.queue_name, &job.id, job.priority).await?;
+        
+        info!("Re-enqueued dead-lettered job: {}", job_id);
         Ok(job)
     }
     
@@ -182,7 +372,8 @@ impl ScraperService {
                 created_at, updated_at, started_at, completed_at, 
                 error_message, pages_crawled, pages_failed, pages_skipped, 
                 next_run_at, worker_id, 
-                metadata as "metadata: serde_json::Value"
+                metadata as "metadata: serde_json::Value",
+                attempts, heartbeat_at, priority, queue_name
             FROM jobs
             ORDER BY created_at DESC
             LIMIT $1 OFFSET $2
@@ -200,6 +391,7 @@ impl ScraperService {
                 "completed" => JobStatus::Completed,
                 "failed" => JobStatus::Failed,
                 "cancelled" => JobStatus::Cancelled,
+                "paused" => JobStatus::Paused,
                 _ => JobStatus::Unknown,
             };
             
@@ -218,6 +410,10 @@ impl ScraperService {
                 next_run_at: row.next_run_at,
                 worker_id: row.worker_id,
                 metadata: row.metadata,
+                attempts: row.attempts,
+                heartbeat_at: row.heartbeat_at,
+                priority: row.priority.parse().unwrap_or_default(),
+                queue_name: row.queue_name,
             }
         }).collect();
         
@@ -237,7 +433,9 @@ impl ScraperService {
                 created_at, updated_at, started_at, completed_at, 
                 error_message, pages_crawled, pages_failed, pages_skipped, 
                 next_run_at, worker_id, 
-                metadata as "metadata: serde_json::Value"
+                metadata as "metadata: serde_json::Value",
+                attempts, heartbeat_at,
+                priority as "priority: JobPriority", queue_name
             FROM jobs
             WHERE config_id = $1
             ORDER BY created_at DESC
@@ -253,4 +451,80 @@ impl ScraperService {
         info!("Retrieved {} jobs for config: {}", jobs.len(), config_id);
         Ok(jobs)
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::Row;
+
+    /// `[recovery] max_attempts` in the default config
+    const MAX_ATTEMPTS: i32 = 3;
+
+    fn scraper_service(pool: &PgPool) -> ScraperService {
+        // Nothing listens on this port, so enqueueing a resumed job fails and is only logged
+        let job_queue = RedisJobQueue::unconnected("redis://127.0.0.1:1", "scraper_jobs");
+        ScraperService::new(pool.clone(), Arc::new(job_queue), Arc::new(WebhookService::new(pool.clone())))
+    }
+
+    async fn insert_paused_job(pool: &PgPool) -> Uuid {
+        let config_id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO scraper_configs (id, name, base_url, include_patterns, exclude_patterns, max_depth, user_agent,
+                request_delay_ms, max_concurrent_requests, created_at, updated_at)
+            VALUES ($1, 'test', 'https://example.com/', '{}', '{}', 3, 'test', 0, 1, NOW(), NOW())
+            "#
+        )
+        .bind(config_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let job_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO jobs (id, config_id, status, created_at, updated_at) VALUES ($1, $2, 'paused', NOW(), NOW())")
+            .bind(job_id)
+            .bind(config_id)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        job_id
+    }
+
+    /// Claim a pending job like a worker does, then pause it once its checkpoint is saved
+    async fn run_and_pause(pool: &PgPool, job_id: Uuid) -> i32 {
+        let attempts: i32 = sqlx::query(
+            "UPDATE jobs SET status = 'running', attempts = attempts + 1 WHERE id = $1 AND status = 'pending' RETURNING attempts"
+        )
+        .bind(job_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+        .get("attempts");
+
+        sqlx::query("UPDATE jobs SET status = 'paused', worker_id = NULL WHERE id = $1")
+            .bind(job_id)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        attempts
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn resumed_jobs_keep_their_retries(pool: PgPool) {
+        let service = scraper_service(&pool);
+        let job_id = insert_paused_job(&pool).await;
+
+        for _ in 0..MAX_ATTEMPTS + 2 {
+            let job = service.resume_job(job_id).await.unwrap();
+            assert_eq!((job.status, job.attempts), (JobStatus::Pending, 0));
+
+            // The run after each resume is the job's first attempt, leaving every retry for a failure
+            assert_eq!(run_and_pause(&pool, job_id).await, 1);
+        }
+
+        let job = service.get_job(job_id).await.unwrap();
+        assert!(job.attempts < MAX_ATTEMPTS);
+    }
+}
@@ -0,0 +1,260 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::read::GzDecoder;
+use std::io::Read;
+use xmlparser::{ElementEnd, Token, Tokenizer};
+
+use crate::utils::error::AppError;
+use crate::utils::xml::unescape;
+
+/// Largest uncompressed sitemap that is parsed, the limit set by the sitemaps protocol
+pub const MAX_SITEMAP_BYTES: usize = 50 * 1024 * 1024;
+
+/// A URL listed in a sitemap
+#[derive(Debug, Clone)]
+pub struct SitemapUrl {
+    pub loc: String,
+    pub lastmod: Option<DateTime<Utc>>,
+}
+
+/// A parsed sitemap file
+#[derive(Debug)]
+pub enum Sitemap {
+    /// A `<urlset>` or plain text sitemap listing pages
+    UrlSet(Vec<SitemapUrl>),
+    /// A `<sitemapindex>` listing further sitemaps
+    Index(Vec<String>),
+}
+
+/// Parse a sitemap, decompressing it first if it is gzipped.
+///
+/// Sitemaps ending in `.gz` are usually served as `application/gzip` rather
+/// than with a `Content-Encoding`, so the body is checked for the gzip magic
+/// bytes instead of relying on the HTTP client to decode it.
+pub fn parse_sitemap(body: &[u8]) -> Result<Sitemap> {
+    let decompressed;
+    let body = if body.starts_with(&[0x1f, 0x8b]) {
+        decompressed = gunzip(body)?;
+        &decompressed[..]
+    } else {
+        body
+    };
+
+    if body.len() > MAX_SITEMAP_BYTES {
+        return Err(AppError::Scraper(format!("Sitemap too large: {} bytes", body.len())).into());
+    }
+
+    let text = String::from_utf8_lossy(body);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+
+    if text.starts_with('<') {
+        parse_xml(text)
+    } else {
+        // Text sitemaps list one URL per line
+        let urls = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("http://") || line.starts_with("https://"))
+            .map(|line| SitemapUrl { loc: line.to_string(), lastmod: None })
+            .collect();
+
+        Ok(Sitemap::UrlSet(urls))
+    }
+}
+
+fn gunzip(body: &[u8]) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+
+    // Read one byte past the limit so oversized sitemaps are detected without inflating them completely
+    GzDecoder::new(body)
+        .take(MAX_SITEMAP_BYTES as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| AppError::Scraper(format!("Failed to decompress sitemap: {}", e)))?;
+
+    Ok(decompressed)
+}
+
+fn parse_xml(text: &str) -> Result<Sitemap> {
+    let mut is_index = None;
+    let mut urls = Vec::new();
+    let mut sitemaps = Vec::new();
+
+    let mut in_entry = false;
+    let mut field: Option<String> = None;
+    let mut value = String::new();
+    let mut loc: Option<String> = None;
+    let mut lastmod: Option<DateTime<Utc>> = None;
+
+    for token in Tokenizer::from(text) {
+        let token = token.map_err(|e| AppError::Scraper(format!("Invalid sitemap XML: {}", e)))?;
+
+        match token {
+            // Prefixed elements come from extensions such as `<image:loc>`, which must not be taken for ours
+            Token::ElementStart { prefix, .. } if !prefix.is_empty() => {},
+            Token::ElementStart { local, .. } => {
+                let local = local.as_str();
+
+                if is_index.is_none() {
+                    is_index = match local {
+                        "urlset" => Some(false),
+                        "sitemapindex" => Some(true),
+                        _ => return Err(AppError::Scraper(format!("Unexpected sitemap root element: {}", local)).into()),
+                    };
+                } else if local == "url" || local == "sitemap" {
+                    in_entry = true;
+                    loc = None;
+                    lastmod = None;
+                } else if in_entry && (local == "loc" || local == "lastmod") {
+                    field = Some(local.to_string());
+                    value.clear();
+                }
+            },
+            Token::Text { text } | Token::Cdata { text, .. } if field.is_some() => {
+                value.push_str(text.as_str());
+            },
+            Token::ElementEnd { end: ElementEnd::Close(prefix, local), .. } if prefix.is_empty() => {
+                match local.as_str() {
+                    "loc" if field.is_some() => {
+                        loc = Some(unescape(value.trim()));
+                        field = None;
+                    },
+                    "lastmod" if field.is_some() => {
+                        lastmod = parse_lastmod(value.trim());
+                        field = None;
+                    },
+                    "url" if in_entry => {
+                        if let Some(loc) = loc.take() {
+                            urls.push(SitemapUrl { loc, lastmod: lastmod.take() });
+                        }
+                        in_entry = false;
+                    },
+                    "sitemap" if in_entry => {
+                        if let Some(loc) = loc.take() {
+                            sitemaps.push(loc);
+                        }
+                        in_entry = false;
+                    },
+                    _ => {}
+                }
+            },
+            _ => {}
+        }
+    }
+
+    match is_index {
+        Some(true) => Ok(Sitemap::Index(sitemaps)),
+        Some(false) => Ok(Sitemap::UrlSet(urls)),
+        None => Err(AppError::Scraper("Sitemap has no root element".to_string()).into()),
+    }
+}
+
+/// Parse a W3C datetime as used by `<lastmod>`, which may be a date or a time with or without seconds
+pub fn parse_lastmod(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.with_timezone(&Utc));
+    }
+
+    if let Ok(datetime) = DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M%:z") {
+        return Some(datetime.with_timezone(&Utc));
+    }
+
+    if let Ok(datetime) = DateTime::parse_from_str(&value.replace('Z', "+00:00"), "%Y-%m-%dT%H:%M%:z") {
+        return Some(datetime.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn gzip(body: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn urls(sitemap: Sitemap) -> Vec<SitemapUrl> {
+        match sitemap {
+            Sitemap::UrlSet(urls) => urls,
+            Sitemap::Index(_) => panic!("expected a urlset"),
+        }
+    }
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9" xmlns:image="http://www.google.com/schemas/sitemap-image/1.1">
+  <url>
+    <loc>https://example.com/a?x=1&amp;y=2</loc>
+    <lastmod>2024-03-01</lastmod>
+    <image:image><image:loc>https://example.com/a.png</image:loc></image:image>
+  </url>
+  <url><loc><![CDATA[https://example.com/b]]></loc></url>
+</urlset>"#;
+
+    #[test]
+    fn parses_a_urlset() {
+        let urls = urls(parse_sitemap(URLSET.as_bytes()).unwrap());
+
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[0].loc, "https://example.com/a?x=1&y=2");
+        assert_eq!(urls[0].lastmod, parse_lastmod("2024-03-01T00:00:00Z"));
+        assert_eq!(urls[1].loc, "https://example.com/b");
+        assert_eq!(urls[1].lastmod, None);
+    }
+
+    #[test]
+    fn parses_an_index() {
+        let index = r#"<sitemapindex><sitemap><loc>https://example.com/s1.xml.gz</loc></sitemap></sitemapindex>"#;
+
+        match parse_sitemap(index.as_bytes()).unwrap() {
+            Sitemap::Index(sitemaps) => assert_eq!(sitemaps, vec!["https://example.com/s1.xml.gz".to_string()]),
+            Sitemap::UrlSet(_) => panic!("expected an index"),
+        }
+    }
+
+    #[test]
+    fn parses_a_gzipped_sitemap() {
+        let urls = urls(parse_sitemap(&gzip(URLSET.as_bytes())).unwrap());
+
+        assert_eq!(urls.len(), 2);
+    }
+
+    #[test]
+    fn parses_a_text_sitemap() {
+        let urls = urls(parse_sitemap(b"\xef\xbb\xbfhttps://example.com/a\n  \nnot a url\nhttp://example.com/b\n").unwrap());
+
+        let locs: Vec<_> = urls.iter().map(|url| url.loc.as_str()).collect();
+        assert_eq!(locs, ["https://example.com/a", "http://example.com/b"]);
+    }
+
+    #[test]
+    fn rejects_a_sitemap_inflating_past_the_limit() {
+        let body = gzip(&vec![b' '; MAX_SITEMAP_BYTES + 1]);
+
+        assert!(parse_sitemap(&body).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_sitemaps() {
+        assert!(parse_sitemap(b"<html><body></body></html>").is_err());
+        assert!(parse_sitemap(b"<urlset><url><loc>https://example.com/</url").is_err());
+        assert!(parse_sitemap(&[0x1f, 0x8b, 0x08, 0x00]).is_err());
+    }
+
+    #[test]
+    fn parses_lastmod_formats() {
+        let expected = DateTime::parse_from_rfc3339("2024-03-01T10:30:00Z").unwrap().with_timezone(&Utc);
+
+        assert_eq!(parse_lastmod("2024-03-01T10:30:00Z"), Some(expected));
+        assert_eq!(parse_lastmod("2024-03-01T11:30+01:00"), Some(expected));
+        assert_eq!(parse_lastmod("2024-03-01T10:30Z"), Some(expected));
+        assert_eq!(parse_lastmod("yesterday"), None);
+    }
+}
@@ -1,31 +1,62 @@
 use anyhow::Result;
+use futures::stream::{FuturesUnordered, StreamExt};
 use sqlx::PgPool;
 use sqlx::Row;
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::time::{interval, sleep, MissedTickBehavior};
 use tracing::{info, error, warn, debug};
 use uuid::Uuid;
 use serde_json;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use redis::AsyncCommands;
 use reqwest;
 
-use crate::domain::job::{Job, JobStatus};
-use crate::domain::page::Page;
+use crate::domain::job::{Job, JobPriority, JobStatus};
+use crate::domain::page::{url_hash, Page, PageLink};
 use crate::domain::scraper_config::ScraperConfig;
+use crate::domain::webhook::WebhookEventType;
 use crate::infrastructure::grpc::markdown_client::MarkdownClient;
 use crate::infrastructure::queue::redis_queue::{JobQueue, RedisJobQueue};
-use crate::infrastructure::storage::s3_client::{StorageClient, S3StorageClient};
+use crate::infrastructure::storage::StorageClient;
 use crate::utils::error::AppError;
-use crate::application::scraper::crawler::{Crawler, CrawlerConfig};
+use crate::application::scraper::crawler::{Crawler, CrawlerConfig, Validators};
+use crate::application::scraper::document::DocumentKind;
+use crate::application::scraper::frontier::{CrawlFrontier, FrontierEntry};
+use crate::application::storage::content_store::ContentStore;
+use crate::application::webhook::service::WebhookService;
+use crate::config::settings::Recovery as RecoveryConfig;
+
+/// How often a running job checks whether it has been cancelled or paused
+const STATUS_CHECK_INTERVAL_SECS: u64 = 2;
+/// Number of URLs looked up at once when checking sitemap `<lastmod>` dates
+const LAST_CRAWLED_BATCH_SIZE: usize = 1000;
+/// Number of links inserted per statement, well below the bind parameter limit of Postgres
+const LINK_INSERT_BATCH_SIZE: usize = 1000;
+
+/// The last successful crawl of a URL by an earlier job of the same config
+struct PreviousPage {
+    id: Uuid,
+    content_hash: String,
+    http_headers: serde_json::Value,
+    html_storage_path: Option<String>,
+    markdown_storage_path: Option<String>,
+    title: Option<String>,
+    metadata: serde_json::Value,
+    content_type: Option<String>,
+    document_storage_path: Option<String>,
+}
 
 pub struct ScraperWorker {
     db_pool: PgPool,
     job_queue: Arc<RedisJobQueue>,
-    storage_client: Arc<S3StorageClient>,
+    storage_client: Arc<dyn StorageClient>,
+    content_store: ContentStore,
     markdown_client: Arc<MarkdownClient>,
+    webhook_service: Arc<WebhookService>,
+    recovery_config: RecoveryConfig,
+    queues: Vec<String>,
     crawler: Crawler,
     worker_id: String,
     running: bool,
@@ -35,8 +66,11 @@ impl ScraperWorker {
     pub fn new(
         db_pool: PgPool,
         job_queue: Arc<RedisJobQueue>,
-        storage_client: Arc<S3StorageClient>,
+        storage_client: Arc<dyn StorageClient>,
         markdown_client: Arc<MarkdownClient>,
+        webhook_service: Arc<WebhookService>,
+        recovery_config: RecoveryConfig,
+        queues: Vec<String>,
         config: CrawlerConfig,
     ) -> Result<Self> {
         // Create the crawler
@@ -45,30 +79,41 @@ impl ScraperWorker {
         // Generate a unique worker ID
         let worker_id = format!("worker-{}", Uuid::new_v4());
         
+        let content_store = ContentStore::new(db_pool.clone(), storage_client.clone());
+        
         Ok(Self {
             db_pool,
             job_queue,
             storage_client,
+            content_store,
             markdown_client,
+            webhook_service,
+            recovery_config,
+            queues,
             crawler,
             worker_id,
             running: false,
         })
     }
     
+    /// The crawler of this worker, from which per-config crawlers are made
+    pub fn crawler(&self) -> &Crawler {
+        &self.crawler
+    }
+    
     pub async fn start(&mut self) -> Result<()> {
         if self.running {
             return Ok(());
         }
         
         self.running = true;
-        info!("Starting scraper worker: {}", self.worker_id);
+        info!("Starting scraper worker {} on queues {:?}", self.worker_id, self.queues);
         
         while self.running {
-            // Try to get a job from the queue
-            match self.job_queue.dequeue::<String>("scraper_jobs").await {
-                Ok(Some((job_id_str, job_data))) => {
-                    info!("Processing job: {}", job_id_str);
+            // Try to get a job from one of our queues
+            match self.dequeue_next().await {
+                Ok(Some((queue, job_id_str))) => {
+                    info!("Processing job {} from queue {}", job_id_str, queue);
                     
                     // Parse the job ID string to UUID
                     let job_id = match Uuid::parse_str(&job_id_str) {
@@ -80,12 +125,11 @@ impl ScraperWorker {
                     };
                     
                     // Process the job
-                    if let Err(e) = self.process_job(job_id).await {
+                    if let Err(e) = self.process_job(job_id, &queue).await {
                         error!("Error processing job {}: {}", job_id, e);
                         
-                        // Mark the job as failed
-                        if let Err(mark_err) = self.mark_job_failed(job_id, &e.to_string()).await {
-                            error!("Error marking job {} as failed: {}", job_id, mark_err);
+                        if let Err(handle_err) = self.handle_job_error(job_id, &queue, &e.to_string()).await {
+                            error!("Error handling failure of job {}: {}", job_id, handle_err);
                         }
                     }
                 },
@@ -109,150 +153,241 @@ impl ScraperWorker {
         self.running = false;
     }
     
-    async fn process_job(&self, job_id: Uuid) -> Result<()> {
+    /// Take the next job from the first of our queues that has one, returning the queue and the job ID
+    async fn dequeue_next(&self) -> Result<Option<(String, String)>> {
+        for queue in &self.queues {
+            if let Some((job_id, _)) = self.job_queue.dequeue::<String>(queue).await? {
+                return Ok(Some((queue.clone(), job_id)));
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    async fn process_job(&self, job_id: Uuid, queue: &str) -> Result<()> {
         // Get the job from the database
         let mut job = self.get_job(job_id).await?;
         
-        // Check if the job is already completed or failed
-        if job.status == JobStatus::Completed || job.status == JobStatus::Failed {
+        // Check if the job is already completed, failed or was cancelled or paused before it was picked up
+        if job.status == JobStatus::Completed || job.status == JobStatus::Failed || job.status == JobStatus::Cancelled {
             warn!("Job {} is already in terminal state: {:?}", job_id, job.status);
             
             // Complete the job in Redis to remove it from the processing list
-            if let Err(e) = self.job_queue.complete("scraper_jobs", &job_id.to_string()).await {
+            if let Err(e) = self.job_queue.complete(queue, &job_id.to_string()).await {
                 error!("Error completing job {} in Redis: {}", job_id, e);
             }
             
             return Ok(());
         }
         
-        // Mark the job as running
-        self.mark_job_running(&mut job).await?;
+        if job.status == JobStatus::Paused {
+            // Resuming the job enqueues it again
+            info!("Job {} is paused, skipping it", job_id);
+            
+            // Complete the job in Redis to remove it from the processing list
+            if let Err(e) = self.job_queue.complete(queue, &job_id.to_string()).await {
+                error!("Error completing job {} in Redis: {}", job_id, e);
+            }
+            
+            return Ok(());
+        }
+        
+        // Mark the job as running, unless it was cancelled, paused or claimed by another worker since we loaded it
+        if !self.mark_job_running(&mut job).await? {
+            info!("Job {} is no longer pending, skipping it", job_id);
+            
+            if let Err(e) = self.job_queue.complete(queue, &job_id.to_string()).await {
+                error!("Error completing job {} in Redis: {}", job_id, e);
+            }
+            
+            return Ok(());
+        }
+        self.webhook_service.notify_job(WebhookEventType::JobStarted, &job).await;
         
         // Get the scraper configuration
         let config = self.get_scraper_config(job.config_id).await?;
         
-        // Parse the include and exclude patterns
-        let include_patterns = &config.include_patterns;
-        let exclude_patterns = &config.exclude_patterns;
+        // Crawl with the config's user agent, headers, delay and concurrency
+        let crawler = self.crawler.for_config(&config)?;
         
-        // Create a queue of URLs to crawl
-        let mut url_queue = vec![(config.base_url.clone(), 0, None)];
-        let mut crawled_urls = HashMap::new();
+        // Continue from the checkpoint of a resumed, recovered or retried job, or seed the frontier
+        let (mut frontier, mut pages_processed) = match self.load_checkpoint(job_id).await? {
+            Some((frontier, pages_processed)) => {
+                info!("Resuming job {} with {} URLs in the frontier", job_id, frontier.len());
+                (frontier, pages_processed)
+            },
+            None => (self.seed_frontier(&crawler, job_id, &config).await?, 0),
+        };
+        
+        // Canonical URLs of the pages recorded so far, so duplicates of a page are recorded only once
+        let crawled_urls = Mutex::new(self.get_crawled_urls(job_id).await?);
+        
+        // Pages an earlier attempt recorded after its last checkpoint, which aren't fetched again
+        let mut recorded_pages = self.get_recorded_pages(job_id).await?;
+        
+        let max_pages = config.max_pages_per_job.map(|max| max.max(0) as usize);
+        let concurrency = crawler.max_concurrent_requests();
+        let mut in_flight = FuturesUnordered::new();
+        
+        // URLs being crawled, which go back into the frontier of a checkpoint
+        let mut in_flight_entries: HashMap<String, FrontierEntry> = HashMap::new();
         
-        // Process URLs until the queue is empty or we reach the max pages
-        while let Some((url, depth, parent_url)) = url_queue.pop() {
-            // Check if we've reached the max pages
-            if let Some(max_pages) = config.max_pages_per_job {
-                if crawled_urls.len() >= max_pages as usize {
-                    info!("Reached max pages ({}) for job {}", max_pages, job_id);
+        // Periodically check whether the job was cancelled or paused through the API
+        let mut status_check = interval(Duration::from_secs(STATUS_CHECK_INTERVAL_SECS));
+        status_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut cancelled = false;
+        let mut paused = false;
+        
+        // Heartbeats keep the job claimed and checkpoint the crawl; the reaper recovers it if they stop
+        let mut heartbeat = interval(Duration::from_secs((self.job_queue.visibility_timeout() / 3).max(1)));
+        heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut lease_lost = false;
+        
+        // Keep up to `concurrency` URLs in flight until the frontier is drained or we reach the max pages
+        loop {
+            while in_flight.len() < concurrency {
+                if max_pages.is_some_and(|max| pages_processed >= max) {
                     break;
                 }
-            }
-            
-            // Check if we've reached the max depth
-            if depth >= config.max_depth {
-                debug!("Reached max depth ({}) for URL: {}", config.max_depth, url);
-                continue;
-            }
-            
-            // Check if we've already crawled this URL
-            if crawled_urls.contains_key(&url) {
-                continue;
-            }
-            
-            // Crawl the URL
-            match self.crawler.crawl_url(&url, depth, parent_url.clone(), &include_patterns, &exclude_patterns).await {
-                Ok((mut page, discovered_urls)) => {
-                    // Set the job ID
-                    page.job_id = job_id;
-                    
-                    // Store the HTML content
-                    if page.html_content.is_some() {
-                        if let Ok(html_path) = self.store_content(&page, "html").await {
-                            page.html_storage_path = Some(html_path);
+                
+                match frontier.pop() {
+                    Some(entry) => {
+                        // Follow the links stored with a page that was already recorded instead of fetching it again
+                        if let Some(page_id) = recorded_pages.remove(&entry.url) {
+                            pages_processed += 1;
                             
-                            // Convert HTML to Markdown and store it
-                            if page.error_message.is_none() {
-                                // Use the HTML content directly from the page object
-                                if let Some(html_content) = &page.html_content {
-                                    if let Ok(markdown) = self.convert_html_to_markdown(html_content, &page.url).await {
-                                        if let Ok(markdown_path) = self.store_markdown(&page, &markdown).await {
-                                            page.markdown_storage_path = Some(markdown_path);
-                                        }
+                            match self.get_followed_links(&config, page_id).await {
+                                Ok(urls) => {
+                                    for url in urls {
+                                        frontier.push(url, entry.depth + 1, Some(entry.url.clone()));
                                     }
-                                }
+                                },
+                                Err(e) => error!("Error loading the links of page {}: {}", page_id, e),
                             }
+                            continue;
                         }
-                    }
-                    
-                    // Clear the HTML content before saving to the database
-                    page.html_content = None;
-                    
-                    // Save the page to the database
-                    if let Err(e) = self.save_page(&page).await {
-                        error!("Error saving page {}: {}", page.url, e);
-                    } else {
-                        // Update job stats
-                        if let Err(e) = self.update_job_stats(&job_id, true, page.error_message.is_some(), false).await {
-                            error!("Error updating job stats for {}: {}", job_id, e);
-                        }
-                        
-                        // Mark the URL as crawled
-                        crawled_urls.insert(url.to_string(), page.id);
                         
-                        // Add discovered URLs to the queue if they match the patterns
-                        for discovered_url in discovered_urls {
-                            if !crawled_urls.contains_key(&discovered_url) {
-                                url_queue.push((discovered_url, depth + 1, Some(url.to_string())));
+                        // A page declared this URL as its canonical one and was recorded under it
+                        let duplicate = crawled_urls.lock().unwrap().contains(&entry.url);
+                        if duplicate {
+                            if let Err(e) = self.record_skipped_pages(job_id, 1).await {
+                                error!("Error updating job stats for {}: {}", job_id, e);
                             }
+                            continue;
                         }
+                        
+                        pages_processed += 1;
+                        in_flight_entries.insert(entry.url.clone(), entry.clone());
+                        in_flight.push(self.process_url(&crawler, job_id, &config, &crawled_urls, entry));
+                    },
+                    None => break,
+                }
+            }
+            
+            // Nothing in flight means the frontier is drained or we hit the max pages
+            if in_flight.is_empty() {
+                break;
+            }
+            
+            tokio::select! {
+                Some((entry, discovered_urls)) = in_flight.next() => {
+                    in_flight_entries.remove(&entry.url);
+                    for discovered_url in discovered_urls {
+                        frontier.push(discovered_url, entry.depth + 1, Some(entry.url.clone()));
                     }
                 },
-                Err(e) => {
-                    warn!("Error crawling URL {}: {}", url, e);
+                _ = status_check.tick() => {
+                    match self.get_job_status(job_id).await {
+                        Some(JobStatus::Cancelled) => {
+                            info!("Job {} was cancelled, stopping crawl with {} URLs in flight", job_id, in_flight.len());
+                            cancelled = true;
+                            break;
+                        },
+                        Some(JobStatus::Paused) => {
+                            info!("Job {} was paused, stopping crawl with {} URLs in flight", job_id, in_flight.len());
+                            paused = true;
+                            break;
+                        },
+                        _ => {}
+                    }
+                },
+                _ = heartbeat.tick() => {
+                    if !self.heartbeat(job_id, queue).await {
+                        lease_lost = true;
+                        break;
+                    }
                     
-                    // Create a page with error information
-                    let page = Page {
-                        id: Uuid::new_v4(),
-                        job_id,
-                        url: url.to_string(),
-                        normalized_url: url.to_string(),
-                        content_hash: String::new(),
-                        http_status: 0,
-                        http_headers: serde_json::Value::Null,
-                        crawled_at: chrono::Utc::now(),
-                        html_storage_path: None,
-                        markdown_storage_path: None,
-                        title: None,
-                        metadata: serde_json::Value::Null,
-                        error_message: Some(e.to_string()),
-                        depth,
-                        parent_url,
-                        html_content: None,
-                    };
+                    // A job recovered from a crashed worker or retried after an error continues from here
+                    let mut checkpoint = frontier.clone();
+                    for entry in in_flight_entries.values() {
+                        checkpoint.requeue(entry.clone());
+                    }
                     
-                    // Save the page to the database
-                    if let Err(save_err) = self.save_page(&page).await {
-                        error!("Error saving error page {}: {}", page.url, save_err);
-                    } else {
-                        // Update job stats
-                        if let Err(stats_err) = self.update_job_stats(&job_id, true, true, false).await {
-                            error!("Error updating job stats for {}: {}", job_id, stats_err);
-                        }
-                        
-                        // Mark the URL as crawled
-                        crawled_urls.insert(url.to_string(), page.id);
+                    if let Err(e) = self.save_checkpoint(job_id, &checkpoint, pages_processed - in_flight_entries.len()).await {
+                        error!("Error saving checkpoint for job {}: {}", job_id, e);
                     }
+                },
+            }
+        }
+        
+        // The job was recovered by the reaper and may already run elsewhere, so stop without writing anything
+        if lease_lost {
+            warn!("Worker {} lost its lease on job {}, abandoning {} URLs in flight", self.worker_id, job_id, in_flight.len());
+            return Ok(());
+        }
+        
+        // Let pages that are already being fetched finish so they are recorded
+        if cancelled || paused {
+            while let Some((entry, discovered_urls)) = in_flight.next().await {
+                for discovered_url in discovered_urls {
+                    frontier.push(discovered_url, entry.depth + 1, Some(entry.url.clone()));
                 }
             }
         }
         
-        // Mark the job as completed
-        self.mark_job_completed(&mut job).await?;
+        if paused {
+            // Save where the crawl stopped and release the job so it can be resumed on any worker
+            self.save_checkpoint(job_id, &frontier, pages_processed).await?;
+            self.release_paused_job(job_id).await?;
+            
+            if let Err(e) = self.job_queue.complete(queue, &job_id.to_string()).await {
+                error!("Error completing job {} in Redis: {}", job_id, e);
+            }
+            
+            info!("Job {} paused after {} pages, {} URLs left in the frontier", job_id, pages_processed, frontier.len());
+            return Ok(());
+        }
+        
+        if let Some(max) = max_pages {
+            if pages_processed >= max && !frontier.is_empty() {
+                info!("Reached max pages ({}) for job {}, {} URLs left in the frontier", max, job_id, frontier.len());
+            }
+        }
+        
+        // Mark the job as completed unless it was cancelled in the meantime
+        let completed = !cancelled && self.mark_job_completed(&mut job).await?;
+        
+        if !completed && !cancelled && self.get_job_status(job_id).await != Some(JobStatus::Cancelled) {
+            warn!("Worker {} lost its lease on job {} before it completed", self.worker_id, job_id);
+            return Ok(());
+        }
+        
+        // The checkpoint is only needed to continue an unfinished job
+        if let Err(e) = self.delete_checkpoint(job_id).await {
+            error!("Error deleting checkpoint for job {}: {}", job_id, e);
+        }
+        
+        // Reload the job so the event carries the final page counters
+        let final_job = self.get_job(job_id).await.unwrap_or(job);
+        if completed {
+            self.webhook_service.notify_job(WebhookEventType::JobCompleted, &final_job).await;
+        } else {
+            self.webhook_service.notify_job(WebhookEventType::JobCancelled, &final_job).await;
+        }
         
         // Complete the job in Redis to remove it from the processing list
         debug!("Attempting to complete job {} in Redis queue", job_id);
-        match self.job_queue.complete("scraper_jobs", &job_id.to_string()).await {
+        match self.job_queue.complete(queue, &job_id.to_string()).await {
             Ok(_) => {
                 debug!("Successfully completed job {} in Redis queue", job_id);
             },
@@ -261,7 +396,7 @@ impl ScraperWorker {
                 
                 // Try to verify if the job is still in the processing list
                 if let Ok(mut conn) = self.job_queue.get_connection().await {
-                    let processing_key = format!("processing:{}", "scraper_jobs");
+                    let processing_key = format!("processing:{}", queue);
                     if let Ok(items) = conn.lrange::<_, Vec<String>>(&processing_key, 0, -1).await {
                         debug!("Current items in processing list: {:?}", items);
                         let job_id_str = job_id.to_string();
@@ -282,10 +417,556 @@ impl ScraperWorker {
             }
         }
         
-        info!("Job {} completed, crawled {} pages", job_id, crawled_urls.len());
+        info!("Job {} {}, crawled {} pages", job_id, if completed { "completed" } else { "cancelled" }, pages_processed);
+        Ok(())
+    }
+    
+    /// Seed the frontier of a new crawl with the base URL, the URLs of the site's sitemaps, or both.
+    ///
+    /// Sitemap URLs whose `<lastmod>` is not newer than their last successful
+    /// crawl for this config are counted as skipped and never fetched.
+    async fn seed_frontier(&self, crawler: &Crawler, job_id: Uuid, config: &ScraperConfig) -> Result<CrawlFrontier> {
+        let mut frontier = CrawlFrontier::new(config.max_depth);
+        
+        if config.crawl_mode.follows_links() {
+            let base_url = crawler.normalize_url(&config.base_url).unwrap_or_else(|_| config.base_url.clone());
+            frontier.push(base_url, 0, None);
+        }
+        
+        if !config.crawl_mode.uses_sitemaps() {
+            return Ok(frontier);
+        }
+        
+        let sitemaps = crawler.discover_sitemaps(&config.base_url).await?;
+        let entries = crawler.fetch_sitemap_urls(sitemaps).await;
+        
+        let candidates: Vec<(String, Option<DateTime<Utc>>)> = entries
+            .into_iter()
+            .filter_map(|entry| Some((crawler.normalize_url(&entry.loc).ok()?, entry.lastmod)))
+            .filter(|(url, _)| crawler.should_crawl_url(url, &config.include_patterns, &config.exclude_patterns))
+            .collect();
+        
+        let with_lastmod: Vec<String> = candidates
+            .iter()
+            .filter(|(_, lastmod)| lastmod.is_some())
+            .map(|(url, _)| url.clone())
+            .collect();
+        let last_crawled = self.get_last_crawled_at(config.id, &with_lastmod).await?;
+        
+        let mut queued = 0;
+        let mut skipped = 0;
+        
+        for (url, lastmod) in candidates {
+            let unchanged = lastmod
+                .zip(last_crawled.get(&url))
+                .is_some_and(|(lastmod, crawled_at)| lastmod <= *crawled_at);
+            
+            if unchanged {
+                if frontier.mark_seen(url) {
+                    skipped += 1;
+                }
+            } else if frontier.push(url, 0, None) {
+                queued += 1;
+            }
+        }
+        
+        if skipped > 0 {
+            self.record_skipped_pages(job_id, skipped).await?;
+        }
+        
+        info!("Seeded job {} with {} URLs from sitemaps, skipped {} unchanged URLs", job_id, queued, skipped);
+        Ok(frontier)
+    }
+    
+    /// When each URL was last crawled successfully by a job of the config
+    async fn get_last_crawled_at(&self, config_id: Uuid, urls: &[String]) -> Result<HashMap<String, DateTime<Utc>>> {
+        let mut last_crawled = HashMap::new();
+        
+        for chunk in urls.chunks(LAST_CRAWLED_BATCH_SIZE) {
+            let rows = sqlx::query(
+                r#"
+                SELECT p.normalized_url, MAX(p.crawled_at) AS crawled_at
+                FROM pages p
+                JOIN jobs j ON j.id = p.job_id
+                WHERE j.config_id = $1 AND p.error_message IS NULL AND p.normalized_url = ANY($2)
+                GROUP BY p.normalized_url
+                "#
+            )
+            .bind(config_id)
+            .bind(chunk)
+            .fetch_all(&self.db_pool)
+            .await?;
+            
+            for row in rows {
+                last_crawled.insert(row.get("normalized_url"), row.get("crawled_at"));
+            }
+        }
+        
+        Ok(last_crawled)
+    }
+    
+    /// Count URLs that were skipped without being fetched
+    async fn record_skipped_pages(&self, job_id: Uuid, count: i32) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE jobs
+            SET pages_skipped = pages_skipped + $1, updated_at = $2
+            WHERE id = $3 AND worker_id = $4
+            "#
+        )
+        .bind(count)
+        .bind(Utc::now())
+        .bind(job_id)
+        .bind(&self.worker_id)
+        .execute(&self.db_pool)
+        .await?;
+        
         Ok(())
     }
     
+    /// Crawl a single frontier entry, store and record the page, and return the entry with the URLs it links to.
+    ///
+    /// A page whose canonical URL was already recorded by this job is a
+    /// duplicate and is counted as skipped without being stored.
+    async fn process_url(
+        &self,
+        crawler: &Crawler,
+        job_id: Uuid,
+        config: &ScraperConfig,
+        crawled_urls: &Mutex<HashSet<String>>,
+        entry: FrontierEntry,
+    ) -> (FrontierEntry, Vec<String>) {
+        let FrontierEntry { url, depth, parent_url } = entry.clone();
+        
+        // An earlier crawl lets us ask the server whether the page changed since
+        let previous = match crawler.normalize_url(&url) {
+            Ok(normalized_url) => self.get_previous_page(job_id, config.id, &normalized_url).await.unwrap_or_else(|e| {
+                warn!("Error loading the previous crawl of {}: {}", url, e);
+                None
+            }),
+            Err(_) => None,
+        };
+        let validators = previous.as_ref().and_then(|previous| Validators::from_headers(&previous.http_headers));
+        
+        match crawler.crawl_url(&url, depth, parent_url.clone(), &config.include_patterns, &config.exclude_patterns, validators.as_ref()).await {
+            Ok((mut page, mut links)) => {
+                // Set the job ID
+                page.job_id = job_id;
+                
+                if !Self::claim_canonical_url(crawled_urls, &page) {
+                    debug!("Page {} is a duplicate of {}", page.url, page.normalized_url);
+                    
+                    if let Err(e) = self.update_job_stats(&job_id, false, false, true).await {
+                        error!("Error updating job stats for {}: {}", job_id, e);
+                    }
+                    
+                    return (entry, Vec::new());
+                }
+                
+                // A page that asks not to be indexed isn't stored, but its links are followed unless it says otherwise
+                if page.noindex {
+                    debug!("Page {} asks not to be indexed", page.url);
+                    
+                    if let Err(e) = self.update_job_stats(&job_id, false, false, true).await {
+                        error!("Error updating job stats for {}: {}", job_id, e);
+                    }
+                    
+                    return (entry, Self::followed_urls(config, &links));
+                }
+                
+                if let Some(previous) = previous.as_ref().filter(|previous| page.http_status == 304 || page.content_hash == previous.content_hash) {
+                    if page.http_status == 304 && config.crawl_mode.follows_links() {
+                        // The body wasn't sent again, so the links come from the stored copy
+                        links = self.get_previous_links(crawler, previous, &page.url).await;
+                    }
+                    
+                    if !self.record_unchanged_page(job_id, page, previous, &links).await {
+                        return (entry, Vec::new());
+                    }
+                    
+                    return (entry, Self::followed_urls(config, &links));
+                }
+                
+                // Store a document as it was downloaded, along with the Markdown extracted from it
+                if page.document_content.is_some() {
+                    if let Ok(document_path) = self.store_content(&page, "document").await {
+                        page.document_storage_path = Some(document_path);
+                        
+                        if let Some(markdown) = &page.markdown_content {
+                            if let Ok(markdown_path) = self.store_markdown(&page, markdown).await {
+                                page.markdown_storage_path = Some(markdown_path);
+                            }
+                        }
+                    }
+                }
+                
+                // Store the HTML content
+                if page.html_content.is_some() {
+                    if let Ok(html_path) = self.store_content(&page, "html").await {
+                        page.html_storage_path = Some(html_path);
+                        
+                        // Convert HTML to Markdown and store it
+                        if page.error_message.is_none() {
+                            // Use the HTML content directly from the page object
+                            if let Some(html_content) = &page.html_content {
+                                if let Ok(markdown) = self.convert_html_to_markdown(html_content, &page.url).await {
+                                    if let Ok(markdown_path) = self.store_markdown(&page, &markdown).await {
+                                        page.markdown_storage_path = Some(markdown_path);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                
+                // Clear the HTML content and document before saving to the database
+                page.html_content = None;
+                page.document_content = None;
+                page.markdown_content = None;
+                
+                // Save the page to the database
+                if let Err(e) = self.save_page(&page).await {
+                    error!("Error saving page {}: {}", page.url, e);
+                    self.release_content(&page).await;
+                    return (entry, Vec::new());
+                }
+                
+                if let Err(e) = self.save_links(&page, &links).await {
+                    error!("Error saving links of page {}: {}", page.url, e);
+                }
+                
+                if let Err(e) = self.save_citations(&page).await {
+                    error!("Error saving citations of page {}: {}", page.url, e);
+                }
+                
+                let event_type = if page.error_message.is_some() {
+                    WebhookEventType::PageFailed
+                } else {
+                    WebhookEventType::PageCrawled
+                };
+                self.webhook_service.notify_page(event_type, &page).await;
+                
+                if let Some(previous) = previous.as_ref().filter(|_| page.error_message.is_none()) {
+                    self.webhook_service.notify_content_changed(&page, &previous.content_hash).await;
+                }
+                
+                // Update job stats
+                if let Err(e) = self.update_job_stats(&job_id, true, page.error_message.is_some(), false).await {
+                    error!("Error updating job stats for {}: {}", job_id, e);
+                }
+                
+                (entry, Self::followed_urls(config, &links))
+            },
+            Err(e) => {
+                warn!("Error crawling URL {}: {}", url, e);
+                
+                // Create a page with error information
+                let page = Page {
+                    id: Uuid::new_v4(),
+                    job_id,
+                    url: url.to_string(),
+                    normalized_url: url.to_string(),
+                    content_hash: String::new(),
+                    http_status: 0,
+                    http_headers: serde_json::Value::Null,
+                    crawled_at: chrono::Utc::now(),
+                    html_storage_path: None,
+                    markdown_storage_path: None,
+                    title: None,
+                    metadata: serde_json::Value::Null,
+                    error_message: Some(e.to_string()),
+                    depth,
+                    parent_url,
+                    content_type: None,
+                    document_storage_path: None,
+                    html_content: None,
+                    document_content: None,
+                    markdown_content: None,
+                    noindex: false,
+                    citations: Vec::new(),
+                };
+                
+                // Save the page to the database
+                if let Err(save_err) = self.save_page(&page).await {
+                    error!("Error saving error page {}: {}", page.url, save_err);
+                } else {
+                    self.webhook_service.notify_page(WebhookEventType::PageFailed, &page).await;
+                    
+                    // Update job stats
+                    if let Err(stats_err) = self.update_job_stats(&job_id, true, true, false).await {
+                        error!("Error updating job stats for {}: {}", job_id, stats_err);
+                    }
+                }
+                
+                (entry, Vec::new())
+            }
+        }
+    }
+    
+    /// Claim the canonical URL of a page for this job, returning false if another page already holds it.
+    ///
+    /// The fetched URL is claimed as well, so it isn't crawled again when it
+    /// is discovered later.
+    fn claim_canonical_url(crawled_urls: &Mutex<HashSet<String>>, page: &Page) -> bool {
+        let mut crawled_urls = crawled_urls.lock().unwrap();
+        
+        let claimed = crawled_urls.insert(page.normalized_url.clone());
+        crawled_urls.insert(page.url.clone());
+        
+        claimed
+    }
+    
+    /// Canonical URLs of the pages a job has recorded, so a resumed job keeps skipping their duplicates
+    async fn get_crawled_urls(&self, job_id: Uuid) -> Result<HashSet<String>> {
+        let rows = sqlx::query("SELECT url, normalized_url FROM pages WHERE job_id = $1 AND error_message IS NULL")
+            .bind(job_id)
+            .fetch_all(&self.db_pool)
+            .await?;
+        
+        Ok(rows
+            .iter()
+            .flat_map(|row| [row.get::<String, _>("url"), row.get::<String, _>("normalized_url")])
+            .collect())
+    }
+    
+    /// The page each URL fetched by a job was recorded as, including pages that failed.
+    ///
+    /// A job retried after an error or recovered from a crashed worker
+    /// continues from its last checkpoint, so it may come across URLs it
+    /// recorded after that checkpoint.
+    async fn get_recorded_pages(&self, job_id: Uuid) -> Result<HashMap<String, Uuid>> {
+        let rows = sqlx::query("SELECT url, id FROM pages WHERE job_id = $1 ORDER BY crawled_at")
+            .bind(job_id)
+            .fetch_all(&self.db_pool)
+            .await?;
+        
+        Ok(rows
+            .iter()
+            .map(|row| (row.get::<String, _>("url"), row.get::<Uuid, _>("id")))
+            .collect())
+    }
+    
+    /// URLs of the stored links of a recorded page that the crawl follows
+    async fn get_followed_links(&self, config: &ScraperConfig, page_id: Uuid) -> Result<Vec<String>> {
+        if !config.crawl_mode.follows_links() {
+            return Ok(Vec::new());
+        }
+        
+        let urls = sqlx::query_scalar::<_, String>(
+            "SELECT url FROM page_links WHERE page_id = $1 AND NOT nofollow ORDER BY position"
+        )
+        .bind(page_id)
+        .fetch_all(&self.db_pool)
+        .await?;
+        
+        Ok(urls)
+    }
+    
+    /// URLs of the links a crawl follows: none for sitemap-only crawls, and never those marked nofollow
+    fn followed_urls(config: &ScraperConfig, links: &[PageLink]) -> Vec<String> {
+        if !config.crawl_mode.follows_links() {
+            return Vec::new();
+        }
+        
+        links.iter().filter(|link| !link.nofollow).map(|link| link.url.clone()).collect()
+    }
+    
+    /// Record the links found on a page along with their anchor text, in the order they appear
+    async fn save_links(&self, page: &Page, links: &[PageLink]) -> Result<()> {
+        for (batch, chunk) in links.chunks(LINK_INSERT_BATCH_SIZE).enumerate() {
+            let mut query_builder = sqlx::QueryBuilder::new(
+                "INSERT INTO page_links (page_id, position, url, anchor_text, element, nofollow) "
+            );
+            
+            query_builder.push_values(chunk.iter().enumerate(), |mut row, (i, link)| {
+                row.push_bind(page.id)
+                    .push_bind((batch * LINK_INSERT_BATCH_SIZE + i) as i32)
+                    .push_bind(&link.url)
+                    .push_bind(&link.anchor_text)
+                    .push_bind(&link.element)
+                    .push_bind(link.nofollow);
+            });
+            
+            query_builder.build().execute(&self.db_pool).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Record the citations found in the text of a page
+    async fn save_citations(&self, page: &Page) -> Result<()> {
+        if page.citations.is_empty() {
+            return Ok(());
+        }
+        
+        let mut query_builder = sqlx::QueryBuilder::new(
+            "INSERT INTO page_citations (page_id, position, kind, citation, normalized, occurrences) "
+        );
+        
+        query_builder.push_values(page.citations.iter().enumerate(), |mut row, (i, citation)| {
+            row.push_bind(page.id)
+                .push_bind(i as i32)
+                .push_bind(citation.kind.as_str())
+                .push_bind(&citation.citation)
+                .push_bind(&citation.normalized)
+                .push_bind(citation.occurrences);
+        });
+        
+        query_builder.build().execute(&self.db_pool).await?;
+        
+        Ok(())
+    }
+    
+    async fn copy_citations(&self, from_page_id: Uuid, to_page_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO page_citations (page_id, position, kind, citation, normalized, occurrences)
+            SELECT $2, position, kind, citation, normalized, occurrences
+            FROM page_citations
+            WHERE page_id = $1
+            "#
+        )
+        .bind(from_page_id)
+        .bind(to_page_id)
+        .execute(&self.db_pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Record a page that is unchanged since its previous crawl, reusing the stored content instead of uploading it again.
+    ///
+    /// Returns false if the page couldn't be saved.
+    async fn record_unchanged_page(&self, job_id: Uuid, mut page: Page, previous: &PreviousPage, links: &[PageLink]) -> bool {
+        debug!("Page {} is unchanged since its previous crawl", page.url);
+        
+        // A 304 response only carries some headers, keep the rest from the previous response
+        if let (serde_json::Value::Object(previous_headers), serde_json::Value::Object(headers)) = (&previous.http_headers, &page.http_headers) {
+            let mut merged = previous_headers.clone();
+            merged.extend(headers.clone());
+            page.http_headers = serde_json::Value::Object(merged);
+        }
+        
+        page.content_hash = previous.content_hash.clone();
+        page.html_storage_path = previous.html_storage_path.clone();
+        page.markdown_storage_path = previous.markdown_storage_path.clone();
+        page.document_storage_path = previous.document_storage_path.clone();
+        page.content_type = page.content_type.or_else(|| previous.content_type.clone());
+        page.title = page.title.or_else(|| previous.title.clone());
+        // Nothing was extracted from the empty body of a 304 response
+        if page.http_status == 304 {
+            page.metadata = previous.metadata.clone();
+        }
+        page.html_content = None;
+        page.document_content = None;
+        page.markdown_content = None;
+        
+        // The new page refers to the stored content as well
+        if let Err(e) = self.content_store.retain(&Self::storage_paths(&page)).await {
+            error!("Error retaining the stored content of page {}: {}", page.url, e);
+            return false;
+        }
+        
+        if let Err(e) = self.save_page(&page).await {
+            error!("Error saving page {}: {}", page.url, e);
+            self.release_content(&page).await;
+            return false;
+        }
+        
+        if let Err(e) = self.save_links(&page, links).await {
+            error!("Error saving links of page {}: {}", page.url, e);
+        }
+        
+        // The body of a 304 response is empty, so its citations are those of the previous crawl
+        let citations = if page.http_status == 304 {
+            self.copy_citations(previous.id, page.id).await
+        } else {
+            self.save_citations(&page).await
+        };
+        if let Err(e) = citations {
+            error!("Error saving citations of page {}: {}", page.url, e);
+        }
+        
+        if let Err(e) = self.update_job_stats(&job_id, false, false, true).await {
+            error!("Error updating job stats for {}: {}", job_id, e);
+        }
+        
+        true
+    }
+    
+    /// Extract the links of a page from the HTML or document stored by its previous crawl
+    async fn get_previous_links(&self, crawler: &Crawler, previous: &PreviousPage, url: &str) -> Vec<PageLink> {
+        let Some(html_path) = &previous.html_storage_path else {
+            return self.get_previous_document_links(crawler, previous, url).await;
+        };
+        
+        match self.storage_client.get_object(html_path).await {
+            Ok(html) => crawler.extract_links(html, url).await,
+            Err(e) => {
+                warn!("Error loading the stored HTML of {}, not following its links: {}", url, e);
+                Vec::new()
+            }
+        }
+    }
+    
+    async fn get_previous_document_links(&self, crawler: &Crawler, previous: &PreviousPage, url: &str) -> Vec<PageLink> {
+        let (Some(document_path), Some(kind)) = (
+            &previous.document_storage_path,
+            previous.content_type.as_deref().and_then(DocumentKind::from_mime_type),
+        ) else {
+            return Vec::new();
+        };
+        
+        let body = match self.storage_client.get_object_bytes(document_path).await {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Error loading the stored document of {}, not following its links: {}", url, e);
+                return Vec::new();
+            }
+        };
+        
+        match crawler.extract_document(kind, body, None).await {
+            Ok((document, _)) => crawler.document_links(&document.links, url, false),
+            Err(e) => {
+                warn!("Error extracting the stored document of {}, not following its links: {}", url, e);
+                Vec::new()
+            }
+        }
+    }
+    
+    /// The last successful crawl of a URL by another job of the config
+    async fn get_previous_page(&self, job_id: Uuid, config_id: Uuid, normalized_url: &str) -> Result<Option<PreviousPage>> {
+        let row = sqlx::query(
+            r#"
+            SELECT p.id, p.content_hash, p.http_headers, p.html_storage_path, p.markdown_storage_path, p.title,
+                p.metadata, p.content_type, p.document_storage_path
+            FROM pages p
+            JOIN jobs j ON j.id = p.job_id
+            WHERE j.config_id = $1 AND p.job_id <> $2 AND p.normalized_url = $3
+                AND p.error_message IS NULL AND p.content_hash <> ''
+            ORDER BY p.crawled_at DESC
+            LIMIT 1
+            "#
+        )
+        .bind(config_id)
+        .bind(job_id)
+        .bind(normalized_url)
+        .fetch_optional(&self.db_pool)
+        .await?;
+        
+        Ok(row.map(|row| PreviousPage {
+            id: row.get("id"),
+            content_hash: row.get("content_hash"),
+            http_headers: row.get("http_headers"),
+            html_storage_path: row.get("html_storage_path"),
+            markdown_storage_path: row.get("markdown_storage_path"),
+            title: row.get("title"),
+            metadata: row.get("metadata"),
+            content_type: row.get("content_type"),
+            document_storage_path: row.get("document_storage_path"),
+        }))
+    }
+    
     async fn store_content(&self, page: &Page, content_type: &str) -> Result<String> {
         if content_type == "html" {
             // Check if there was an error crawling the page
@@ -297,9 +978,9 @@ impl ScraperWorker {
             
             // Get the HTML content from the Page object
             if let Some(html_content) = &page.html_content {
-                // Store the HTML content in S3 and return the path from the S3 client
+                // Store the HTML content under its hash and return the path
                 debug!("Attempting to upload HTML content for URL: {}", page.url);
-                match self.storage_client.upload_html(&page.job_id, &page.url, html_content).await {
+                match self.content_store.put(html_content.as_bytes(), "text/html").await {
                     Ok(path) => {
                         debug!("Successfully uploaded HTML content to path: {}", path);
                         Ok(path)
@@ -314,6 +995,27 @@ impl ScraperWorker {
                 debug!("Page has no HTML content: {}", page.url);
                 Err(AppError::InvalidInput("Page has no HTML content".to_string()).into())
             }
+        } else if content_type == "document" {
+            let (Some(document_content), Some(kind)) = (
+                &page.document_content,
+                page.content_type.as_deref().and_then(DocumentKind::from_mime_type),
+            ) else {
+                debug!("Page has no document content: {}", page.url);
+                return Err(AppError::InvalidInput("Page has no document content".to_string()).into());
+            };
+            
+            // Store the document as it was downloaded
+            debug!("Attempting to upload {} document for URL: {}", kind.mime_type(), page.url);
+            match self.content_store.put(document_content, kind.mime_type()).await {
+                Ok(path) => {
+                    debug!("Successfully uploaded document to path: {}", path);
+                    Ok(path)
+                },
+                Err(e) => {
+                    error!("Failed to upload document: {}", e);
+                    Err(e)
+                }
+            }
         } else {
             // For other content types, we'll just return a placeholder path
             debug!("Unsupported content type: {}", content_type);
@@ -344,9 +1046,9 @@ impl ScraperWorker {
     }
     
     async fn store_markdown(&self, page: &Page, markdown: &str) -> Result<String> {
-        // Store the content and return the path from the S3 client
+        // Store the content under its hash and return the path
         debug!("Attempting to upload Markdown content for URL: {}", page.url);
-        match self.storage_client.upload_markdown(&page.job_id, &page.url, &markdown).await {
+        match self.content_store.put(markdown.as_bytes(), "text/markdown").await {
             Ok(path) => {
                 debug!("Successfully uploaded Markdown content to path: {}", path);
                 Ok(path)
@@ -358,17 +1060,34 @@ impl ScraperWorker {
         }
     }
     
+    /// Paths of the stored objects a page refers to
+    fn storage_paths(page: &Page) -> Vec<String> {
+        [&page.html_storage_path, &page.markdown_storage_path, &page.document_storage_path]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+    
+    /// Give back the references to the stored content of a page that couldn't be saved
+    async fn release_content(&self, page: &Page) {
+        if let Err(e) = self.content_store.release(&Self::storage_paths(page)).await {
+            error!("Error releasing the stored content of page {}: {}", page.url, e);
+        }
+    }
+    
+    /// Insert a page, failing if this worker no longer holds the lease on its job
     async fn save_page(&self, page: &Page) -> Result<()> {
-        sqlx::query(
+        let result = sqlx::query(
             r#"
             INSERT INTO pages (
                 id, job_id, url, normalized_url, content_hash, http_status, http_headers,
                 crawled_at, html_storage_path, markdown_storage_path, title, metadata,
-                error_message, depth, parent_url
-            )
-            VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15
+                error_message, depth, parent_url, url_hash, content_type, document_storage_path
             )
+            SELECT
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $17, $18, $19
+            WHERE EXISTS (SELECT 1 FROM jobs WHERE id = $2 AND worker_id = $16)
             "#
         )
         .bind(page.id)
@@ -386,47 +1105,38 @@ impl ScraperWorker {
         .bind(&page.error_message)
         .bind(page.depth)
         .bind(&page.parent_url)
+        .bind(&self.worker_id)
+        .bind(url_hash(&page.normalized_url))
+        .bind(&page.content_type)
+        .bind(&page.document_storage_path)
         .execute(&self.db_pool)
         .await?;
         
+        if result.rows_affected() == 0 {
+            return Err(AppError::Scraper(format!("Worker {} no longer holds job {}", self.worker_id, page.job_id)).into());
+        }
+        
         Ok(())
     }
     
     async fn update_job_stats(&self, job_id: &Uuid, crawled: bool, failed: bool, skipped: bool) -> Result<()> {
-        // Get the current job stats
-        let row = sqlx::query(
-            r#"
-            SELECT pages_crawled, pages_failed, pages_skipped
-            FROM jobs
-            WHERE id = $1
-            "#
-        )
-        .bind(job_id)
-        .fetch_one(&self.db_pool)
-        .await?;
-        
-        // Calculate the new stats
-        let pages_crawled: i32 = row.get("pages_crawled");
-        let pages_failed: i32 = row.get("pages_failed");
-        let pages_skipped: i32 = row.get("pages_skipped");
-        
-        let new_crawled = if crawled { pages_crawled + 1 } else { pages_crawled };
-        let new_failed = if failed { pages_failed + 1 } else { pages_failed };
-        let new_skipped = if skipped { pages_skipped + 1 } else { pages_skipped };
-        
-        // Update the job stats
+        // Increment in the database so concurrent pages of the same job don't overwrite each other
         sqlx::query(
             r#"
             UPDATE jobs
-            SET pages_crawled = $1, pages_failed = $2, pages_skipped = $3, updated_at = $4
-            WHERE id = $5
+            SET pages_crawled = pages_crawled + $1,
+                pages_failed = pages_failed + $2,
+                pages_skipped = pages_skipped + $3,
+                updated_at = $4
+            WHERE id = $5 AND worker_id = $6
             "#
         )
-        .bind(new_crawled)
-        .bind(new_failed)
-        .bind(new_skipped)
+        .bind(crawled as i32)
+        .bind(failed as i32)
+        .bind(skipped as i32)
         .bind(Utc::now())
         .bind(job_id)
+        .bind(&self.worker_id)
         .execute(&self.db_pool)
         .await?;
         
@@ -439,7 +1149,7 @@ impl ScraperWorker {
             SELECT 
                 id, config_id, status, started_at, completed_at, error_message,
                 pages_crawled, pages_failed, pages_skipped, created_at, updated_at,
-                next_run_at, worker_id, metadata
+                next_run_at, worker_id, metadata, attempts, heartbeat_at, priority, queue_name
             FROM jobs
             WHERE id = $1
             "#
@@ -457,6 +1167,7 @@ impl ScraperWorker {
             "completed" => JobStatus::Completed,
             "failed" => JobStatus::Failed,
             "cancelled" => JobStatus::Cancelled,
+            "paused" => JobStatus::Paused,
             _ => JobStatus::Unknown,
         };
 
@@ -475,6 +1186,10 @@ impl ScraperWorker {
             next_run_at: row.get("next_run_at"),
             worker_id: row.get("worker_id"),
             metadata: row.get("metadata"),
+            attempts: row.get("attempts"),
+            heartbeat_at: row.get("heartbeat_at"),
+            priority: row.get::<String, _>("priority").parse().unwrap_or_default(),
+            queue_name: row.get("queue_name"),
         })
     }
     
@@ -485,7 +1200,8 @@ impl ScraperWorker {
                 id, name, description, base_url, include_patterns,
                 exclude_patterns, max_depth, max_pages_per_job, respect_robots_txt,
                 user_agent, request_delay_ms, max_concurrent_requests, schedule,
-                headers, created_at, updated_at, active
+                headers, created_at, updated_at, active, queue_name, crawl_mode,
+                canonicalization, extraction_rules, retention
             FROM scraper_configs
             WHERE id = $1
             "#
@@ -513,19 +1229,27 @@ impl ScraperWorker {
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
             active: row.get("active"),
+            queue_name: row.get("queue_name"),
+            crawl_mode: row.get::<String, _>("crawl_mode").parse().unwrap_or_default(),
+            canonicalization: row.get("canonicalization"),
+            extraction_rules: row.get("extraction_rules"),
+            retention: row.get("retention"),
         })
     }
     
-    async fn mark_job_running(&self, job: &mut Job) -> Result<()> {
+    /// Mark a pending job as running, returning false if it has been cancelled, paused or already claimed
+    async fn mark_job_running(&self, job: &mut Job) -> Result<bool> {
         // Update the job status in the database
         let worker_id = self.worker_id.clone();
         let now = Utc::now();
         
-        sqlx::query(
+        // Resumed and recovered jobs keep their original start time
+        let result = sqlx::query(
             r#"
             UPDATE jobs
-            SET status = $1, started_at = $2, updated_at = $3, worker_id = $4
-            WHERE id = $5
+            SET status = $1, started_at = COALESCE(started_at, $2), updated_at = $3, worker_id = $4,
+                attempts = attempts + 1, heartbeat_at = $3
+            WHERE id = $5 AND status = 'pending'
             "#
         )
         .bind("running")
@@ -536,50 +1260,236 @@ impl ScraperWorker {
         .execute(&self.db_pool)
         .await?;
         
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        
         // Update the job object
         job.status = JobStatus::Running;
-        job.started_at = Some(now);
+        job.started_at = job.started_at.or(Some(now));
         job.updated_at = now;
         job.worker_id = Some(self.worker_id.clone());
+        job.attempts += 1;
+        job.heartbeat_at = Some(now);
         
-        Ok(())
+        Ok(true)
     }
     
-    async fn mark_job_completed(&self, job: &mut Job) -> Result<()> {
+    /// Mark a job as completed, returning false if it was cancelled in the meantime or this worker lost its lease.
+    ///
+    /// A job that was paused just as its frontier ran out has nothing left to
+    /// resume, so it is completed as well.
+    async fn mark_job_completed(&self, job: &mut Job) -> Result<bool> {
         // Update the job status in the database
         let now = Utc::now();
         
-        sqlx::query(
+        let result = sqlx::query(
             r#"
             UPDATE jobs
             SET status = $1, completed_at = $2, updated_at = $3
-            WHERE id = $4
+            WHERE id = $4 AND status IN ('running', 'paused') AND worker_id = $5
             "#
         )
         .bind("completed")
         .bind(now)
         .bind(now)
         .bind(job.id)
+        .bind(&self.worker_id)
         .execute(&self.db_pool)
         .await?;
         
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        
         // Update the job object
         job.status = JobStatus::Completed;
         job.completed_at = Some(now);
         job.updated_at = now;
         
+        Ok(true)
+    }
+    
+    /// Record a heartbeat for a running job, returning false if this worker no longer holds its lease
+    async fn heartbeat(&self, job_id: Uuid, queue: &str) -> bool {
+        let result = sqlx::query("UPDATE jobs SET heartbeat_at = $1 WHERE id = $2 AND worker_id = $3")
+            .bind(Utc::now())
+            .bind(job_id)
+            .bind(&self.worker_id)
+            .execute(&self.db_pool)
+            .await;
+        
+        match result {
+            Ok(result) if result.rows_affected() == 0 => return false,
+            Ok(_) => {},
+            Err(e) => error!("Error recording heartbeat for job {}: {}", job_id, e),
+        }
+        
+        match self.job_queue.extend_visibility(queue, &job_id.to_string()).await {
+            Ok(true) => {},
+            Ok(false) => warn!("Visibility timeout of job {} expired before its heartbeat", job_id),
+            Err(e) => error!("Error extending visibility of job {}: {}", job_id, e),
+        }
+        
+        true
+    }
+    
+    /// Current status of a job, used to notice cancellation and pausing through the API
+    async fn get_job_status(&self, job_id: Uuid) -> Option<JobStatus> {
+        let status = sqlx::query_scalar::<_, String>("SELECT status FROM jobs WHERE id = $1")
+            .bind(job_id)
+            .fetch_optional(&self.db_pool)
+            .await;
+        
+        match status {
+            Ok(Some(status)) => match status.as_str() {
+                "cancelled" => Some(JobStatus::Cancelled),
+                "paused" => Some(JobStatus::Paused),
+                "running" => Some(JobStatus::Running),
+                _ => Some(JobStatus::Unknown),
+            },
+            Ok(None) => None,
+            Err(e) => {
+                error!("Error checking status of job {}: {}", job_id, e);
+                None
+            }
+        }
+    }
+    
+    /// Load the frontier and page count saved when the job was paused or at its last heartbeat
+    async fn load_checkpoint(&self, job_id: Uuid) -> Result<Option<(CrawlFrontier, usize)>> {
+        let row = sqlx::query("SELECT frontier, pages_processed FROM job_checkpoints WHERE job_id = $1")
+            .bind(job_id)
+            .fetch_optional(&self.db_pool)
+            .await?;
+        
+        match row {
+            Some(row) => {
+                let frontier: CrawlFrontier = serde_json::from_value(row.get("frontier"))?;
+                let pages_processed: i32 = row.get("pages_processed");
+                Ok(Some((frontier, pages_processed.max(0) as usize)))
+            },
+            None => Ok(None),
+        }
+    }
+    
+    /// Persist the pending URLs and the set of seen URLs so the job can be resumed
+    async fn save_checkpoint(&self, job_id: Uuid, frontier: &CrawlFrontier, pages_processed: usize) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO job_checkpoints (job_id, frontier, pages_processed, updated_at)
+            SELECT $1, $2, $3, $4
+            WHERE EXISTS (SELECT 1 FROM jobs WHERE id = $1 AND worker_id = $5)
+            ON CONFLICT (job_id) DO UPDATE
+            SET frontier = EXCLUDED.frontier,
+                pages_processed = EXCLUDED.pages_processed,
+                updated_at = EXCLUDED.updated_at
+            "#
+        )
+        .bind(job_id)
+        .bind(serde_json::to_value(frontier)?)
+        .bind(pages_processed as i32)
+        .bind(Utc::now())
+        .bind(&self.worker_id)
+        .execute(&self.db_pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    async fn delete_checkpoint(&self, job_id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM job_checkpoints WHERE job_id = $1")
+            .bind(job_id)
+            .execute(&self.db_pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    /// Clear the worker of a paused job once its checkpoint is saved, which allows it to be resumed
+    async fn release_paused_job(&self, job_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE jobs
+            SET worker_id = NULL, updated_at = $1
+            WHERE id = $2 AND status = 'paused' AND worker_id = $3
+            "#
+        )
+        .bind(Utc::now())
+        .bind(job_id)
+        .bind(&self.worker_id)
+        .execute(&self.db_pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Retry a job that failed with backoff, or fail it and move it to the dead-letter list once its attempts are used up
+    async fn handle_job_error(&self, job_id: Uuid, queue: &str, error_message: &str) -> Result<()> {
+        let job = self.get_job(job_id).await.ok();
+        let attempts = job.as_ref().map_or(0, |job| job.attempts);
+        
+        // Only a job this worker claimed can be retried, otherwise it never started
+        let claimed = job.as_ref().is_some_and(|job| {
+            job.status == JobStatus::Running && job.worker_id.as_deref() == Some(self.worker_id.as_str())
+        });
+        
+        if claimed && attempts < self.recovery_config.max_attempts && self.release_job_for_retry(job_id, error_message).await? {
+            let exponent = (attempts - 1).clamp(0, 10) as u32;
+            let delay_seconds = self.recovery_config.retry_base_delay_seconds.saturating_mul(2u64.pow(exponent));
+            
+            self.job_queue.complete(queue, &job_id.to_string()).await?;
+            let priority = job.as_ref().map_or(JobPriority::Normal, |job| job.priority);
+            self.job_queue.schedule_with_priority(queue, &job_id, delay_seconds, priority).await?;
+            
+            warn!("Job {} failed on attempt {} of {}, retrying in {} seconds", job_id, attempts, self.recovery_config.max_attempts, delay_seconds);
+            return Ok(());
+        }
+        
+        // Mark the job as failed
+        if self.mark_job_failed(job_id, error_message).await? {
+            self.job_queue.fail(queue, &job_id.to_string(), error_message, attempts).await?;
+            
+            if let Ok(job) = self.get_job(job_id).await {
+                self.webhook_service.notify_job(WebhookEventType::JobFailed, &job).await;
+            }
+        } else {
+            // The job was cancelled or taken over by another worker
+            self.job_queue.complete(queue, &job_id.to_string()).await?;
+        }
+        
         Ok(())
     }
     
-    async fn mark_job_failed(&self, job_id: Uuid, error_message: &str) -> Result<()> {
+    /// Put a failed job back to pending so it can be retried, returning false if this worker no longer holds it
+    async fn release_job_for_retry(&self, job_id: Uuid, error_message: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = 'pending', error_message = $1, worker_id = NULL, updated_at = $2
+            WHERE id = $3 AND status = 'running' AND worker_id = $4
+            "#
+        )
+        .bind(error_message)
+        .bind(Utc::now())
+        .bind(job_id)
+        .bind(&self.worker_id)
+        .execute(&self.db_pool)
+        .await?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+    
+    /// Mark a job as failed, returning false if it was cancelled or is held by another worker
+    async fn mark_job_failed(&self, job_id: Uuid, error_message: &str) -> Result<bool> {
         // Update the job status in the database
         let now = Utc::now();
         
-        sqlx::query(
+        let result = sqlx::query(
             r#"
             UPDATE jobs
             SET status = $1, error_message = $2, completed_at = $3, updated_at = $4
-            WHERE id = $5
+            WHERE id = $5 AND status <> 'cancelled' AND (worker_id = $6 OR worker_id IS NULL)
             "#
         )
         .bind("failed")
@@ -587,9 +1497,10 @@ impl ScraperWorker {
         .bind(now)
         .bind(now)
         .bind(job_id)
+        .bind(&self.worker_id)
         .execute(&self.db_pool)
         .await?;
         
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 } 
\ No newline at end of file
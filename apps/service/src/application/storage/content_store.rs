@@ -0,0 +1,198 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use sqlx::{PgExecutor, PgPool, Row};
+use std::sync::Arc;
+use tracing::{debug, error};
+
+use crate::infrastructure::storage::StorageClient;
+
+/// Content-addressed storage of page content.
+///
+/// An object is stored once under the SHA-256 of its bytes, however many
+/// pages hold the same content. `storage_blobs` counts the page columns that
+/// refer to each object: every stored or reused path takes a reference, and a
+/// page that is deleted or never saved gives its references back with
+/// `release`. An object left without references is no longer used by any
+/// page, and the retention janitor deletes it after a grace period.
+#[derive(Clone)]
+pub struct ContentStore {
+    db_pool: PgPool,
+    storage_client: Arc<dyn StorageClient>,
+}
+
+impl ContentStore {
+    pub fn new(db_pool: PgPool, storage_client: Arc<dyn StorageClient>) -> Self {
+        Self { db_pool, storage_client }
+    }
+
+    /// Path of the object with the given content hash, spread over two levels of directories
+    pub fn blob_path(sha256: &str) -> String {
+        format!("blobs/{}/{}/{}", &sha256[..2], &sha256[2..4], sha256)
+    }
+
+    /// Store content and take a reference to it, returning its path.
+    ///
+    /// The object is only uploaded if no earlier upload of the same content
+    /// has been confirmed. The reference is taken first, so an unreferenced
+    /// copy that is being reused is never counted as unused. Uploads of the
+    /// same content write the same bytes to the same path, so concurrent or
+    /// repeated uploads of an unconfirmed object are harmless.
+    pub async fn put(&self, content: &[u8], content_type: &str) -> Result<String> {
+        let sha256 = hex::encode(Sha256::digest(content));
+        let path = Self::blob_path(&sha256);
+
+        let uploaded: bool = sqlx::query(
+            r#"
+            INSERT INTO storage_blobs (path, sha256, size_bytes, ref_count)
+            VALUES ($1, $2, $3, 1)
+            ON CONFLICT (path) DO UPDATE
+            SET ref_count = storage_blobs.ref_count + 1, last_referenced_at = NOW()
+            RETURNING uploaded_at IS NOT NULL AS uploaded
+            "#
+        )
+        .bind(&path)
+        .bind(&sha256)
+        .bind(content.len() as i64)
+        .fetch_one(&self.db_pool)
+        .await?
+        .get("uploaded");
+
+        if uploaded {
+            debug!("Reusing stored content at {}", path);
+            return Ok(path);
+        }
+
+        if let Err(e) = self.storage_client.put_object(&path, content, content_type).await {
+            if let Err(release_err) = self.release(std::slice::from_ref(&path)).await {
+                error!("Error releasing the reference to {}: {}", path, release_err);
+            }
+            return Err(e);
+        }
+
+        sqlx::query("UPDATE storage_blobs SET uploaded_at = NOW() WHERE path = $1 AND uploaded_at IS NULL")
+            .bind(&path)
+            .execute(&self.db_pool)
+            .await?;
+
+        Ok(path)
+    }
+
+    /// Take another reference to each of the given paths, as when a new page reuses the content of an earlier crawl.
+    ///
+    /// The paths are those of stored pages, so their objects exist.
+    pub async fn retain(&self, paths: &[String]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO storage_blobs (path, ref_count, uploaded_at)
+            SELECT path, COUNT(*), NOW() FROM UNNEST($1::TEXT[]) AS path GROUP BY path
+            ON CONFLICT (path) DO UPDATE
+            SET ref_count = storage_blobs.ref_count + EXCLUDED.ref_count, last_referenced_at = NOW()
+            "#
+        )
+        .bind(paths)
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Give back one reference to each of the given paths
+    pub async fn release(&self, paths: &[String]) -> Result<()> {
+        Self::release_with(&self.db_pool, paths).await
+    }
+
+    /// Give back one reference to each of the given paths, as part of a transaction such as the one deleting their pages.
+    ///
+    /// The release counts as a reference for the grace period, since a crawl
+    /// may have read the path just before and be about to take it again.
+    pub async fn release_with<'e, E: PgExecutor<'e>>(executor: E, paths: &[String]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE storage_blobs b
+            SET ref_count = GREATEST(b.ref_count - r.count, 0), last_referenced_at = NOW()
+            FROM (SELECT path, COUNT(*)::INTEGER AS count FROM UNNEST($1::TEXT[]) AS path GROUP BY path) r
+            WHERE b.path = r.path
+            "#
+        )
+        .bind(paths)
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::settings::Storage as StorageConfig;
+    use crate::infrastructure::storage::fs_client::FsStorageClient;
+
+    async fn content_store(pool: &PgPool) -> ContentStore {
+        let root = std::env::temp_dir().join(format!("content-store-{}", uuid::Uuid::new_v4()));
+        let storage_client = FsStorageClient::new(&StorageConfig::fs(root.to_str().unwrap())).await.unwrap();
+
+        ContentStore::new(pool.clone(), Arc::new(storage_client))
+    }
+
+    async fn blob(pool: &PgPool, path: &str) -> (i32, bool) {
+        let row = sqlx::query("SELECT ref_count, uploaded_at IS NOT NULL AS uploaded FROM storage_blobs WHERE path = $1")
+            .bind(path)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+
+        (row.get("ref_count"), row.get("uploaded"))
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn stores_content_once_and_confirms_the_upload(pool: PgPool) {
+        let store = content_store(&pool).await;
+
+        let path = store.put(b"<p>hello</p>", "text/html").await.unwrap();
+        assert_eq!(path, ContentStore::blob_path(&hex::encode(Sha256::digest(b"<p>hello</p>"))));
+        assert_eq!(blob(&pool, &path).await, (1, true));
+
+        assert_eq!(store.put(b"<p>hello</p>", "text/html").await.unwrap(), path);
+        assert_eq!(blob(&pool, &path).await, (2, true));
+        assert_eq!(store.storage_client.get_object(&path).await.unwrap(), "<p>hello</p>");
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn uploads_again_when_an_earlier_upload_was_never_confirmed(pool: PgPool) {
+        let store = content_store(&pool).await;
+        let path = ContentStore::blob_path(&hex::encode(Sha256::digest(b"body")));
+
+        // A crawl took its reference but failed, or is still, uploading
+        sqlx::query("INSERT INTO storage_blobs (path, ref_count) VALUES ($1, 1)")
+            .bind(&path)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(store.put(b"body", "text/plain").await.unwrap(), path);
+        assert_eq!(blob(&pool, &path).await, (2, true));
+        assert_eq!(store.storage_client.get_object(&path).await.unwrap(), "body");
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn retains_and_releases_references(pool: PgPool) {
+        let store = content_store(&pool).await;
+        let path = store.put(b"shared", "text/plain").await.unwrap();
+
+        store.retain(&[path.clone(), path.clone(), "legacy/page.html".to_string()]).await.unwrap();
+        assert_eq!(blob(&pool, &path).await, (3, true));
+        assert_eq!(blob(&pool, "legacy/page.html").await, (1, true));
+
+        store.release(&[path.clone(), path.clone(), path.clone(), path.clone()]).await.unwrap();
+        assert_eq!(blob(&pool, &path).await, (0, true));
+    }
+}
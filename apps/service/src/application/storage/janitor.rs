@@ -0,0 +1,183 @@
+use anyhow::Result;
+use sqlx::types::Json;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::application::storage::retention;
+use crate::config::settings::Retention as RetentionConfig;
+use crate::domain::scraper_config::RetentionPolicy;
+use crate::infrastructure::storage::StorageClient;
+
+/// What one pass of the janitor deleted
+#[derive(Debug, Default)]
+struct Sweep {
+    pages: usize,
+    jobs: u64,
+    objects: usize,
+}
+
+/// Applies the retention policies of configs and deletes stored objects nothing refers to any more.
+///
+/// Each pass deletes the expired crawls of every config in batches, giving
+/// back their references to stored objects, then the jobs left without
+/// crawls. Objects that have been without references for the grace period
+/// are then deleted from storage along with their `storage_blobs` rows.
+pub struct RetentionJanitor {
+    db_pool: PgPool,
+    storage_client: Arc<dyn StorageClient>,
+    config: RetentionConfig,
+    running: bool,
+}
+
+impl RetentionJanitor {
+    pub fn new(db_pool: PgPool, storage_client: Arc<dyn StorageClient>, config: RetentionConfig) -> Self {
+        Self {
+            db_pool,
+            storage_client,
+            config,
+            running: false,
+        }
+    }
+
+    pub async fn start(&mut self) -> Result<()> {
+        if !self.config.enabled {
+            info!("Retention janitor is disabled");
+            return Ok(());
+        }
+
+        if self.running {
+            return Ok(());
+        }
+
+        self.running = true;
+        info!("Starting retention janitor");
+
+        loop {
+            match self.sweep().await {
+                Ok(sweep) if sweep.pages == 0 && sweep.jobs == 0 && sweep.objects == 0 => {}
+                Ok(sweep) => info!(
+                    "Deleted {} expired pages, {} expired jobs and {} unreferenced objects",
+                    sweep.pages, sweep.jobs, sweep.objects
+                ),
+                Err(e) => error!("Error applying retention policies: {}", e),
+            }
+
+            if !self.running {
+                break;
+            }
+
+            sleep(Duration::from_secs(self.config.check_interval_seconds)).await;
+        }
+
+        info!("Retention janitor stopped");
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.running = false;
+    }
+
+    async fn sweep(&self) -> Result<Sweep> {
+        let mut sweep = Sweep::default();
+        let batch_size = self.config.batch_size.max(1);
+
+        let rows = sqlx::query("SELECT id, retention FROM scraper_configs")
+            .fetch_all(&self.db_pool)
+            .await?;
+
+        for row in &rows {
+            let config_id: Uuid = row.get("id");
+            let Json(policy): Json<RetentionPolicy> = row.get("retention");
+
+            if !policy.is_enabled() {
+                continue;
+            }
+
+            match self.apply_policy(config_id, &policy, batch_size).await {
+                Ok((pages, jobs)) => {
+                    sweep.pages += pages;
+                    sweep.jobs += jobs;
+                },
+                Err(e) => warn!("Error applying the retention policy of config {}: {}", config_id, e),
+            }
+        }
+
+        loop {
+            let objects = self.delete_unreferenced_objects(batch_size).await?;
+            sweep.objects += objects;
+
+            if (objects as i64) < batch_size {
+                break;
+            }
+        }
+
+        Ok(sweep)
+    }
+
+    /// Delete all expired crawls of a config a batch at a time, then its expired jobs
+    async fn apply_policy(&self, config_id: Uuid, policy: &RetentionPolicy, batch_size: i64) -> Result<(usize, u64)> {
+        let mut pages = 0;
+
+        loop {
+            let deleted = retention::delete_expired_pages(&self.db_pool, config_id, policy, batch_size).await?;
+            pages += deleted;
+
+            if (deleted as i64) < batch_size {
+                break;
+            }
+        }
+
+        let jobs = retention::delete_expired_jobs(&self.db_pool, config_id, policy).await?;
+
+        debug!("Deleted {} expired pages and {} expired jobs of config {}", pages, jobs, config_id);
+        Ok((pages, jobs))
+    }
+
+    /// Delete a batch of objects that have been without references for the grace period.
+    ///
+    /// The rows stay locked until their objects are gone, so storing the same
+    /// content again waits for the deletion and then uploads a new copy rather
+    /// than referring to an object that is being deleted.
+    async fn delete_unreferenced_objects(&self, batch_size: i64) -> Result<usize> {
+        let mut tx = self.db_pool.begin().await?;
+
+        let paths: Vec<String> = sqlx::query(
+            r#"
+            SELECT path FROM storage_blobs
+            WHERE ref_count = 0 AND last_referenced_at < NOW() - make_interval(secs => $1)
+            ORDER BY last_referenced_at
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+            "#
+        )
+        .bind(self.config.object_grace_period_seconds as f64)
+        .bind(batch_size)
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(|row| row.get("path"))
+        .collect();
+
+        let mut deleted = Vec::with_capacity(paths.len());
+
+        for path in paths {
+            match self.storage_client.delete_object(&path).await {
+                Ok(()) => deleted.push(path),
+                Err(e) => warn!("Error deleting unreferenced object {}: {}", path, e),
+            }
+        }
+
+        sqlx::query("DELETE FROM storage_blobs WHERE path = ANY($1)")
+            .bind(&deleted)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(deleted.len())
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::Result;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+
+use crate::application::storage::content_store::ContentStore;
+use crate::config::settings::Storage as StorageConfig;
+use crate::infrastructure::storage::StorageClient;
+
+/// Page columns that hold the path of a stored object
+const STORAGE_PATH_COLUMNS: [&str; 3] = ["html_storage_path", "markdown_storage_path", "document_storage_path"];
+
+/// Moves objects stored under `{job_id}/{md5(url)}` before content addressing to their content-addressed paths.
+///
+/// Each pass copies a batch of legacy objects, points the pages holding them
+/// at the copy and gives back the legacy object's references, so identical
+/// content of earlier crawls ends up stored once. The legacy objects are left
+/// in place without references.
+pub struct LegacyObjectMigrator {
+    db_pool: PgPool,
+    storage_client: Arc<dyn StorageClient>,
+    content_store: ContentStore,
+    config: StorageConfig,
+    running: bool,
+}
+
+impl LegacyObjectMigrator {
+    pub fn new(db_pool: PgPool, storage_client: Arc<dyn StorageClient>, config: StorageConfig) -> Self {
+        let content_store = ContentStore::new(db_pool.clone(), storage_client.clone());
+
+        Self {
+            db_pool,
+            storage_client,
+            content_store,
+            config,
+            running: false,
+        }
+    }
+
+    pub async fn start(&mut self) -> Result<()> {
+        if !self.config.migrate_legacy_objects {
+            info!("Legacy object migration is disabled");
+            return Ok(());
+        }
+
+        if self.running {
+            return Ok(());
+        }
+
+        self.running = true;
+        info!("Starting legacy object migration");
+
+        while self.running {
+            match self.migrate_batch().await {
+                Ok(0) => {
+                    info!("All legacy objects have been moved to content-addressed paths");
+                    self.running = false;
+                    continue;
+                },
+                Ok(count) => info!("Moved {} legacy objects to content-addressed paths", count),
+                Err(e) => error!("Error moving legacy objects: {}", e),
+            }
+
+            sleep(Duration::from_secs(self.config.migration_interval_seconds)).await;
+        }
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.running = false;
+    }
+
+    /// Move a batch of legacy objects, returning how many were looked at
+    async fn migrate_batch(&self) -> Result<usize> {
+        let rows = sqlx::query(
+            r#"
+            SELECT path FROM storage_blobs
+            WHERE sha256 IS NULL AND ref_count > 0
+            ORDER BY created_at, path
+            LIMIT $1
+            "#
+        )
+        .bind(self.config.migration_batch_size.max(1))
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        for row in &rows {
+            let path: String = row.get("path");
+
+            if let Err(e) = self.migrate_object(&path).await {
+                warn!("Error moving legacy object {}: {}", path, e);
+            }
+        }
+
+        Ok(rows.len())
+    }
+
+    async fn migrate_object(&self, legacy_path: &str) -> Result<()> {
+        let content = match self.storage_client.get_object_bytes(legacy_path).await {
+            Ok(content) => content,
+            Err(e) => {
+                // Pages keep the missing object's path, and it isn't tried again
+                self.mark_missing(legacy_path).await?;
+                return Err(e);
+            }
+        };
+
+        let content_type = content_type_of(legacy_path);
+        let blob_path = self.content_store.put(&content, content_type).await?;
+
+        // Point the pages at the copy and move their references over, all at once
+        let mut tx = self.db_pool.begin().await?;
+        let mut moved: u64 = 0;
+
+        for column in STORAGE_PATH_COLUMNS {
+            let result = sqlx::query(&format!("UPDATE pages SET {} = $2 WHERE {} = $1", column, column))
+                .bind(legacy_path)
+                .bind(&blob_path)
+                .execute(&mut *tx)
+                .await?;
+            moved += result.rows_affected();
+        }
+
+        sqlx::query("UPDATE storage_blobs SET ref_count = GREATEST(ref_count - $2, 0) WHERE path = $1")
+            .bind(legacy_path)
+            .bind(moved as i32)
+            .execute(&mut *tx)
+            .await?;
+
+        // `put` took one reference already
+        sqlx::query("UPDATE storage_blobs SET ref_count = GREATEST(ref_count + $2 - 1, 0) WHERE path = $1")
+            .bind(&blob_path)
+            .bind(moved as i32)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        debug!("Moved {} to {} for {} page columns", legacy_path, blob_path, moved);
+        Ok(())
+    }
+
+    /// Record a legacy object that can't be read under an empty hash, so the migration skips it
+    async fn mark_missing(&self, legacy_path: &str) -> Result<()> {
+        sqlx::query("UPDATE storage_blobs SET sha256 = '' WHERE path = $1")
+            .bind(legacy_path)
+            .execute(&self.db_pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Content type of a legacy object, known from the extension it was stored with
+fn content_type_of(path: &str) -> &'static str {
+    match path.rsplit('.').next().unwrap_or_default() {
+        "html" => "text/html",
+        "md" => "text/markdown",
+        "pdf" => "application/pdf",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "doc" => "application/msword",
+        "rtf" => "application/rtf",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
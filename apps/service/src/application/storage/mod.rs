@@ -0,0 +1,4 @@
+pub mod content_store;
+pub mod janitor;
+pub mod migrator;
+pub mod retention;
@@ -0,0 +1,383 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::application::storage::content_store::ContentStore;
+use crate::domain::scraper_config::RetentionPolicy;
+
+/// Crawls of a config that its retention policy no longer keeps, as the CTE `expired`.
+///
+/// Binds the config id, the number of finished jobs to keep (0 for none), the
+/// number of days to keep crawls for (NULL for none) and whether to keep
+/// crawls that found new content. Only crawls of finished jobs are
+/// candidates, and a crawl found new content when its hash differs from the
+/// previous successful crawl of the same URL.
+const EXPIRED_PAGES: &str = r#"
+    WITH finished_jobs AS (
+        SELECT id, created_at, updated_at FROM jobs
+        WHERE config_id = $1 AND status IN ('completed', 'failed', 'cancelled')
+    ),
+    kept_jobs AS (
+        SELECT id FROM finished_jobs ORDER BY created_at DESC LIMIT $2
+    ),
+    crawls AS (
+        SELECT p.id, p.job_id, p.crawled_at,
+            p.html_storage_path, p.markdown_storage_path, p.document_storage_path,
+            p.error_message IS NULL AND p.content_hash <> ''
+                AND p.content_hash IS DISTINCT FROM LAG(p.content_hash) OVER (
+                    PARTITION BY p.url_hash, p.error_message IS NULL AND p.content_hash <> ''
+                    ORDER BY p.crawled_at, p.id
+                ) AS changed
+        FROM pages p
+        JOIN finished_jobs j ON j.id = p.job_id
+    ),
+    expired AS (
+        SELECT * FROM crawls
+        WHERE job_id NOT IN (SELECT id FROM kept_jobs)
+            AND ($3::INTEGER IS NULL OR crawled_at < NOW() - make_interval(days => $3::INTEGER))
+            AND NOT ($4 AND changed)
+    )
+"#;
+
+/// Finished jobs that are neither among the last jobs kept nor recent enough to keep
+const EXPIRED_JOBS_CONDITION: &str = r#"
+    j.id NOT IN (SELECT id FROM kept_jobs)
+    AND ($3::INTEGER IS NULL OR j.updated_at < NOW() - make_interval(days => $3::INTEGER))
+"#;
+
+/// What applying a config's retention policy would delete
+#[derive(Debug, Default, Serialize)]
+pub struct RetentionReport {
+    pub pages: i64,
+    pub oldest_crawled_at: Option<DateTime<Utc>>,
+    pub newest_crawled_at: Option<DateTime<Utc>>,
+    /// Jobs left without crawls once the expired crawls are gone
+    pub jobs: i64,
+    /// Stored objects only the expired crawls refer to
+    pub objects: i64,
+    pub bytes: i64,
+}
+
+fn bind_policy<'q>(
+    query: sqlx::query::Query<'q, sqlx::Postgres, sqlx::postgres::PgArguments>,
+    config_id: Uuid,
+    policy: &RetentionPolicy,
+) -> sqlx::query::Query<'q, sqlx::Postgres, sqlx::postgres::PgArguments> {
+    query
+        .bind(config_id)
+        .bind(policy.keep_last_jobs.unwrap_or(0))
+        .bind(policy.keep_versions_days)
+        .bind(policy.keep_changed_versions)
+}
+
+/// Work out what the retention policy of a config would delete, without deleting anything
+pub async fn report(db_pool: &PgPool, config_id: Uuid, policy: &RetentionPolicy) -> Result<RetentionReport> {
+    if !policy.is_enabled() {
+        return Ok(RetentionReport::default());
+    }
+
+    let sql = format!(
+        r#"
+        {EXPIRED_PAGES},
+        released AS (
+            SELECT path, COUNT(*) AS refs
+            FROM (
+                SELECT html_storage_path AS path FROM expired
+                UNION ALL
+                SELECT markdown_storage_path FROM expired
+                UNION ALL
+                SELECT document_storage_path FROM expired
+            ) r
+            WHERE path IS NOT NULL
+            GROUP BY path
+        )
+        SELECT
+            (SELECT COUNT(*) FROM expired) AS pages,
+            (SELECT MIN(crawled_at) FROM expired) AS oldest_crawled_at,
+            (SELECT MAX(crawled_at) FROM expired) AS newest_crawled_at,
+            (
+                SELECT COUNT(*) FROM finished_jobs j
+                WHERE {EXPIRED_JOBS_CONDITION}
+                    AND NOT EXISTS (
+                        SELECT 1 FROM pages p WHERE p.job_id = j.id AND p.id NOT IN (SELECT id FROM expired)
+                    )
+            ) AS jobs,
+            COUNT(b.path) AS objects,
+            COALESCE(SUM(b.size_bytes), 0)::BIGINT AS bytes
+        FROM released r
+        JOIN storage_blobs b ON b.path = r.path AND b.ref_count <= r.refs
+        "#
+    );
+
+    let row = bind_policy(sqlx::query(&sql), config_id, policy)
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok(RetentionReport {
+        pages: row.get("pages"),
+        oldest_crawled_at: row.get("oldest_crawled_at"),
+        newest_crawled_at: row.get("newest_crawled_at"),
+        jobs: row.get("jobs"),
+        objects: row.get("objects"),
+        bytes: row.get("bytes"),
+    })
+}
+
+/// Delete up to `limit` of a config's expired crawls, oldest first, and give back their references to stored objects
+pub async fn delete_expired_pages(db_pool: &PgPool, config_id: Uuid, policy: &RetentionPolicy, limit: i64) -> Result<usize> {
+    if !policy.is_enabled() {
+        return Ok(0);
+    }
+
+    let sql = format!("{EXPIRED_PAGES} SELECT id FROM expired ORDER BY crawled_at, id LIMIT $5");
+
+    let ids: Vec<Uuid> = bind_policy(sqlx::query(&sql), config_id, policy)
+        .bind(limit)
+        .fetch_all(db_pool)
+        .await?
+        .iter()
+        .map(|row| row.get("id"))
+        .collect();
+
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    // Links and citations go with their pages
+    let mut tx = db_pool.begin().await?;
+
+    let rows = sqlx::query(
+        r#"
+        DELETE FROM pages WHERE id = ANY($1)
+        RETURNING html_storage_path, markdown_storage_path, document_storage_path
+        "#
+    )
+    .bind(&ids)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let paths: Vec<String> = rows
+        .iter()
+        .flat_map(|row| {
+            ["html_storage_path", "markdown_storage_path", "document_storage_path"]
+                .map(|column| row.get::<Option<String>, _>(column))
+        })
+        .flatten()
+        .collect();
+
+    ContentStore::release_with(&mut *tx, &paths).await?;
+    tx.commit().await?;
+
+    Ok(rows.len())
+}
+
+/// Delete a config's expired jobs once none of their crawls are left
+pub async fn delete_expired_jobs(db_pool: &PgPool, config_id: Uuid, policy: &RetentionPolicy) -> Result<u64> {
+    if !policy.is_enabled() {
+        return Ok(0);
+    }
+
+    let sql = format!(
+        r#"
+        {EXPIRED_PAGES}
+        DELETE FROM jobs
+        WHERE id IN (
+            SELECT j.id FROM finished_jobs j
+            WHERE {EXPIRED_JOBS_CONDITION}
+                AND NOT EXISTS (SELECT 1 FROM pages p WHERE p.job_id = j.id)
+        )
+        "#
+    );
+
+    let result = bind_policy(sqlx::query(&sql), config_id, policy)
+        .execute(db_pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Number and total size of stored objects no page refers to, which the janitor deletes after the grace period
+pub async fn unreferenced_objects(db_pool: &PgPool) -> Result<(i64, i64)> {
+    let row = sqlx::query(
+        "SELECT COUNT(*) AS objects, COALESCE(SUM(size_bytes), 0)::BIGINT AS bytes FROM storage_blobs WHERE ref_count = 0"
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    Ok((row.get("objects"), row.get("bytes")))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::page::url_hash;
+
+    async fn insert_config(pool: &PgPool) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO scraper_configs (id, name, base_url, include_patterns, exclude_patterns, max_depth, user_agent,
+                request_delay_ms, max_concurrent_requests, created_at, updated_at)
+            VALUES ($1, 'test', 'https://example.com/', '{}', '{}', 3, 'test', 0, 1, NOW(), NOW())
+            "#
+        )
+        .bind(id)
+        .execute(pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    async fn insert_job(pool: &PgPool, config_id: Uuid, status: &str, days_ago: i32) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO jobs (id, config_id, status, created_at, updated_at)
+            VALUES ($1, $2, $3, NOW() - make_interval(days => $4), NOW() - make_interval(days => $4))
+            "#
+        )
+        .bind(id)
+        .bind(config_id)
+        .bind(status)
+        .bind(days_ago)
+        .execute(pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    /// A successful crawl, or a failed one without a content hash, whose HTML is stored at `html_path`
+    async fn insert_page(pool: &PgPool, job_id: Uuid, url: &str, content_hash: &str, days_ago: i32, html_path: Option<&str>) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO pages (id, job_id, url, normalized_url, url_hash, content_hash, http_status, crawled_at, depth,
+                html_storage_path, error_message)
+            VALUES ($1, $2, $3, $3, $4, $5, 200, NOW() - make_interval(days => $6), 0, $7,
+                CASE WHEN $5 = '' THEN 'failed' END)
+            "#
+        )
+        .bind(id)
+        .bind(job_id)
+        .bind(url)
+        .bind(url_hash(url))
+        .bind(content_hash)
+        .bind(days_ago)
+        .bind(html_path)
+        .execute(pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    async fn insert_blob(pool: &PgPool, path: &str, ref_count: i32, size_bytes: i64) {
+        sqlx::query("INSERT INTO storage_blobs (path, ref_count, size_bytes, uploaded_at) VALUES ($1, $2, $3, NOW())")
+            .bind(path)
+            .bind(ref_count)
+            .bind(size_bytes)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn page_ids(pool: &PgPool) -> Vec<Uuid> {
+        sqlx::query("SELECT id FROM pages ORDER BY crawled_at")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get("id"))
+            .collect()
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn keeps_the_last_finished_jobs(pool: PgPool) {
+        let config_id = insert_config(&pool).await;
+        let oldest = insert_job(&pool, config_id, "completed", 30).await;
+        let older = insert_job(&pool, config_id, "failed", 20).await;
+        let last = insert_job(&pool, config_id, "completed", 10).await;
+        let running = insert_job(&pool, config_id, "running", 40).await;
+
+        insert_page(&pool, oldest, "https://example.com/", "a", 30, None).await;
+        insert_page(&pool, older, "https://example.com/", "a", 20, None).await;
+        let kept = insert_page(&pool, last, "https://example.com/", "a", 10, None).await;
+        let unfinished = insert_page(&pool, running, "https://example.com/", "a", 40, None).await;
+
+        let policy = RetentionPolicy { keep_last_jobs: Some(1), keep_versions_days: None, keep_changed_versions: false };
+
+        let report = report(&pool, config_id, &policy).await.unwrap();
+        assert_eq!((report.pages, report.jobs), (2, 2));
+
+        assert_eq!(delete_expired_pages(&pool, config_id, &policy, 1).await.unwrap(), 1);
+        assert_eq!(delete_expired_pages(&pool, config_id, &policy, 10).await.unwrap(), 1);
+        assert_eq!(delete_expired_jobs(&pool, config_id, &policy).await.unwrap(), 2);
+
+        assert_eq!(page_ids(&pool).await, vec![unfinished, kept]);
+        let jobs: i64 = sqlx::query("SELECT COUNT(*) AS jobs FROM jobs").fetch_one(&pool).await.unwrap().get("jobs");
+        assert_eq!(jobs, 2);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn keeps_recent_crawls_and_those_that_found_new_content(pool: PgPool) {
+        let config_id = insert_config(&pool).await;
+        let mut pages = Vec::new();
+
+        // The content of the page changed 90 and 10 days ago, and one crawl in between failed
+        for (days_ago, content_hash) in [(90, "a"), (60, "a"), (50, ""), (40, "a"), (10, "b"), (5, "b")] {
+            let job_id = insert_job(&pool, config_id, "completed", days_ago).await;
+            pages.push(insert_page(&pool, job_id, "https://example.com/page", content_hash, days_ago, None).await);
+        }
+
+        let policy = RetentionPolicy { keep_last_jobs: None, keep_versions_days: Some(30), keep_changed_versions: true };
+        delete_expired_pages(&pool, config_id, &policy, 100).await.unwrap();
+        assert_eq!(page_ids(&pool).await, vec![pages[0], pages[4], pages[5]]);
+
+        let policy = RetentionPolicy { keep_changed_versions: false, ..policy };
+        delete_expired_pages(&pool, config_id, &policy, 100).await.unwrap();
+        assert_eq!(page_ids(&pool).await, vec![pages[4], pages[5]]);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn releases_the_objects_of_deleted_crawls(pool: PgPool) {
+        let config_id = insert_config(&pool).await;
+        let old_job = insert_job(&pool, config_id, "completed", 20).await;
+        let new_job = insert_job(&pool, config_id, "completed", 10).await;
+
+        insert_blob(&pool, "blobs/only-old", 1, 100).await;
+        insert_blob(&pool, "blobs/shared", 2, 50).await;
+        insert_page(&pool, old_job, "https://example.com/a", "a", 20, Some("blobs/only-old")).await;
+        insert_page(&pool, old_job, "https://example.com/b", "b", 20, Some("blobs/shared")).await;
+        insert_page(&pool, new_job, "https://example.com/b", "b", 10, Some("blobs/shared")).await;
+
+        let policy = RetentionPolicy { keep_last_jobs: Some(1), keep_versions_days: None, keep_changed_versions: false };
+
+        let report = report(&pool, config_id, &policy).await.unwrap();
+        assert_eq!((report.pages, report.jobs, report.objects, report.bytes), (2, 1, 1, 100));
+
+        assert_eq!(delete_expired_pages(&pool, config_id, &policy, 100).await.unwrap(), 2);
+        assert_eq!(unreferenced_objects(&pool).await.unwrap(), (1, 100));
+
+        let shared: i32 = sqlx::query("SELECT ref_count FROM storage_blobs WHERE path = 'blobs/shared'")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .get("ref_count");
+        assert_eq!(shared, 1);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn deletes_nothing_without_a_policy(pool: PgPool) {
+        let config_id = insert_config(&pool).await;
+        let job_id = insert_job(&pool, config_id, "completed", 400).await;
+        insert_page(&pool, job_id, "https://example.com/", "a", 400, None).await;
+
+        let policy = RetentionPolicy::default();
+        assert_eq!(report(&pool, config_id, &policy).await.unwrap().pages, 0);
+        assert_eq!(delete_expired_pages(&pool, config_id, &policy, 100).await.unwrap(), 0);
+        assert_eq!(delete_expired_jobs(&pool, config_id, &policy).await.unwrap(), 0);
+        assert_eq!(page_ids(&pool).await.len(), 1);
+    }
+}
@@ -0,0 +1,260 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use reqwest::Client as HttpClient;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::application::webhook::signature;
+use crate::config::settings::Webhooks as WebhooksConfig;
+use crate::domain::webhook::{Webhook, WebhookDelivery};
+
+/// Maximum number of deliveries sent at the same time
+const MAX_CONCURRENT_DELIVERIES: usize = 10;
+/// Response bodies are truncated to this many bytes before being stored
+const MAX_RESPONSE_BODY_BYTES: usize = 4096;
+/// Upper bound for the retry backoff
+const MAX_RETRY_DELAY_SECONDS: i64 = 6 * 60 * 60;
+
+/// Sends pending webhook deliveries and retries failed ones with exponential backoff
+pub struct WebhookDispatcher {
+    db_pool: PgPool,
+    http_client: HttpClient,
+    config: WebhooksConfig,
+    running: bool,
+}
+
+impl WebhookDispatcher {
+    pub fn new(db_pool: PgPool, config: WebhooksConfig) -> Result<Self> {
+        let http_client = HttpClient::builder()
+            .timeout(Duration::from_secs(config.request_timeout_secs))
+            .user_agent(concat!("Firmata-Webhooks/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+
+        Ok(Self {
+            db_pool,
+            http_client,
+            config,
+            running: false,
+        })
+    }
+
+    pub async fn start(&mut self) -> Result<()> {
+        if !self.config.enabled {
+            info!("Webhook dispatcher is disabled");
+            return Ok(());
+        }
+
+        if self.running {
+            return Ok(());
+        }
+
+        self.running = true;
+        info!("Starting webhook dispatcher");
+
+        while self.running {
+            match self.dispatch_due().await {
+                Ok(0) => {}
+                Ok(count) => debug!("Dispatched {} webhook deliveries", count),
+                Err(e) => error!("Error dispatching webhook deliveries: {}", e),
+            }
+
+            sleep(Duration::from_secs(self.config.poll_interval_seconds)).await;
+        }
+
+        info!("Webhook dispatcher stopped");
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        info!("Stopping webhook dispatcher");
+        self.running = false;
+    }
+
+    /// Time until which an in-flight delivery is hidden from other dispatchers.
+    ///
+    /// next_retry_at is pushed past the request timeout while a delivery is
+    /// being sent so that another dispatcher instance does not pick it up too.
+    pub fn lease_until(&self) -> DateTime<Utc> {
+        Utc::now() + chrono::Duration::seconds(self.config.request_timeout_secs as i64 * 2)
+    }
+
+    /// Claim a batch of due deliveries and send them
+    async fn dispatch_due(&self) -> Result<usize> {
+        let lease_until = self.lease_until();
+
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            UPDATE webhook_deliveries
+            SET next_retry_at = $2, updated_at = NOW()
+            WHERE id IN (
+                SELECT id
+                FROM webhook_deliveries
+                WHERE status IN ('pending', 'failed') AND next_retry_at <= NOW()
+                ORDER BY next_retry_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#
+        )
+        .bind(self.config.batch_size)
+        .bind(lease_until)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        if deliveries.is_empty() {
+            return Ok(0);
+        }
+
+        let webhooks = self.get_webhooks(&deliveries).await?;
+        let count = deliveries.len();
+
+        stream::iter(deliveries)
+            .for_each_concurrent(MAX_CONCURRENT_DELIVERIES, |mut delivery| {
+                let webhook = webhooks.get(&delivery.webhook_id);
+                async move {
+                    let result = match webhook {
+                        Some(webhook) if webhook.active => self.deliver(webhook, &mut delivery).await,
+                        _ => {
+                            // The webhook was deactivated after the event was recorded
+                            delivery.mark_failed("Webhook is inactive".to_string(), None);
+                            self.update_delivery(&delivery).await
+                        }
+                    };
+
+                    if let Err(e) = result {
+                        error!("Error processing webhook delivery {}: {}", delivery.id, e);
+                    }
+                }
+            })
+            .await;
+
+        Ok(count)
+    }
+
+    /// POST a delivery to its webhook and record the outcome
+    pub async fn deliver(&self, webhook: &Webhook, delivery: &mut WebhookDelivery) -> Result<()> {
+        debug!("Delivering {} to {}", delivery.id, webhook.url);
+
+        // Sign the exact bytes that are sent so receivers can verify them
+        let body = serde_json::to_vec(&delivery.payload)?;
+        let timestamp = Utc::now().timestamp();
+
+        let mut request = self.http_client.post(&webhook.url);
+
+        // Custom headers configured on the webhook go first, so the headers
+        // below replace any of the same name rather than being sent twice
+        if let serde_json::Value::Object(headers) = &webhook.headers {
+            for (name, value) in headers {
+                if let Some(value) = value.as_str() {
+                    request = request.header(name.as_str(), value);
+                }
+            }
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert("X-Firmata-Event", HeaderValue::from_str(&delivery.event_type.to_string())?);
+        headers.insert("X-Firmata-Delivery", HeaderValue::from_str(&delivery.id.to_string())?);
+        headers.insert(signature::TIMESTAMP_HEADER, HeaderValue::from(timestamp));
+
+        if let Some(signature) = signature::signature_header(&webhook.signing_secrets(), timestamp, &body) {
+            headers.insert(signature::SIGNATURE_HEADER, HeaderValue::from_str(&signature)?);
+        }
+
+        match request.headers(headers).body(body).send().await {
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await.ok().map(truncate_body);
+
+                if status.is_success() {
+                    delivery.mark_delivered(status.as_u16() as i32, body);
+                    debug!("Delivered {} to {} ({})", delivery.id, webhook.url, status);
+                } else {
+                    delivery.response_status = Some(status.as_u16() as i32);
+                    delivery.response_body = body;
+                    let retry_after = self.retry_delay_seconds(delivery.retry_count + 1);
+                    delivery.mark_failed(format!("Receiver responded with {}", status), retry_after);
+                    warn!("Webhook delivery {} to {} failed with status {}", delivery.id, webhook.url, status);
+                }
+            },
+            Err(e) => {
+                let retry_after = self.retry_delay_seconds(delivery.retry_count + 1);
+                delivery.mark_failed(format!("Request failed: {}", e), retry_after);
+                warn!("Webhook delivery {} to {} failed: {}", delivery.id, webhook.url, e);
+            }
+        }
+
+        self.update_delivery(delivery).await
+    }
+
+    /// Backoff before the given attempt, or `None` once retries are exhausted
+    fn retry_delay_seconds(&self, retry_count: i32) -> Option<i64> {
+        if retry_count > self.config.max_retries {
+            return None;
+        }
+
+        let exponent = (retry_count - 1).clamp(0, 20) as u32;
+        let delay = self.config.retry_base_delay_seconds.saturating_mul(2i64.pow(exponent));
+        Some(delay.min(MAX_RETRY_DELAY_SECONDS))
+    }
+
+    async fn get_webhooks(&self, deliveries: &[WebhookDelivery]) -> Result<HashMap<Uuid, Webhook>> {
+        let ids: Vec<Uuid> = deliveries.iter().map(|d| d.webhook_id).collect();
+
+        let webhooks = sqlx::query_as::<_, Webhook>(
+            r#"
+            SELECT id, name, url, event_types, secret, previous_secret, previous_secret_expires_at,
+                   active, created_at, updated_at, headers
+            FROM webhooks
+            WHERE id = ANY($1)
+            "#
+        )
+        .bind(&ids)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(webhooks.into_iter().map(|w| (w.id, w)).collect())
+    }
+
+    async fn update_delivery(&self, delivery: &WebhookDelivery) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $1, response_status = $2, response_body = $3, error_message = $4,
+                updated_at = $5, delivered_at = $6, retry_count = $7, next_retry_at = $8
+            WHERE id = $9
+            "#
+        )
+        .bind(delivery.status.to_string())
+        .bind(delivery.response_status)
+        .bind(&delivery.response_body)
+        .bind(&delivery.error_message)
+        .bind(delivery.updated_at)
+        .bind(delivery.delivered_at)
+        .bind(delivery.retry_count)
+        .bind(delivery.next_retry_at)
+        .bind(delivery.id)
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn truncate_body(mut body: String) -> String {
+    if body.len() > MAX_RESPONSE_BODY_BYTES {
+        let mut end = MAX_RESPONSE_BODY_BYTES;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        body.truncate(end);
+    }
+    body
+}
@@ -0,0 +1,3 @@
+pub mod service;
+pub mod dispatcher;
+pub mod signature;
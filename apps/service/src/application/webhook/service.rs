@@ -0,0 +1,137 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::domain::job::Job;
+use crate::domain::page::Page;
+use crate::domain::webhook::{Webhook, WebhookDelivery, WebhookEventType};
+
+/// Records webhook deliveries for events raised by jobs and pages.
+///
+/// Emitting an event only writes a pending `webhook_deliveries` row for every
+/// subscribed webhook; the `WebhookDispatcher` picks those rows up and POSTs
+/// them, so emitters never wait on a subscriber.
+pub struct WebhookService {
+    db_pool: PgPool,
+}
+
+impl WebhookService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Emit an event to every active webhook subscribed to it and return the number of deliveries recorded
+    pub async fn emit(&self, event_type: WebhookEventType, data: serde_json::Value) -> Result<usize> {
+        let webhooks = sqlx::query_as::<_, Webhook>(
+            r#"
+            SELECT id, name, url, event_types, secret, previous_secret, previous_secret_expires_at,
+                   active, created_at, updated_at, headers
+            FROM webhooks
+            WHERE active = true
+            "#
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        let mut recorded = 0;
+
+        for webhook in webhooks.iter().filter(|w| w.is_subscribed_to(&event_type)) {
+            let delivery = Self::build_delivery(webhook.id, event_type.clone(), data.clone());
+            self.record_delivery(&delivery).await?;
+            recorded += 1;
+        }
+
+        if recorded > 0 {
+            debug!("Recorded {} deliveries for event {}", recorded, event_type.to_string());
+        }
+
+        Ok(recorded)
+    }
+
+    /// Emit a job lifecycle event, logging instead of failing if it cannot be recorded
+    pub async fn notify_job(&self, event_type: WebhookEventType, job: &Job) {
+        let data = serde_json::json!({ "job": job });
+
+        if let Err(e) = self.emit(event_type.clone(), data).await {
+            error!("Failed to emit {} for job {}: {}", event_type.to_string(), job.id, e);
+        }
+    }
+
+    /// Emit a page event, logging instead of failing if it cannot be recorded
+    pub async fn notify_page(&self, event_type: WebhookEventType, page: &Page) {
+        let data = serde_json::json!({ "page": page });
+
+        if let Err(e) = self.emit(event_type.clone(), data).await {
+            error!("Failed to emit {} for page {}: {}", event_type.to_string(), page.url, e);
+        }
+    }
+
+    /// Emit a content change of a page that differs from its previous crawl, logging instead of failing
+    pub async fn notify_content_changed(&self, page: &Page, previous_content_hash: &str) {
+        let data = serde_json::json!({
+            "page": page,
+            "previous_content_hash": previous_content_hash,
+        });
+
+        if let Err(e) = self.emit(WebhookEventType::ContentChanged, data).await {
+            error!("Failed to emit {} for page {}: {}", WebhookEventType::ContentChanged.to_string(), page.url, e);
+        }
+    }
+
+    /// Build a delivery whose payload wraps `data` in the standard event envelope
+    pub fn build_delivery(webhook_id: Uuid, event_type: WebhookEventType, data: serde_json::Value) -> WebhookDelivery {
+        let mut delivery = WebhookDelivery::new(webhook_id, event_type.clone(), serde_json::Value::Null);
+        delivery.payload = serde_json::json!({
+            "id": delivery.id,
+            "event": event_type.to_string(),
+            "created_at": delivery.created_at,
+            "data": data,
+        });
+        delivery
+    }
+
+    /// Build a synthetic ping delivery for a webhook
+    pub fn build_ping(webhook: &Webhook) -> WebhookDelivery {
+        let data = serde_json::json!({
+            "webhook_id": webhook.id,
+            "message": "ping",
+        });
+        Self::build_delivery(webhook.id, WebhookEventType::Ping, data)
+    }
+
+    /// Build a new delivery that resends the payload of an earlier one
+    pub fn build_redelivery(original: &WebhookDelivery) -> WebhookDelivery {
+        WebhookDelivery::new(original.webhook_id, original.event_type.clone(), original.payload.clone())
+    }
+
+    pub async fn record_delivery(&self, delivery: &WebhookDelivery) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_deliveries (
+                id, webhook_id, event_type, payload, status, response_status, response_body,
+                error_message, created_at, updated_at, delivered_at, retry_count, next_retry_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            "#
+        )
+        .bind(delivery.id)
+        .bind(delivery.webhook_id)
+        .bind(delivery.event_type.to_string())
+        .bind(&delivery.payload)
+        .bind(delivery.status.to_string())
+        .bind(delivery.response_status)
+        .bind(&delivery.response_body)
+        .bind(&delivery.error_message)
+        .bind(delivery.created_at)
+        .bind(delivery.updated_at)
+        .bind(delivery.delivered_at)
+        .bind(delivery.retry_count)
+        .bind(delivery.next_retry_at)
+        .execute(&self.db_pool)
+        .await?;
+
+        debug!("Queued webhook delivery {} for webhook {}", delivery.id, delivery.webhook_id);
+        Ok(())
+    }
+}
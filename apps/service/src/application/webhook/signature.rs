@@ -0,0 +1,43 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header carrying the unix timestamp that was signed together with the body
+pub const TIMESTAMP_HEADER: &str = "X-Firmata-Timestamp";
+/// Header carrying one `sha256=<hex>` entry per active secret, comma separated
+pub const SIGNATURE_HEADER: &str = "X-Firmata-Signature";
+
+/// Compute the HMAC-SHA256 signature of `"{timestamp}.{body}"` as lowercase hex.
+///
+/// Receivers recompute this over the raw request body and the value of the
+/// timestamp header, and reject requests whose timestamp is too old to
+/// protect against replays.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Build the signature header value for the given secrets, or `None` if there are none
+pub fn signature_header(secrets: &[&str], timestamp: i64, body: &[u8]) -> Option<String> {
+    if secrets.is_empty() {
+        return None;
+    }
+
+    let signatures: Vec<String> = secrets
+        .iter()
+        .map(|secret| format!("sha256={}", sign(secret, timestamp, body)))
+        .collect();
+
+    Some(signatures.join(","))
+}
+
+/// Generate a new random webhook secret
+pub fn generate_secret() -> String {
+    format!("whsec_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
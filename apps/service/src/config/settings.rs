@@ -13,15 +13,104 @@ pub struct Redis {
     pub url: String,
     pub pool_size: u32,
     pub job_queue_name: String,
+    /// Queues this instance's worker takes jobs from, in order of preference
+    #[serde(default)]
+    pub worker_queues: Vec<String>,
+}
+
+impl Redis {
+    /// Queues to work on, falling back to the default job queue
+    pub fn worker_queues(&self) -> Vec<String> {
+        if self.worker_queues.is_empty() {
+            vec![self.job_queue_name.clone()]
+        } else {
+            self.worker_queues.clone()
+        }
+    }
+}
+
+/// Where crawled content is stored
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// An S3 compatible object store such as AWS S3 or MinIO
+    #[default]
+    S3,
+    /// A directory on the local filesystem, for development and air-gapped deployments
+    Fs,
+}
+
+/// Compression of stored HTML, Markdown and text documents
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageCompression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Storage {
-    pub endpoint: String,
-    pub region: String,
-    pub bucket: String,
-    pub access_key: String,
-    pub secret_key: String,
+    #[serde(default)]
+    pub backend: StorageBackend,
+    /// Root directory of the `fs` backend
+    #[serde(default = "default_storage_path")]
+    pub path: String,
+    /// Compression of new objects; objects keep the compression they were stored with
+    #[serde(default)]
+    pub compression: StorageCompression,
+    /// Move objects stored before content addressing to content-addressed paths in the background
+    #[serde(default)]
+    pub migrate_legacy_objects: bool,
+    /// Number of legacy objects moved per pass
+    #[serde(default = "default_migration_batch_size")]
+    pub migration_batch_size: i64,
+    #[serde(default = "default_migration_interval_seconds")]
+    pub migration_interval_seconds: u64,
+    /// Settings of the `s3` backend, which the `fs` backend doesn't need
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub bucket: Option<String>,
+    #[serde(default)]
+    pub access_key: Option<String>,
+    #[serde(default)]
+    pub secret_key: Option<String>,
+}
+
+#[cfg(test)]
+impl Storage {
+    /// Settings of the `fs` backend keeping content below `path`
+    pub fn fs(path: &str) -> Self {
+        Self {
+            backend: StorageBackend::Fs,
+            path: path.to_string(),
+            compression: StorageCompression::None,
+            migrate_legacy_objects: false,
+            migration_batch_size: default_migration_batch_size(),
+            migration_interval_seconds: default_migration_interval_seconds(),
+            endpoint: None,
+            region: None,
+            bucket: None,
+            access_key: None,
+            secret_key: None,
+        }
+    }
+}
+
+fn default_storage_path() -> String {
+    "./data/storage".to_string()
+}
+
+fn default_migration_batch_size() -> i64 {
+    100
+}
+
+fn default_migration_interval_seconds() -> u64 {
+    60
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -43,6 +132,8 @@ pub struct Scraper {
     pub max_retries: u32,
     pub request_timeout_secs: u64,
     pub respect_robots_txt: bool,
+    pub robots_txt_cache_ttl_secs: u64,
+    pub max_crawl_delay_ms: u64,
     pub max_page_size_bytes: usize,
 }
 
@@ -50,6 +141,37 @@ pub struct Scraper {
 pub struct Scheduler {
     pub enabled: bool,
     pub check_interval_seconds: u64,
+    pub promote_interval_seconds: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Webhooks {
+    pub enabled: bool,
+    pub poll_interval_seconds: u64,
+    pub batch_size: i64,
+    pub max_retries: i32,
+    pub retry_base_delay_seconds: i64,
+    pub request_timeout_secs: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Recovery {
+    pub enabled: bool,
+    pub visibility_timeout_seconds: u64,
+    pub check_interval_seconds: u64,
+    pub max_attempts: i32,
+    pub retry_base_delay_seconds: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Retention {
+    /// Apply the retention policies of configs and delete unreferenced objects in the background
+    pub enabled: bool,
+    pub check_interval_seconds: u64,
+    /// Pages or objects deleted per transaction
+    pub batch_size: i64,
+    /// How long an object must have been without references before it is deleted, so a crawl about to reuse it still finds it
+    pub object_grace_period_seconds: u64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -61,6 +183,9 @@ pub struct AppConfig {
     pub server: Server,
     pub scraper: Scraper,
     pub scheduler: Scheduler,
+    pub webhooks: Webhooks,
+    pub recovery: Recovery,
+    pub retention: Retention,
 }
 
 impl AppConfig {
@@ -84,4 +209,23 @@ impl AppConfig {
 
         s.try_deserialize()
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::FileFormat;
+
+    #[test]
+    fn fs_storage_needs_no_s3_settings() {
+        let storage: Storage = Config::builder()
+            .add_source(File::from_str("backend = \"fs\"\npath = \"/var/lib/scraper\"", FileFormat::Toml))
+            .build()
+            .and_then(Config::try_deserialize)
+            .unwrap();
+
+        assert_eq!(storage.backend, StorageBackend::Fs);
+        assert_eq!(storage.path, "/var/lib/scraper");
+        assert_eq!(storage.bucket, None);
+        assert_eq!(storage.access_key, None);
+    }
+}
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use uuid::Uuid;
 use sqlx::Type;
 
@@ -12,6 +13,7 @@ pub enum JobStatus {
     Failed,
     Cancelled,
     Unknown,
+    Paused,
 }
 
 impl ToString for JobStatus {
@@ -23,6 +25,7 @@ impl ToString for JobStatus {
             JobStatus::Failed => "failed".to_string(),
             JobStatus::Cancelled => "cancelled".to_string(),
             JobStatus::Unknown => "unknown".to_string(),
+            JobStatus::Paused => "paused".to_string(),
         }
     }
 }
@@ -37,6 +40,7 @@ impl TryFrom<i32> for JobStatus {
             2 => Ok(JobStatus::Completed),
             3 => Ok(JobStatus::Failed),
             4 => Ok(JobStatus::Cancelled),
+            6 => Ok(JobStatus::Paused),
             _ => Ok(JobStatus::Unknown),
         }
     }
@@ -51,6 +55,46 @@ impl From<JobStatus> for i32 {
             JobStatus::Failed => 3,
             JobStatus::Cancelled => 4,
             JobStatus::Unknown => 5,
+            JobStatus::Paused => 6,
+        }
+    }
+}
+
+/// Order in which jobs of the same queue are picked up by workers
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum JobPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl JobPriority {
+    /// All priorities, in the order workers dequeue them
+    pub const DESCENDING: [JobPriority; 3] = [JobPriority::High, JobPriority::Normal, JobPriority::Low];
+}
+
+impl ToString for JobPriority {
+    fn to_string(&self) -> String {
+        match self {
+            JobPriority::Low => "low".to_string(),
+            JobPriority::Normal => "normal".to_string(),
+            JobPriority::High => "high".to_string(),
+        }
+    }
+}
+
+impl FromStr for JobPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "low" => Ok(JobPriority::Low),
+            "normal" => Ok(JobPriority::Normal),
+            "high" => Ok(JobPriority::High),
+            _ => Err(format!("Unknown job priority: {}", s)),
         }
     }
 }
@@ -71,10 +115,14 @@ pub struct Job {
     pub next_run_at: Option<DateTime<Utc>>,
     pub worker_id: Option<String>,
     pub metadata: serde_json::Value,
+    pub attempts: i32,
+    pub heartbeat_at: Option<DateTime<Utc>>,
+    pub priority: JobPriority,
+    pub queue_name: String,
 }
 
 impl Job {
-    pub fn new(config_id: Uuid) -> Self {
+    pub fn new(config_id: Uuid, queue_name: String) -> Self {
         let now = Utc::now();
         Self {
             id: Uuid::new_v4(),
@@ -91,6 +139,10 @@ impl Job {
             next_run_at: None,
             worker_id: None,
             metadata: serde_json::json!({}),
+            attempts: 0,
+            heartbeat_at: None,
+            priority: JobPriority::Normal,
+            queue_name,
         }
     }
 
@@ -117,6 +169,16 @@ impl Job {
         self.updated_at = now;
     }
 
+    pub fn pause(&mut self) {
+        self.status = JobStatus::Paused;
+        self.updated_at = Utc::now();
+    }
+
+    pub fn resume(&mut self) {
+        self.status = JobStatus::Pending;
+        self.updated_at = Utc::now();
+    }
+
     pub fn cancel(&mut self) {
         let now = Utc::now();
         self.status = JobStatus::Cancelled;
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 #[derive(sqlx::FromRow,Debug, Clone, Serialize, Deserialize)]
@@ -19,11 +20,82 @@ pub struct Page {
     pub error_message: Option<String>,
     pub depth: i32,
     pub parent_url: Option<String>,
+    /// MIME type of the response, e.g. `text/html` or `application/pdf`
+    pub content_type: Option<String>,
+    /// Where a non-HTML document is stored as it was downloaded
+    pub document_storage_path: Option<String>,
     
     // Temporary field to hold HTML content, not stored in the database
     #[sqlx(skip)]
     #[serde(skip)]
     pub html_content: Option<String>,
+    
+    // Temporary fields to hold a downloaded document and its Markdown rendition, not stored in the database
+    #[sqlx(skip)]
+    #[serde(skip)]
+    pub document_content: Option<Vec<u8>>,
+    #[sqlx(skip)]
+    #[serde(skip)]
+    pub markdown_content: Option<String>,
+    
+    // Whether the page asked not to be indexed through meta robots or X-Robots-Tag, not stored in the database
+    #[sqlx(skip)]
+    #[serde(skip)]
+    pub noindex: bool,
+    
+    // Citations found in the text of the page, stored in their own table
+    #[sqlx(skip)]
+    #[serde(skip)]
+    pub citations: Vec<PageCitation>,
+}
+
+/// A link found on a page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageLink {
+    /// Canonical form of the link target
+    pub url: String,
+    /// Text of the anchor, or the `alt` or `title` of the element
+    pub anchor_text: Option<String>,
+    /// Element the link came from: `a`, `area`, `link`, `iframe`, `frame`, or `document` for a link in a PDF or Word file
+    pub element: String,
+    /// Whether the link is marked `rel="nofollow"` or the page asked for its links not to be followed
+    pub nofollow: bool,
+}
+
+/// Kind of authority a citation refers to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CitationKind {
+    Statute,
+    Regulation,
+    Case,
+}
+
+impl CitationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CitationKind::Statute => "statute",
+            CitationKind::Regulation => "regulation",
+            CitationKind::Case => "case",
+        }
+    }
+}
+
+/// A citation of a statute, regulation or case found on a page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageCitation {
+    pub kind: CitationKind,
+    /// The citation as it was first written on the page
+    pub citation: String,
+    /// Normalised form, e.g. `42 U.S.C. § 1983` for `42 USC 1983`
+    pub normalized: String,
+    /// How many times the page cites it
+    pub occurrences: i32,
+}
+
+/// Hex SHA-256 of a normalized URL, which identifies the URL across crawls
+pub fn url_hash(normalized_url: &str) -> String {
+    hex::encode(Sha256::digest(normalized_url.as_bytes()))
 }
 
 impl Page {
@@ -53,7 +125,13 @@ impl Page {
             error_message: None,
             depth,
             parent_url,
+            content_type: None,
+            document_storage_path: None,
             html_content: None,
+            document_content: None,
+            markdown_content: None,
+            noindex: false,
+            citations: Vec::new(),
         }
     }
 
@@ -81,7 +159,13 @@ impl Page {
             error_message: Some(error_message),
             depth,
             parent_url,
+            content_type: None,
+            document_storage_path: None,
             html_content: None,
+            document_content: None,
+            markdown_content: None,
+            noindex: false,
+            citations: Vec::new(),
         }
     }
 
@@ -1,7 +1,144 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::Type;
+use std::str::FromStr;
 use uuid::Uuid;
 
+/// Where a config's crawls find the URLs to crawl
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CrawlMode {
+    /// Start at the base URL and follow links
+    #[default]
+    Links,
+    /// Crawl only the URLs listed in the site's sitemaps
+    Sitemap,
+    /// Seed the crawl from the sitemaps and the base URL, then follow links
+    Both,
+}
+
+impl CrawlMode {
+    pub fn follows_links(&self) -> bool {
+        matches!(self, CrawlMode::Links | CrawlMode::Both)
+    }
+
+    pub fn uses_sitemaps(&self) -> bool {
+        matches!(self, CrawlMode::Sitemap | CrawlMode::Both)
+    }
+}
+
+impl ToString for CrawlMode {
+    fn to_string(&self) -> String {
+        match self {
+            CrawlMode::Links => "links".to_string(),
+            CrawlMode::Sitemap => "sitemap".to_string(),
+            CrawlMode::Both => "both".to_string(),
+        }
+    }
+}
+
+impl FromStr for CrawlMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "links" => Ok(CrawlMode::Links),
+            "sitemap" => Ok(CrawlMode::Sitemap),
+            "both" => Ok(CrawlMode::Both),
+            _ => Err(format!("Unknown crawl mode: {}", s)),
+        }
+    }
+}
+
+/// How a config's crawls rewrite URLs so variants of the same page are crawled once.
+///
+/// Fragments are always removed, hosts lowercased and default ports dropped.
+/// Every rule is off by default, so configs that don't opt in keep the
+/// `normalized_url` (and URL hash) their pages were recorded under.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CanonicalizationRules {
+    /// Remove analytics parameters such as `utm_source`, `gclid` and `fbclid` from the query
+    pub strip_tracking_params: bool,
+    /// Further query parameters to remove, where a trailing `*` matches any suffix
+    pub tracking_params: Vec<String>,
+    /// Order the query parameters by name
+    pub sort_query_params: bool,
+    /// Remove the trailing slash of any path other than `/`
+    pub strip_trailing_slash: bool,
+    /// Record a page under the URL of its `<link rel="canonical">` or `Link` header
+    pub honor_canonical_link: bool,
+}
+
+/// Type an extracted field value is converted to
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    #[default]
+    Text,
+    Integer,
+    Number,
+    /// A calendar date, stored as `YYYY-MM-DD`
+    Date,
+    /// A URL, resolved against the page's base URL
+    Url,
+}
+
+/// A rule extracting one field of a page into its metadata
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExtractionRule {
+    /// Metadata key the value is written to
+    pub field: String,
+    /// CSS selector of the elements holding the value. Without one, the regex runs on the text of the whole page or document
+    #[serde(default)]
+    pub selector: Option<String>,
+    /// Attribute to read instead of the element's text
+    #[serde(default)]
+    pub attribute: Option<String>,
+    /// Regex applied to the value, keeping its first capture group or else the whole match
+    #[serde(default)]
+    pub regex: Option<String>,
+    #[serde(default, rename = "type")]
+    pub field_type: FieldType,
+    /// Collect every match into an array instead of keeping the first
+    #[serde(default)]
+    pub multiple: bool,
+}
+
+/// How long a config's crawls are kept before the retention janitor deletes them.
+///
+/// A crawl is kept while any rule keeps it, and crawls of unfinished jobs are
+/// always kept. With neither `keep_last_jobs` nor `keep_versions_days` set,
+/// nothing is deleted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Keep every crawl of the config's last N finished jobs
+    pub keep_last_jobs: Option<i64>,
+    /// Keep crawls made in the last N days
+    pub keep_versions_days: Option<i32>,
+    /// Keep each crawl that found new content for its URL, so the history of every URL stays complete
+    pub keep_changed_versions: bool,
+}
+
+impl RetentionPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.keep_last_jobs.is_some() || self.keep_versions_days.is_some()
+    }
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_last_jobs: None,
+            keep_versions_days: None,
+            keep_changed_versions: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScraperConfig {
     pub id: Uuid,
@@ -21,6 +158,12 @@ pub struct ScraperConfig {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub active: bool,
+    /// Queue the config's jobs are sent to, or the default queue if not set
+    pub queue_name: Option<String>,
+    pub crawl_mode: CrawlMode,
+    pub canonicalization: Json<CanonicalizationRules>,
+    pub extraction_rules: Json<Vec<ExtractionRule>>,
+    pub retention: Json<RetentionPolicy>,
 }
 
 impl ScraperConfig {
@@ -50,6 +193,11 @@ impl ScraperConfig {
             created_at: now,
             updated_at: now,
             active: true,
+            queue_name: None,
+            crawl_mode: CrawlMode::Links,
+            canonicalization: Json(CanonicalizationRules::default()),
+            extraction_rules: Json(Vec::new()),
+            retention: Json(RetentionPolicy::default()),
         }
     }
 } 
\ No newline at end of file
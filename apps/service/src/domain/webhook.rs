@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use uuid::Uuid;
 use sqlx::{FromRow, postgres::PgRow, Row};
 
@@ -13,6 +14,8 @@ pub enum WebhookEventType {
     PageCrawled,
     PageFailed,
     ContentChanged,
+    /// Synthetic event sent on demand to test a receiver
+    Ping,
 }
 
 impl ToString for WebhookEventType {
@@ -26,6 +29,26 @@ impl ToString for WebhookEventType {
             WebhookEventType::PageCrawled => "page.crawled".to_string(),
             WebhookEventType::PageFailed => "page.failed".to_string(),
             WebhookEventType::ContentChanged => "content.changed".to_string(),
+            WebhookEventType::Ping => "ping".to_string(),
+        }
+    }
+}
+
+impl FromStr for WebhookEventType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "job.created" => Ok(WebhookEventType::JobCreated),
+            "job.started" => Ok(WebhookEventType::JobStarted),
+            "job.completed" => Ok(WebhookEventType::JobCompleted),
+            "job.failed" => Ok(WebhookEventType::JobFailed),
+            "job.cancelled" => Ok(WebhookEventType::JobCancelled),
+            "page.crawled" => Ok(WebhookEventType::PageCrawled),
+            "page.failed" => Ok(WebhookEventType::PageFailed),
+            "content.changed" => Ok(WebhookEventType::ContentChanged),
+            "ping" => Ok(WebhookEventType::Ping),
+            _ => Err(format!("Unknown webhook event type: {}", s)),
         }
     }
 }
@@ -47,13 +70,32 @@ impl ToString for WebhookDeliveryStatus {
     }
 }
 
+impl FromStr for WebhookDeliveryStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(WebhookDeliveryStatus::Pending),
+            "delivered" => Ok(WebhookDeliveryStatus::Delivered),
+            "failed" => Ok(WebhookDeliveryStatus::Failed),
+            _ => Err(format!("Unknown webhook delivery status: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Webhook {
     pub id: Uuid,
     pub name: String,
     pub url: String,
     pub event_types: Vec<WebhookEventType>,
+    /// Key signing deliveries, only returned when the webhook is created or its secret is rotated
+    #[serde(skip_serializing)]
     pub secret: Option<String>,
+    /// Secret that was replaced by the last rotation, still used for signing until it expires
+    #[serde(skip_serializing)]
+    pub previous_secret: Option<String>,
+    pub previous_secret_expires_at: Option<DateTime<Utc>>,
     pub active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -62,12 +104,13 @@ pub struct Webhook {
 
 impl<'r> FromRow<'r, PgRow> for Webhook {
     fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
-        let event_types_json: serde_json::Value = row.try_get("event_types")?;
-        let event_types: Vec<WebhookEventType> = serde_json::from_value(event_types_json)
-            .map_err(|e| sqlx::Error::ColumnDecode {
-                index: "event_types".to_string(),
-                source: Box::new(e),
-            })?;
+        // event_types is stored as TEXT[] using the dotted event names; unknown
+        // names are skipped rather than failing the whole row
+        let event_types_raw: Vec<String> = row.try_get("event_types")?;
+        let event_types: Vec<WebhookEventType> = event_types_raw
+            .iter()
+            .filter_map(|s| s.parse().ok())
+            .collect();
 
         Ok(Self {
             id: row.try_get("id")?,
@@ -75,6 +118,8 @@ impl<'r> FromRow<'r, PgRow> for Webhook {
             url: row.try_get("url")?,
             event_types,
             secret: row.try_get("secret")?,
+            previous_secret: row.try_get("previous_secret")?,
+            previous_secret_expires_at: row.try_get("previous_secret_expires_at")?,
             active: row.try_get("active")?,
             created_at: row.try_get("created_at")?,
             updated_at: row.try_get("updated_at")?,
@@ -92,6 +137,8 @@ impl Webhook {
             url,
             event_types,
             secret: None,
+            previous_secret: None,
+            previous_secret_expires_at: None,
             active: true,
             created_at: now,
             updated_at: now,
@@ -113,6 +160,35 @@ impl Webhook {
     pub fn is_subscribed_to(&self, event_type: &WebhookEventType) -> bool {
         self.event_types.contains(event_type)
     }
+
+    /// Replace the secret, keeping the old one valid for signing during the grace period
+    pub fn rotate_secret(&mut self, new_secret: String, grace_period_seconds: i64) {
+        let now = Utc::now();
+        self.previous_secret = self.secret.take();
+        self.previous_secret_expires_at = self
+            .previous_secret
+            .as_ref()
+            .map(|_| now + chrono::Duration::seconds(grace_period_seconds));
+        self.secret = Some(new_secret);
+        self.updated_at = now;
+    }
+
+    /// Secrets that outgoing deliveries should currently be signed with, newest first
+    pub fn signing_secrets(&self) -> Vec<&str> {
+        let mut secrets = Vec::new();
+
+        if let Some(secret) = &self.secret {
+            secrets.push(secret.as_str());
+        }
+
+        if let (Some(previous), Some(expires_at)) = (&self.previous_secret, self.previous_secret_expires_at) {
+            if expires_at > Utc::now() {
+                secrets.push(previous.as_str());
+            }
+        }
+
+        secrets
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -132,7 +208,40 @@ pub struct WebhookDelivery {
     pub next_retry_at: Option<DateTime<Utc>>,
 }
 
+impl<'r> FromRow<'r, PgRow> for WebhookDelivery {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let event_type_raw: String = row.try_get("event_type")?;
+        let event_type = event_type_raw.parse().map_err(|e: String| sqlx::Error::ColumnDecode {
+            index: "event_type".to_string(),
+            source: e.into(),
+        })?;
+
+        let status_raw: String = row.try_get("status")?;
+        let status = status_raw.parse().map_err(|e: String| sqlx::Error::ColumnDecode {
+            index: "status".to_string(),
+            source: e.into(),
+        })?;
+
+        Ok(Self {
+            id: row.try_get("id")?,
+            webhook_id: row.try_get("webhook_id")?,
+            event_type,
+            payload: row.try_get("payload")?,
+            status,
+            response_status: row.try_get("response_status")?,
+            response_body: row.try_get("response_body")?,
+            error_message: row.try_get("error_message")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+            delivered_at: row.try_get("delivered_at")?,
+            retry_count: row.try_get("retry_count")?,
+            next_retry_at: row.try_get("next_retry_at")?,
+        })
+    }
+}
+
 impl WebhookDelivery {
+    /// Create a pending delivery that is due immediately
     pub fn new(webhook_id: Uuid, event_type: WebhookEventType, payload: serde_json::Value) -> Self {
         let now = Utc::now();
         Self {
@@ -148,7 +257,7 @@ impl WebhookDelivery {
             updated_at: now,
             delivered_at: None,
             retry_count: 0,
-            next_retry_at: None,
+            next_retry_at: Some(now),
         }
     }
 
@@ -158,6 +267,7 @@ impl WebhookDelivery {
         self.response_status = Some(response_status);
         self.response_body = response_body;
         self.delivered_at = Some(now);
+        self.next_retry_at = None;
         self.updated_at = now;
     }
 
@@ -168,8 +278,7 @@ impl WebhookDelivery {
         self.retry_count += 1;
         self.updated_at = now;
 
-        if let Some(seconds) = retry_after_seconds {
-            self.next_retry_at = Some(now + chrono::Duration::seconds(seconds));
-        }
+        // Without a retry delay the delivery is given up on
+        self.next_retry_at = retry_after_seconds.map(|seconds| now + chrono::Duration::seconds(seconds));
     }
 } 
\ No newline at end of file
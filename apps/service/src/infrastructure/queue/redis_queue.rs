@@ -1,30 +1,44 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use deadpool_redis::{Config, Pool, Runtime};
 use redis::AsyncCommands;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::time::Duration;
 use uuid::Uuid;
 use tracing::{debug, info, error, warn};
 
 use crate::config::settings::Redis as RedisConfig;
+use crate::domain::job::JobPriority;
 use crate::utils::error::AppError;
 
 const DEFAULT_VISIBILITY_TIMEOUT: u64 = 300; // 5 minutes
-const DEFAULT_POLL_INTERVAL: u64 = 5; // 5 seconds
+/// Set of every queue name jobs were ever sent to, used by the promoter and the reaper
+const QUEUES_KEY: &str = "queues";
 
 #[async_trait]
 pub trait JobQueue {
     async fn enqueue<T: Serialize + Send + Sync>(&self, queue: &str, job: &T) -> Result<String>;
     async fn dequeue<T: DeserializeOwned + Send + Sync>(&self, queue: &str) -> Result<Option<(String, T)>>;
     async fn complete(&self, queue: &str, job_id: &str) -> Result<()>;
-    async fn fail(&self, queue: &str, job_id: &str, error: &str) -> Result<()>;
+    async fn fail(&self, queue: &str, job_id: &str, error: &str, attempts: i32) -> Result<()>;
     async fn schedule<T: Serialize + Send + Sync>(&self, queue: &str, job: &T, delay_seconds: u64) -> Result<String>;
 }
 
+/// A job that exhausted its attempts, as stored in the `failed:{queue}` dead-letter list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterEntry {
+    pub job_id: String,
+    pub queue: String,
+    pub error: String,
+    pub attempts: i32,
+    pub failed_at: DateTime<Utc>,
+}
+
 pub struct RedisJobQueue {
     pool: Pool,
     visibility_timeout: u64,
+    default_queue: String,
 }
 
 impl RedisJobQueue {
@@ -34,83 +48,345 @@ impl RedisJobQueue {
         
         // Test connection
         let mut conn = pool.get().await.map_err(|e| AppError::Redis(e.to_string()))?;
-        redis::cmd("PING").query_async(&mut conn).await.map_err(|e| AppError::Redis(e.to_string()))?;
+        redis::cmd("PING").query_async::<()>(&mut conn).await.map_err(|e| AppError::Redis(e.to_string()))?;
         
         Ok(Self {
             pool,
             visibility_timeout: DEFAULT_VISIBILITY_TIMEOUT,
+            default_queue: config.job_queue_name.clone(),
         })
     }
     
+    /// A queue whose connections are only opened when it is first used
+    #[cfg(test)]
+    pub fn unconnected(url: &str, default_queue: &str) -> Self {
+        Self {
+            pool: Config::from_url(url).create_pool(Some(Runtime::Tokio1)).unwrap(),
+            visibility_timeout: DEFAULT_VISIBILITY_TIMEOUT,
+            default_queue: default_queue.to_string(),
+        }
+    }
+    
     pub fn with_visibility_timeout(mut self, seconds: u64) -> Self {
         self.visibility_timeout = seconds;
         self
     }
     
-    pub async fn get_connection(&self) -> Result<deadpool_redis::Connection> {
-        self.pool.get().await.map_err(|e| AppError::Redis(e.to_string()).into())
+    /// Queue used for jobs of configs that don't name their own
+    pub fn default_queue(&self) -> &str {
+        &self.default_queue
     }
-}
-
-#[async_trait]
-impl JobQueue for RedisJobQueue {
-    async fn enqueue<T: Serialize + Send + Sync>(&self, queue: &str, job: &T) -> Result<String> {
+    
+    /// Every queue jobs were sent to, including the default queue
+    pub async fn known_queues(&self) -> Result<Vec<String>> {
+        let mut conn = self.get_connection().await?;
+        
+        let mut queues: Vec<String> = conn.smembers(QUEUES_KEY).await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        
+        if !queues.contains(&self.default_queue) {
+            queues.push(self.default_queue.clone());
+        }
+        queues.sort();
+        
+        Ok(queues)
+    }
+    
+    /// Add a job to the list of its priority; workers take higher priorities first
+    pub async fn enqueue_with_priority<T: Serialize + Send + Sync>(&self, queue: &str, job: &T, priority: JobPriority) -> Result<String> {
+        let job_id = Uuid::new_v4().to_string();
+        let job_data = serde_json::to_string(job)?;
+        
+        let mut conn = self.get_connection().await?;
+        
+        // Add to the queue and register the queue name
+        let mut pipe = redis::pipe();
+        pipe.lpush(queue_key(queue, priority), &job_data).ignore()
+            .sadd(QUEUES_KEY, queue).ignore();
+        
+        pipe.query_async::<()>(&mut conn).await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        
+        Ok(job_id)
+    }
+    
+    /// Schedule a job to be moved into the list of its priority after `delay_seconds`
+    pub async fn schedule_with_priority<T: Serialize + Send + Sync>(&self, queue: &str, job: &T, delay_seconds: u64, priority: JobPriority) -> Result<String> {
         let job_id = Uuid::new_v4().to_string();
         let job_data = serde_json::to_string(job)?;
         
         let mut conn = self.get_connection().await?;
         
-        // Add to the queue
-        conn.lpush(format!("queue:{}", queue), &job_data).await
+        // Calculate the execution time
+        let execute_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() + delay_seconds;
+        
+        // Add to the sorted set with score as execution time and register the queue name
+        let mut pipe = redis::pipe();
+        pipe.zadd(scheduled_key(queue, priority), job_data, execute_at as f64).ignore()
+            .sadd(QUEUES_KEY, queue).ignore();
+        
+        pipe.query_async::<()>(&mut conn).await
             .map_err(|e| AppError::Redis(e.to_string()))?;
         
         Ok(job_id)
     }
     
+    /// Seconds a dequeued job stays claimed without a heartbeat
+    pub fn visibility_timeout(&self) -> u64 {
+        self.visibility_timeout
+    }
+    
+    pub async fn get_connection(&self) -> Result<deadpool_redis::Connection> {
+        self.pool.get().await.map_err(|e| AppError::Redis(e.to_string()).into())
+    }
+    
+    /// Push the visibility timeout of a dequeued job forward, returning false if it already expired
+    pub async fn extend_visibility(&self, queue: &str, job_id: &str) -> Result<bool> {
+        let mut conn = self.get_connection().await?;
+        
+        let extended: bool = conn.expire(format!("job:{}:{}", queue, job_id), self.visibility_timeout as i64).await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        
+        Ok(extended)
+    }
+    
+    /// Move scheduled jobs whose execution time has passed into the live queue of their priority.
+    ///
+    /// Runs as a Lua script so an entry is never promoted twice or lost when
+    /// several promoters run at the same time. At most `limit` jobs are moved
+    /// per priority. Returns the number of jobs moved.
+    pub async fn promote_due(&self, queue: &str, limit: usize) -> Result<usize> {
+        let mut conn = self.get_connection().await?;
+        
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        
+        // KEYS holds (scheduled set, live list) pairs, one per priority
+        let script = redis::Script::new(r#"
+            local promoted = 0
+            for i = 1, #KEYS, 2 do
+                local due = redis.call('ZRANGEBYSCORE', KEYS[i], '-inf', ARGV[1], 'LIMIT', 0, ARGV[2])
+                for _, job_data in ipairs(due) do
+                    redis.call('ZREM', KEYS[i], job_data)
+                    redis.call('LPUSH', KEYS[i + 1], job_data)
+                end
+                promoted = promoted + #due
+            end
+            return promoted
+        "#);
+        
+        let mut invocation = script.prepare_invoke();
+        for priority in JobPriority::DESCENDING {
+            invocation.key(scheduled_key(queue, priority)).key(queue_key(queue, priority));
+        }
+        
+        let promoted: usize = invocation
+            .arg(now)
+            .arg(limit)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        
+        Ok(promoted)
+    }
+    
+    /// Number of entries in the dead-letter list
+    pub async fn dead_letter_count(&self, queue: &str) -> Result<usize> {
+        let mut conn = self.get_connection().await?;
+        
+        let count: usize = conn.llen(format!("failed:{}", queue)).await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        
+        Ok(count)
+    }
+    
+    /// Dead-letter entries, most recent first
+    pub async fn list_dead_letters(&self, queue: &str, offset: usize, limit: usize) -> Result<Vec<DeadLetterEntry>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        
+        let mut conn = self.get_connection().await?;
+        
+        let items: Vec<String> = conn.lrange(format!("failed:{}", queue), offset as isize, (offset + limit - 1) as isize).await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        
+        Ok(items.iter().filter_map(|item| parse_dead_letter(item)).collect())
+    }
+    
+    /// Find the dead-letter entry of a job
+    pub async fn get_dead_letter(&self, queue: &str, job_id: &str) -> Result<Option<DeadLetterEntry>> {
+        Ok(self.find_dead_letter(queue, job_id).await?.map(|(_, entry)| entry))
+    }
+    
+    /// Remove the dead-letter entry of a job, returning false if there is none
+    pub async fn remove_dead_letter(&self, queue: &str, job_id: &str) -> Result<bool> {
+        let Some((raw, _)) = self.find_dead_letter(queue, job_id).await? else {
+            return Ok(false);
+        };
+        
+        let mut conn = self.get_connection().await?;
+        let removed: i32 = conn.lrem(format!("failed:{}", queue), 1, &raw).await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        
+        Ok(removed > 0)
+    }
+    
+    async fn find_dead_letter(&self, queue: &str, job_id: &str) -> Result<Option<(String, DeadLetterEntry)>> {
+        let mut conn = self.get_connection().await?;
+        
+        let items: Vec<String> = conn.lrange(format!("failed:{}", queue), 0, -1).await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        
+        Ok(items
+            .into_iter()
+            .filter_map(|item| parse_dead_letter(&item).map(|entry| (item, entry)))
+            .find(|(_, entry)| entry.job_id == job_id))
+    }
+    
+    /// Entries of the processing list whose visibility timeout has expired, as `(job_id, job_data)` pairs
+    pub async fn find_expired(&self, queue: &str) -> Result<Vec<(String, String)>> {
+        let mut conn = self.get_connection().await?;
+        
+        let items: Vec<String> = conn.lrange(format!("processing:{}", queue), 0, -1).await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        
+        let mut expired = Vec::new();
+        for job_data in items {
+            let job_id = parse_job_id(&job_data);
+            let exists: bool = conn.exists(format!("job:{}:{}", queue, job_id)).await
+                .map_err(|e| AppError::Redis(e.to_string()))?;
+            
+            if !exists {
+                expired.push((job_id, job_data));
+            }
+        }
+        
+        Ok(expired)
+    }
+    
+    /// Remove an expired entry from the processing list.
+    ///
+    /// Returns false if the job was claimed again in the meantime or another
+    /// reaper already removed the entry, so only one caller recovers the job.
+    pub async fn reclaim(&self, queue: &str, job_id: &str, job_data: &str) -> Result<bool> {
+        let mut conn = self.get_connection().await?;
+        
+        let script = redis::Script::new(r#"
+            if redis.call('EXISTS', KEYS[2]) == 1 then
+                return 0
+            end
+            return redis.call('LREM', KEYS[1], 1, ARGV[1])
+        "#);
+        
+        let removed: i32 = script
+            .key(format!("processing:{}", queue))
+            .key(format!("job:{}:{}", queue, job_id))
+            .arg(job_data)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        
+        Ok(removed > 0)
+    }
+}
+
+/// List holding the jobs of a queue with the given priority.
+///
+/// Normal priority uses the plain `queue:{queue}` key so entries queued before
+/// priorities existed are still picked up.
+fn queue_key(queue: &str, priority: JobPriority) -> String {
+    match priority {
+        JobPriority::Normal => format!("queue:{}", queue),
+        _ => format!("queue:{}:{}", queue, priority.to_string()),
+    }
+}
+
+/// Sorted set holding the scheduled jobs of a queue with the given priority
+fn scheduled_key(queue: &str, priority: JobPriority) -> String {
+    match priority {
+        JobPriority::Normal => format!("scheduled:{}", queue),
+        _ => format!("scheduled:{}:{}", queue, priority.to_string()),
+    }
+}
+
+fn parse_dead_letter(item: &str) -> Option<DeadLetterEntry> {
+    match serde_json::from_str(item) {
+        Ok(entry) => Some(entry),
+        Err(e) => {
+            warn!("Skipping malformed dead-letter entry: {}", e);
+            None
+        }
+    }
+}
+
+/// Extract the job ID from a queue entry.
+///
+/// The job data might be a JSON string, a JSON object with a `job_id` field
+/// or just a UUID string.
+fn parse_job_id(job_data: &str) -> String {
+    if job_data.starts_with('"') && job_data.ends_with('"') && job_data.len() >= 2 {
+        // It's a quoted string, remove the quotes
+        job_data[1..job_data.len()-1].to_string()
+    } else if job_data.starts_with('{') {
+        // It's a JSON object
+        match serde_json::from_str::<serde_json::Value>(job_data) {
+            Ok(json) => {
+                if let Some(id) = json.get("job_id").and_then(|id| id.as_str()) {
+                    id.to_string()
+                } else {
+                    // Fallback to using the job data as the ID
+                    job_data.to_string()
+                }
+            },
+            Err(_) => {
+                // Fallback to using the job data as the ID
+                job_data.to_string()
+            }
+        }
+    } else {
+        // Use the job data as the ID
+        job_data.to_string()
+    }
+}
+
+#[async_trait]
+impl JobQueue for RedisJobQueue {
+    async fn enqueue<T: Serialize + Send + Sync>(&self, queue: &str, job: &T) -> Result<String> {
+        self.enqueue_with_priority(queue, job, JobPriority::Normal).await
+    }
+    
     async fn dequeue<T: DeserializeOwned + Send + Sync>(&self, queue: &str) -> Result<Option<(String, T)>> {
         let mut conn = self.get_connection().await?;
-        let queue_key = format!("queue:{}", queue);
         let processing_key = format!("processing:{}", queue);
         
-        // Use BRPOPLPUSH to atomically move a job from the queue to a processing list
+        // Use RPOPLPUSH to atomically move a job from the queue to a processing list,
+        // trying the lists from the highest priority down
         // This ensures that jobs are not lost if the worker crashes
-        let job_data: Option<String> = conn.brpoplpush(&queue_key, &processing_key, DEFAULT_POLL_INTERVAL as f64).await
-            .map_err(|e| AppError::Redis(e.to_string()))?;
+        let mut job_data: Option<String> = None;
+        for priority in JobPriority::DESCENDING {
+            job_data = conn.rpoplpush(queue_key(queue, priority), &processing_key).await
+                .map_err(|e| AppError::Redis(e.to_string()))?;
+            
+            if job_data.is_some() {
+                break;
+            }
+        }
         
         if let Some(job_data) = job_data {
             debug!("Dequeued job data: {}", job_data);
             
             // Parse the job data to extract the job ID
-            // The job data might be a JSON string or just a UUID string
-            let job_id = if job_data.starts_with('"') && job_data.ends_with('"') {
-                // It's a quoted string, remove the quotes
-                job_data[1..job_data.len()-1].to_string()
-            } else if job_data.starts_with('{') {
-                // It's a JSON object
-                match serde_json::from_str::<serde_json::Value>(&job_data) {
-                    Ok(json) => {
-                        if let Some(id) = json.get("job_id").and_then(|id| id.as_str()) {
-                            id.to_string()
-                        } else {
-                            // Fallback to using the job data as the ID
-                            job_data.clone()
-                        }
-                    },
-                    Err(_) => {
-                        // Fallback to using the job data as the ID
-                        job_data.clone()
-                    }
-                }
-            } else {
-                // Use the job data as the ID
-                job_data.clone()
-            };
+            let job_id = parse_job_id(&job_data);
             
             debug!("Using job ID: {}", job_id);
             
             // Set the job data with an expiration time
             let job_key = format!("job:{}:{}", queue, job_id);
-            conn.set_ex(&job_key, &job_data, self.visibility_timeout as u64)
+            conn.set_ex::<_, _, ()>(&job_key, &job_data, self.visibility_timeout)
                 .await.map_err(|e| AppError::Redis(e.to_string()))?;
             
             // Deserialize the job data
@@ -154,59 +430,57 @@ impl JobQueue for RedisJobQueue {
             debug!("Job {} is in processing list: {}", job_id, job_in_list);
         }
         
-        // Use a pipeline to execute both commands atomically
+        // Entries are stored as enqueued, which for job IDs is a JSON string, so remove either form.
+        // Only one entry is removed in case the job was recovered and is queued again.
+        let quoted_job_id = serde_json::to_string(job_id)?;
+        
+        // Use a pipeline to execute the commands atomically
         let mut pipe = redis::pipe();
-        pipe.lrem(&processing_key, 0, job_id).del(&job_key);
+        pipe.atomic()
+            .lrem(&processing_key, 1, job_id)
+            .lrem(&processing_key, 1, &quoted_job_id)
+            .del(&job_key);
         
-        let result: (i32, i32) = pipe.query_async(&mut conn).await
+        let result: (i32, i32, i32) = pipe.query_async(&mut conn).await
             .map_err(|e| AppError::Redis(format!("Failed to execute pipeline: {}", e)))?;
         
-        debug!("Complete job result: removed {} items from processing list, deleted {} job keys", result.0, result.1);
+        debug!("Complete job result: removed {} items from processing list, deleted {} job keys", result.0 + result.1, result.2);
         debug!("Completed job {} in queue {}", job_id, queue);
         
         Ok(())
     }
     
-    async fn fail(&self, queue: &str, job_id: &str, error: &str) -> Result<()> {
+    async fn fail(&self, queue: &str, job_id: &str, error: &str, attempts: i32) -> Result<()> {
         let mut conn = self.get_connection().await?;
         
-        // Get the job data
-        let job_data: Option<String> = conn.get(format!("job:{}:{}", queue, job_id)).await
-            .map_err(|e| AppError::Redis(e.to_string()))?;
+        let entry = DeadLetterEntry {
+            job_id: job_id.to_string(),
+            queue: queue.to_string(),
+            error: error.to_string(),
+            attempts,
+            failed_at: Utc::now(),
+        };
+        let failed_data = serde_json::to_string(&entry)?;
         
-        if let Some(job_data) = job_data {
-            // Move to failed queue with error information
-            let failed_data = format!("{{\"job\":{},\"error\":\"{}\"}}", job_data, error.replace("\"", "\\\""));
-            
-            conn.lpush(format!("failed:{}", queue), failed_data).await
-                .map_err(|e| AppError::Redis(e.to_string()))?;
-            
-            // Remove from processing
-            conn.del(format!("job:{}:{}", queue, job_id)).await
-                .map_err(|e| AppError::Redis(e.to_string()))?;
-        }
+        let processing_key = format!("processing:{}", queue);
+        let quoted_job_id = serde_json::to_string(job_id)?;
+        
+        // Move the job from processing to the dead-letter list
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .lrem(&processing_key, 1, job_id).ignore()
+            .lrem(&processing_key, 1, &quoted_job_id).ignore()
+            .del(format!("job:{}:{}", queue, job_id)).ignore()
+            .lpush(format!("failed:{}", queue), &failed_data).ignore();
+        
+        pipe.query_async::<()>(&mut conn).await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
         
+        warn!("Moved job {} to the dead-letter list of queue {} after {} attempts", job_id, queue, attempts);
         Ok(())
     }
     
     async fn schedule<T: Serialize + Send + Sync>(&self, queue: &str, job: &T, delay_seconds: u64) -> Result<String> {
-        let job_id = Uuid::new_v4().to_string();
-        let job_data = serde_json::to_string(job)?;
-        
-        let mut conn = self.get_connection().await?;
-        
-        // Calculate the execution time
-        let execute_at = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() + delay_seconds;
-        
-        // Add to the sorted set with score as execution time
-        conn.zadd(
-            format!("scheduled:{}", queue),
-            job_data,
-            execute_at as f64
-        ).await.map_err(|e| AppError::Redis(e.to_string()))?;
-        
-        Ok(job_id)
+        self.schedule_with_priority(queue, job, delay_seconds, JobPriority::Normal).await
     }
 } 
\ No newline at end of file
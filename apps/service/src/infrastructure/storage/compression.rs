@@ -0,0 +1,64 @@
+use anyhow::Result;
+use async_compression::tokio::bufread::{GzipDecoder, GzipEncoder, ZstdDecoder, ZstdEncoder};
+use futures::StreamExt;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+use crate::infrastructure::storage::ObjectStream;
+use crate::utils::error::AppError;
+
+/// Compression of a stored object, recorded as its `Content-Encoding`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Gzip,
+    Zstd,
+}
+
+impl ContentEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Zstd => "zstd",
+        }
+    }
+
+    /// The encoding named by a `Content-Encoding` value, where no value or `identity` means the object is stored as is
+    pub fn from_header(value: Option<&str>) -> Result<Option<Self>> {
+        match value.map(|value| value.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("") | Some("identity") => Ok(None),
+            Some("gzip") | Some("x-gzip") => Ok(Some(ContentEncoding::Gzip)),
+            Some("zstd") => Ok(Some(ContentEncoding::Zstd)),
+            Some(other) => Err(AppError::Storage(format!("Unsupported content encoding: {}", other)).into()),
+        }
+    }
+}
+
+/// Whether content of a type is worth compressing; PDFs and Office Open XML files are compressed already
+pub fn is_compressible(content_type: &str) -> bool {
+    let mime_type = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+
+    mime_type.starts_with("text/")
+        || matches!(
+            mime_type.as_str(),
+            "application/json" | "application/xml" | "application/xhtml+xml" | "application/rtf" | "application/msword"
+        )
+}
+
+/// Compress a body as it is read
+pub fn encode(body: ObjectStream, encoding: ContentEncoding) -> ObjectStream {
+    let reader = StreamReader::new(body);
+
+    match encoding {
+        ContentEncoding::Gzip => ReaderStream::new(GzipEncoder::new(reader)).boxed(),
+        ContentEncoding::Zstd => ReaderStream::new(ZstdEncoder::new(reader)).boxed(),
+    }
+}
+
+/// Decompress a body as it is read
+pub fn decode(body: ObjectStream, encoding: ContentEncoding) -> ObjectStream {
+    let reader = StreamReader::new(body);
+
+    match encoding {
+        ContentEncoding::Gzip => ReaderStream::new(GzipDecoder::new(reader)).boxed(),
+        ContentEncoding::Zstd => ReaderStream::new(ZstdDecoder::new(reader)).boxed(),
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
+use std::path::{Component, Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::config::settings::{Storage as StorageConfig, StorageCompression};
+use crate::infrastructure::storage::compression::{self, ContentEncoding};
+use crate::infrastructure::storage::{compression_for, ObjectStream, StorageClient, StoredObject};
+use crate::utils::error::AppError;
+
+/// Suffix of the file recording the compression of an object
+const ENCODING_SUFFIX: &str = ".encoding";
+
+/// Stores objects as files below a root directory, using the same paths as the S3 backend would as keys
+pub struct FsStorageClient {
+    root: PathBuf,
+    compression: StorageCompression,
+}
+
+impl FsStorageClient {
+    pub async fn new(config: &StorageConfig) -> Result<Self> {
+        let root = PathBuf::from(&config.path);
+
+        fs::create_dir_all(&root)
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to create storage directory {}: {}", root.display(), e)))?;
+
+        Ok(Self {
+            root,
+            compression: config.compression,
+        })
+    }
+
+    /// The file of an object, refusing paths that would leave the root directory or clash with the file recording a compression
+    fn file_path(&self, path: &str) -> Result<PathBuf> {
+        let relative = Path::new(path);
+
+        if path.is_empty()
+            || path.ends_with(ENCODING_SUFFIX)
+            || !relative.components().all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(AppError::Storage(format!("Invalid object path: {}", path)).into());
+        }
+
+        Ok(self.root.join(relative))
+    }
+
+    /// Write an object through a temporary file, so a reader never sees it half written
+    async fn write(&self, path: &str, body: ObjectStream, encoding: Option<ContentEncoding>) -> Result<()> {
+        let file_path = self.file_path(path)?;
+
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let temp_path = file_path.with_extension(format!("tmp-{}", Uuid::new_v4()));
+
+        if let Err(e) = write_file(&temp_path, body).await {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err(e);
+        }
+
+        // Record the compression before the object appears, so it is never read without it
+        let encoding_path = encoding_path(&file_path);
+        let recorded = match encoding {
+            Some(encoding) => fs::write(&encoding_path, encoding.as_str()).await,
+            None => remove_if_exists(&encoding_path).await,
+        };
+
+        if let Err(e) = recorded {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err(e.into());
+        }
+
+        if let Err(e) = fs::rename(&temp_path, &file_path).await {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+}
+
+/// The file next to an object recording its compression, as files have no metadata to keep it in
+fn encoding_path(file_path: &Path) -> PathBuf {
+    let mut path = file_path.as_os_str().to_owned();
+    path.push(ENCODING_SUFFIX);
+    PathBuf::from(path)
+}
+
+async fn write_file(path: &Path, mut body: ObjectStream) -> Result<()> {
+    let mut file = fs::File::create(path).await?;
+
+    while let Some(chunk) = body.try_next().await? {
+        file.write_all(&chunk).await?;
+    }
+
+    file.flush().await?;
+    Ok(())
+}
+
+async fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[async_trait]
+impl StorageClient for FsStorageClient {
+    async fn put_object_stream(&self, path: &str, body: ObjectStream, content_type: &str) -> Result<()> {
+        debug!("Attempting to write {} to {}, path: {}", content_type, self.root.display(), path);
+
+        let encoding = compression_for(self.compression, content_type);
+        let body = match encoding {
+            Some(encoding) => compression::encode(body, encoding),
+            None => body,
+        };
+
+        match self.write(path, body, encoding).await {
+            Ok(()) => {
+                debug!("Successfully wrote to {}, path: {}", self.root.display(), path);
+                Ok(())
+            },
+            Err(e) => {
+                error!("Failed to write {} to {}: {:?}", content_type, self.root.display(), e);
+                Err(AppError::Storage(format!("Failed to upload {}: {}", path, e)).into())
+            }
+        }
+    }
+
+    async fn get_object_stream(&self, path: &str) -> Result<StoredObject> {
+        let file_path = self.file_path(path)?;
+
+        let file = fs::File::open(&file_path)
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to get object: {}", e)))?;
+
+        let content_encoding = match fs::read_to_string(encoding_path(&file_path)).await {
+            Ok(encoding) => ContentEncoding::from_header(Some(&encoding))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(AppError::Storage(format!("Failed to get object encoding: {}", e)).into()),
+        };
+
+        Ok(StoredObject {
+            body: ReaderStream::new(file).boxed(),
+            content_encoding,
+        })
+    }
+
+    async fn delete_object(&self, path: &str) -> Result<()> {
+        let file_path = self.file_path(path)?;
+
+        // Deleting an object that is already gone succeeds, as it does on S3
+        remove_if_exists(&file_path)
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to delete object: {}", e)))?;
+
+        remove_if_exists(&encoding_path(&file_path))
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to delete object encoding: {}", e)))?;
+
+        Ok(())
+    }
+}
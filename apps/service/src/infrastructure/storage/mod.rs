@@ -1 +1,104 @@
-pub mod s3_client; 
\ No newline at end of file
+pub mod compression;
+pub mod fs_client;
+pub mod s3_client;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use std::sync::Arc;
+
+use crate::config::settings::{Storage as StorageConfig, StorageBackend, StorageCompression};
+use crate::utils::error::AppError;
+use compression::ContentEncoding;
+use fs_client::FsStorageClient;
+use s3_client::S3StorageClient;
+
+/// Body of an object, read or written a chunk at a time
+pub type ObjectStream = BoxStream<'static, std::io::Result<Bytes>>;
+
+/// An object being read, with its body as it is stored
+pub struct StoredObject {
+    pub body: ObjectStream,
+    /// Compression of the body, if any
+    pub content_encoding: Option<ContentEncoding>,
+}
+
+impl StoredObject {
+    /// The body with any compression undone
+    pub fn into_decoded(self) -> ObjectStream {
+        match self.content_encoding {
+            Some(encoding) => compression::decode(self.body, encoding),
+            None => self.body,
+        }
+    }
+}
+
+/// Object store holding crawled HTML, Markdown and documents
+#[async_trait]
+pub trait StorageClient: Send + Sync {
+    /// Store an object from a stream, replacing any object at the same path.
+    ///
+    /// The body is compressed with the configured compression if its content
+    /// type is worth compressing, and the compression is recorded as the
+    /// object's `Content-Encoding`.
+    async fn put_object_stream(&self, path: &str, body: ObjectStream, content_type: &str) -> Result<()>;
+
+    /// Open an object for reading, without undoing its compression
+    async fn get_object_stream(&self, path: &str) -> Result<StoredObject>;
+
+    async fn delete_object(&self, path: &str) -> Result<()>;
+
+    /// Store an object, replacing any object at the same path
+    async fn put_object(&self, path: &str, content: &[u8], content_type: &str) -> Result<()> {
+        let body = stream::once(futures::future::ready(Ok(Bytes::copy_from_slice(content)))).boxed();
+        self.put_object_stream(path, body, content_type).await
+    }
+
+    async fn get_object(&self, path: &str) -> Result<String> {
+        let bytes = self.get_object_bytes(path).await?;
+
+        let content = String::from_utf8(bytes)
+            .map_err(|e| AppError::Storage(format!("Failed to convert object to string: {}", e)))?;
+
+        Ok(content)
+    }
+
+    async fn get_object_bytes(&self, path: &str) -> Result<Vec<u8>> {
+        let mut body = self.get_object_stream(path).await?.into_decoded();
+        let mut content = Vec::new();
+
+        while let Some(chunk) = body
+            .try_next()
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to read object {}: {}", path, e)))?
+        {
+            content.extend_from_slice(&chunk);
+        }
+
+        Ok(content)
+    }
+}
+
+/// The compression to store content of the given type with, if any
+pub fn compression_for(compression: StorageCompression, content_type: &str) -> Option<ContentEncoding> {
+    if !compression::is_compressible(content_type) {
+        return None;
+    }
+
+    match compression {
+        StorageCompression::None => None,
+        StorageCompression::Gzip => Some(ContentEncoding::Gzip),
+        StorageCompression::Zstd => Some(ContentEncoding::Zstd),
+    }
+}
+
+/// Create the storage client of the configured backend
+pub async fn create_storage_client(config: &StorageConfig) -> Result<Arc<dyn StorageClient>> {
+    let client: Arc<dyn StorageClient> = match config.backend {
+        StorageBackend::S3 => Arc::new(S3StorageClient::new(config).await?),
+        StorageBackend::Fs => Arc::new(FsStorageClient::new(config).await?),
+    };
+
+    Ok(client)
+}
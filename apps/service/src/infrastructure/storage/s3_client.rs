@@ -2,60 +2,66 @@ use anyhow::Result;
 use async_trait::async_trait;
 use aws_config::Region;
 use aws_sdk_s3::{Client,  config::Credentials};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use bytes::{Bytes, BytesMut};
+use futures::stream::{self, StreamExt, TryStreamExt};
 use std::io::Cursor;
-use uuid::Uuid;
 use tracing::{debug, error};
-use md5;
 
-use crate::config::settings::Storage as StorageConfig;
+use crate::config::settings::{Storage as StorageConfig, StorageCompression};
+use crate::infrastructure::storage::compression::{self, ContentEncoding};
+use crate::infrastructure::storage::{compression_for, ObjectStream, StorageClient, StoredObject};
 use crate::utils::error::AppError;
 
-#[async_trait]
-pub trait StorageClient {
-    async fn upload_html(&self, job_id: &Uuid, url: &str, content: &str) -> Result<String>;
-    async fn upload_markdown(&self, job_id: &Uuid, url: &str, content: &str) -> Result<String>;
-    async fn get_object(&self, path: &str) -> Result<String>;
-    async fn delete_object(&self, path: &str) -> Result<()>;
-}
+/// Size of the parts of a multipart upload; S3 needs every part but the last to be at least 5 MiB
+const MULTIPART_PART_SIZE: usize = 8 * 1024 * 1024;
 
 pub struct S3StorageClient {
     client: Client,
     bucket: String,
+    compression: StorageCompression,
 }
 
 impl S3StorageClient {
     pub async fn new(config: &StorageConfig) -> Result<Self> {
+        let bucket = required_setting(&config.bucket, "bucket")?;
+        let endpoint = required_setting(&config.endpoint, "endpoint")?;
+
         // Set up credentials
         let credentials = Credentials::new(
-            &config.access_key,
-            &config.secret_key,
+            required_setting(&config.access_key, "access_key")?,
+            required_setting(&config.secret_key, "secret_key")?,
             None,
             None,
             "s3-credentials",
         );
 
         // Set up region directly
-        let region = Region::new(config.region.clone());
+        let region = Region::new(required_setting(&config.region, "region")?.to_string());
 
         // Build the S3 client configuration
         let s3_config = aws_sdk_s3::Config::builder()
             .region(region)
-            .endpoint_url(&config.endpoint)
+            .endpoint_url(endpoint)
             .credentials_provider(credentials)
             .build();
 
         // Create the client
         let client = Client::from_conf(s3_config);
 
-        // Verify bucket exists
-        let buckets = client.list_buckets().send().await?;
-        let bucket_exists = buckets.buckets().iter().any(|b| b.name() == Some(&config.bucket));
+        // Check the configured bucket only, which doesn't need permission to list every bucket
+        let bucket_exists = match client.head_bucket().bucket(bucket).send().await {
+            Ok(_) => true,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => false,
+            Err(e) => return Err(AppError::Storage(format!("Failed to access bucket {}: {}", bucket, e)).into()),
+        };
 
         if !bucket_exists {
             // Create the bucket if it doesn't exist
             client
                 .create_bucket()
-                .bucket(&config.bucket)
+                .bucket(bucket)
                 .send()
                 .await
                 .map_err(|e| AppError::Storage(format!("Failed to create bucket: {}", e)))?;
@@ -63,74 +69,63 @@ impl S3StorageClient {
 
         Ok(Self {
             client,
-            bucket: config.bucket.clone(),
+            bucket: bucket.to_string(),
+            compression: config.compression,
         })
     }
+}
 
-    fn generate_path(&self, job_id: &Uuid, url: &str, extension: &str) -> String {
-        // Create a hash of the URL to avoid path issues
-        let url_hash = format!("{:x}", md5::compute(url.as_bytes()));
-        
-        // Generate a path with job ID and URL hash
-        format!("{}/{}.{}", job_id, url_hash, extension)
-    }
+/// A setting the `s3` backend can't do without
+fn required_setting<'a>(value: &'a Option<String>, name: &str) -> Result<&'a str, AppError> {
+    value
+        .as_deref()
+        .ok_or_else(|| AppError::Storage(format!("storage.{} must be set for the s3 backend", name)))
 }
 
 #[async_trait]
 impl StorageClient for S3StorageClient {
-    async fn upload_html(&self, job_id: &Uuid, url: &str, content: &str) -> Result<String> {
-        let path = self.generate_path(job_id, url, "html");
-        
+    async fn put_object_stream(&self, path: &str, body: ObjectStream, content_type: &str) -> Result<()> {
         // Upload the content
-        debug!("Attempting to upload HTML to S3 bucket: {}, path: {}", self.bucket, path);
-        
-        // Create a simpler put_object request
-        let request = self.client
-            .put_object()
-            .bucket(&self.bucket)
-            .key(&path)
-            .body(content.as_bytes().to_vec().into())
-            .content_type("text/html");
-            
-        match request.send().await {
-            Ok(_) => {
-                debug!("Successfully uploaded HTML to S3 bucket: {}, path: {}", self.bucket, path);
-                Ok(path)
-            },
-            Err(e) => {
-                error!("Failed to upload HTML to S3: {:?}", e);
-                Err(AppError::Storage(format!("Failed to upload HTML: {}", e)).into())
-            }
-        }
-    }
+        debug!("Attempting to upload {} to S3 bucket: {}, path: {}", content_type, self.bucket, path);
 
-    async fn upload_markdown(&self, job_id: &Uuid, url: &str, content: &str) -> Result<String> {
-        let path = self.generate_path(job_id, url, "md");
-        
-        // Upload the content
-        debug!("Attempting to upload Markdown to S3 bucket: {}, path: {}", self.bucket, path);
-        
-        // Create a simpler put_object request
-        let request = self.client
-            .put_object()
-            .bucket(&self.bucket)
-            .key(&path)
-            .body(content.as_bytes().to_vec().into())
-            .content_type("text/markdown");
-            
-        match request.send().await {
-            Ok(_) => {
-                debug!("Successfully uploaded Markdown to S3 bucket: {}, path: {}", self.bucket, path);
-                Ok(path)
+        let encoding = compression_for(self.compression, content_type);
+        let mut body = match encoding {
+            Some(encoding) => compression::encode(body, encoding),
+            None => body,
+        };
+
+        // Content that fits in one part is uploaded in one request, anything larger as a multipart upload
+        let (first_part, finished) = read_part(&mut body).await?;
+
+        let result = if finished {
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(path)
+                .body(ByteStream::from(first_part))
+                .content_type(content_type)
+                .set_content_encoding(encoding.map(|encoding| encoding.as_str().to_string()))
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(|e| AppError::Storage(format!("Failed to upload {}: {}", path, e)).into())
+        } else {
+            self.upload_multipart(path, first_part, body, content_type, encoding).await
+        };
+
+        match result {
+            Ok(()) => {
+                debug!("Successfully uploaded to S3 bucket: {}, path: {}", self.bucket, path);
+                Ok(())
             },
             Err(e) => {
-                error!("Failed to upload Markdown to S3: {:?}", e);
-                Err(AppError::Storage(format!("Failed to upload Markdown: {}", e)).into())
+                error!("Failed to upload {} to S3: {:?}", content_type, e);
+                Err(e)
             }
         }
     }
 
-    async fn get_object(&self, path: &str) -> Result<String> {
+    async fn get_object_stream(&self, path: &str) -> Result<StoredObject> {
         // Get the object
         let response = self.client
             .get_object()
@@ -139,16 +134,22 @@ impl StorageClient for S3StorageClient {
             .send()
             .await
             .map_err(|e| AppError::Storage(format!("Failed to get object: {}", e)))?;
-        
-        // Read the body
-        let body = response.body.collect().await?;
-        let bytes = body.into_bytes();
-        
-        // Convert to string
-        let content = String::from_utf8(bytes.to_vec())
-            .map_err(|e| AppError::Storage(format!("Failed to convert object to string: {}", e)))?;
-        
-        Ok(content)
+
+        let content_encoding = ContentEncoding::from_header(response.content_encoding())?;
+
+        // Read the body as it arrives
+        let body = stream::unfold(response.body, |mut body| async move {
+            body.try_next()
+                .await
+                .map_err(std::io::Error::other)
+                .transpose()
+                .map(|chunk| (chunk, body))
+        });
+
+        Ok(StoredObject {
+            body: body.boxed(),
+            content_encoding,
+        })
     }
 
     async fn delete_object(&self, path: &str) -> Result<()> {
@@ -163,4 +164,112 @@ impl StorageClient for S3StorageClient {
         
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+impl S3StorageClient {
+    /// Upload a body too large for one request in parts, aborting the upload if any part fails
+    async fn upload_multipart(
+        &self,
+        path: &str,
+        first_part: Bytes,
+        mut body: ObjectStream,
+        content_type: &str,
+        encoding: Option<ContentEncoding>,
+    ) -> Result<()> {
+        let upload = self.client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(path)
+            .content_type(content_type)
+            .set_content_encoding(encoding.map(|encoding| encoding.as_str().to_string()))
+            .send()
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to start the upload of {}: {}", path, e)))?;
+
+        let upload_id = upload
+            .upload_id()
+            .ok_or_else(|| AppError::Storage(format!("No upload id for {}", path)))?
+            .to_string();
+
+        let mut parts = Vec::new();
+        let mut part = first_part;
+        let mut finished = false;
+
+        let result: Result<()> = async {
+            loop {
+                let part_number = parts.len() as i32 + 1;
+
+                let uploaded = self.client
+                    .upload_part()
+                    .bucket(&self.bucket)
+                    .key(path)
+                    .upload_id(&upload_id)
+                    .part_number(part_number)
+                    .body(ByteStream::from(part))
+                    .send()
+                    .await
+                    .map_err(|e| AppError::Storage(format!("Failed to upload part {} of {}: {}", part_number, path, e)))?;
+
+                parts.push(
+                    CompletedPart::builder()
+                        .set_e_tag(uploaded.e_tag().map(str::to_string))
+                        .part_number(part_number)
+                        .build(),
+                );
+
+                if finished {
+                    break;
+                }
+
+                (part, finished) = read_part(&mut body).await?;
+
+                // The body ended exactly at the end of the last part
+                if part.is_empty() {
+                    break;
+                }
+            }
+
+            self.client
+                .complete_multipart_upload()
+                .bucket(&self.bucket)
+                .key(path)
+                .upload_id(&upload_id)
+                .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+                .send()
+                .await
+                .map_err(|e| AppError::Storage(format!("Failed to complete the upload of {}: {}", path, e)))?;
+
+            Ok(())
+        }
+        .await;
+
+        if result.is_err() {
+            if let Err(e) = self.client
+                .abort_multipart_upload()
+                .bucket(&self.bucket)
+                .key(path)
+                .upload_id(&upload_id)
+                .send()
+                .await
+            {
+                error!("Failed to abort the upload of {}: {}", path, e);
+            }
+        }
+
+        result
+    }
+}
+
+/// Read a body until a part is full or the body ends, returning the part and whether the body ended
+async fn read_part(body: &mut ObjectStream) -> Result<(Bytes, bool)> {
+    let mut part = BytesMut::new();
+
+    while part.len() < MULTIPART_PART_SIZE {
+        match body.try_next().await? {
+            Some(chunk) => part.extend_from_slice(&chunk),
+            None => return Ok((part.freeze(), true)),
+        }
+    }
+
+    Ok((part.freeze(), false))
+}
@@ -10,14 +10,21 @@ use tokio::signal;
 use tokio::sync::Mutex;
 use tracing::{info, error};
 
+use crate::application::scheduler::promoter::JobPromoter;
 use crate::application::scheduler::service::SchedulerService;
 use crate::application::scraper::service::ScraperService;
 use crate::application::scraper::worker::ScraperWorker;
 use crate::application::scraper::crawler::CrawlerConfig;
+use crate::application::scraper::reaper::JobReaper;
+use crate::application::storage::janitor::RetentionJanitor;
+use crate::application::storage::migrator::LegacyObjectMigrator;
+use crate::application::webhook::dispatcher::WebhookDispatcher;
+use crate::application::webhook::service::WebhookService;
 use crate::config::settings::AppConfig;
+use crate::domain::scraper_config::CanonicalizationRules;
 use crate::infrastructure::queue::redis_queue::RedisJobQueue;
 use crate::infrastructure::queue::redis_client::RedisClient;
-use crate::infrastructure::storage::s3_client::S3StorageClient;
+use crate::infrastructure::storage::create_storage_client;
 use crate::infrastructure::grpc::markdown_client::MarkdownClient;
 use crate::utils::logging;
 
@@ -41,16 +48,14 @@ async fn main() -> anyhow::Result<()> {
         .await?;
     info!("Database connection established");
     
-    // Initialize S3 storage client
-    let storage_client = Arc::new(S3StorageClient::new(
-        &config.storage
-    ).await?);
-    info!("S3 storage client initialized");
+    // Initialize the storage client of the configured backend
+    let storage_client = create_storage_client(&config.storage).await?;
+    info!("Storage client initialized with the {:?} backend", config.storage.backend);
     
     // Initialize Redis job queue
     let job_queue = Arc::new(RedisJobQueue::new(
         &config.redis
-    ).await?);
+    ).await?.with_visibility_timeout(config.recovery.visibility_timeout_seconds));
     info!("Redis job queue initialized");
     
     // Initialize Redis client
@@ -65,6 +70,10 @@ async fn main() -> anyhow::Result<()> {
     ).await?);
     info!("Markdown client initialized");
     
+    // Initialize webhook service
+    let webhook_service = Arc::new(WebhookService::new(db_pool.clone()));
+    info!("Webhook service initialized");
+    
     // Create crawler configuration
     let crawler_config = CrawlerConfig {
         max_concurrent_requests: config.scraper.max_concurrent_requests as usize,
@@ -73,7 +82,11 @@ async fn main() -> anyhow::Result<()> {
         user_agent: config.scraper.default_user_agent.clone(),
         request_timeout_secs: config.scraper.request_timeout_secs,
         respect_robots_txt: config.scraper.respect_robots_txt,
+        robots_txt_cache_ttl_secs: config.scraper.robots_txt_cache_ttl_secs,
+        max_crawl_delay_ms: config.scraper.max_crawl_delay_ms,
         max_page_size_bytes: config.scraper.max_page_size_bytes,
+        canonicalization: CanonicalizationRules::default(),
+        extraction_rules: Vec::new(),
     };
     
     // Create worker and scheduler with interior mutability
@@ -82,6 +95,9 @@ async fn main() -> anyhow::Result<()> {
         job_queue.clone(),
         storage_client.clone(),
         markdown_client.clone(),
+        webhook_service.clone(),
+        config.recovery.clone(),
+        config.redis.worker_queues(),
         crawler_config,
     ) {
         Ok(worker) => worker,
@@ -96,10 +112,50 @@ async fn main() -> anyhow::Result<()> {
     let scheduler_service = Arc::new(Mutex::new(SchedulerService::new(
         db_pool.clone(),
         job_queue.clone(),
+        webhook_service.clone(),
         config.scheduler.clone(),
     )));
     info!("Scheduler service initialized");
     
+    // Initialize scheduled job promoter
+    let job_promoter = Arc::new(Mutex::new(JobPromoter::new(
+        job_queue.clone(),
+        config.scheduler.clone(),
+    )));
+    info!("Scheduled job promoter initialized");
+    
+    // Initialize webhook dispatcher
+    let webhook_dispatcher = Arc::new(Mutex::new(WebhookDispatcher::new(
+        db_pool.clone(),
+        config.webhooks.clone(),
+    )?));
+    info!("Webhook dispatcher initialized");
+    
+    // Initialize job reaper
+    let job_reaper = Arc::new(Mutex::new(JobReaper::new(
+        db_pool.clone(),
+        job_queue.clone(),
+        webhook_service.clone(),
+        config.recovery.clone(),
+    )));
+    info!("Job reaper initialized");
+    
+    // Initialize legacy object migration
+    let legacy_object_migrator = Arc::new(Mutex::new(LegacyObjectMigrator::new(
+        db_pool.clone(),
+        storage_client.clone(),
+        config.storage.clone(),
+    )));
+    info!("Legacy object migrator initialized");
+    
+    // Initialize retention janitor
+    let retention_janitor = Arc::new(Mutex::new(RetentionJanitor::new(
+        db_pool.clone(),
+        storage_client.clone(),
+        config.retention.clone(),
+    )));
+    info!("Retention janitor initialized");
+    
     // Start the API server
     let api_handle = tokio::spawn(api::routes::serve(
         config.server.port,
@@ -111,6 +167,9 @@ async fn main() -> anyhow::Result<()> {
             job_queue.clone(),
             storage_client.clone(),
             markdown_client.clone(),
+            webhook_service.clone(),
+            config.recovery.clone(),
+            config.redis.worker_queues(),
             CrawlerConfig {
                 max_concurrent_requests: config.scraper.max_concurrent_requests as usize,
                 delay_between_requests_ms: config.scraper.request_delay_ms,
@@ -118,15 +177,25 @@ async fn main() -> anyhow::Result<()> {
                 user_agent: config.scraper.default_user_agent.clone(),
                 request_timeout_secs: config.scraper.request_timeout_secs,
                 respect_robots_txt: config.scraper.respect_robots_txt,
+                robots_txt_cache_ttl_secs: config.scraper.robots_txt_cache_ttl_secs,
+                max_crawl_delay_ms: config.scraper.max_crawl_delay_ms,
                 max_page_size_bytes: config.scraper.max_page_size_bytes,
+                canonicalization: CanonicalizationRules::default(),
+                extraction_rules: Vec::new(),
             }
         ).unwrap()),
         Arc::new(SchedulerService::new(
             db_pool.clone(),
             job_queue.clone(),
+            webhook_service.clone(),
             config.scheduler.clone(),
         )),
         redis_client.clone(),
+        webhook_service.clone(),
+        Arc::new(WebhookDispatcher::new(
+            db_pool.clone(),
+            config.webhooks.clone(),
+        )?),
     ));
     info!("API server started on {}", config.server.address);
     
@@ -147,6 +216,46 @@ async fn main() -> anyhow::Result<()> {
         }
     });
     
+    // Start the scheduled job promoter
+    let promoter_clone = job_promoter.clone();
+    let promoter_handle = tokio::spawn(async move {
+        if let Err(e) = promoter_clone.lock().await.start().await {
+            error!("Scheduled job promoter error: {}", e);
+        }
+    });
+    
+    // Start the webhook dispatcher
+    let dispatcher_clone = webhook_dispatcher.clone();
+    let dispatcher_handle = tokio::spawn(async move {
+        if let Err(e) = dispatcher_clone.lock().await.start().await {
+            error!("Webhook dispatcher error: {}", e);
+        }
+    });
+    
+    // Start the job reaper
+    let reaper_clone = job_reaper.clone();
+    let reaper_handle = tokio::spawn(async move {
+        if let Err(e) = reaper_clone.lock().await.start().await {
+            error!("Job reaper error: {}", e);
+        }
+    });
+    
+    // Start the legacy object migration
+    let migrator_clone = legacy_object_migrator.clone();
+    let migrator_handle = tokio::spawn(async move {
+        if let Err(e) = migrator_clone.lock().await.start().await {
+            error!("Legacy object migration error: {}", e);
+        }
+    });
+    
+    // Start the retention janitor
+    let janitor_clone = retention_janitor.clone();
+    let janitor_handle = tokio::spawn(async move {
+        if let Err(e) = janitor_clone.lock().await.start().await {
+            error!("Retention janitor error: {}", e);
+        }
+    });
+    
     // Wait for shutdown signal
     match signal::ctrl_c().await {
         Ok(()) => {
@@ -156,6 +265,11 @@ async fn main() -> anyhow::Result<()> {
             api_handle.abort();
             worker_handle.abort();
             scheduler_handle.abort();
+            promoter_handle.abort();
+            dispatcher_handle.abort();
+            reaper_handle.abort();
+            migrator_handle.abort();
+            janitor_handle.abort();
             
             info!("All services stopped");
         }
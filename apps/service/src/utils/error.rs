@@ -35,6 +35,10 @@ pub enum AppError {
     #[error("Scraper error: {0}")]
     Scraper(String),
 
+    /// A site the service fetched from on the caller's behalf failed
+    #[error("Upstream error: {0}")]
+    Upstream(String),
+
     #[error("Internal server error: {0}")]
     Internal(String),
 }
@@ -51,6 +55,7 @@ impl IntoResponse for AppError {
             AppError::Storage(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             AppError::MarkdownService(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             AppError::Scraper(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+            AppError::Upstream(msg) => (StatusCode::BAD_GATEWAY, msg),
             AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
         };
 
@@ -67,6 +72,10 @@ impl IntoResponse for AppError {
 
 impl From<anyhow::Error> for AppError {
     fn from(err: anyhow::Error) -> Self {
-        AppError::Internal(err.to_string())
+        // Keep the status of application errors raised by services
+        match err.downcast::<AppError>() {
+            Ok(app_error) => app_error,
+            Err(err) => AppError::Internal(err.to_string()),
+        }
     }
 } 
\ No newline at end of file
@@ -1,2 +1,3 @@
 pub mod error;
-pub mod logging; 
\ No newline at end of file
+pub mod logging;
+pub mod xml; 
\ No newline at end of file